@@ -0,0 +1,73 @@
+//! `backup export` and `backup import`: everything todore keeps, in one `.tar.gz` that can
+//! be carried to another machine.
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use std::collections::BTreeMap;
+use std::io;
+use std::io::Read;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+/// The tasks, including the archive, milestones and sort order, as JSON.
+pub const TASKS: &str = "tasks.json";
+pub const TEMPLATES: &str = "templates.json";
+pub const CONFIG: &str = "config.toml";
+pub const HISTORY: &str = "history.txt";
+
+/// Packs `files`, by name, into a gzipped tarball.
+pub fn pack(files: &BTreeMap<&str, String>) -> io::Result<Vec<u8>> {
+    let mtime = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let mut builder = tar::Builder::new(GzEncoder::new(vec![], flate2::Compression::default()));
+    for (name, contents) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(mtime);
+        header.set_cksum();
+        builder.append_data(&mut header, name, contents.as_bytes())?;
+    }
+    builder.into_inner()?.finish()
+}
+
+/// Every file in a bundle written by [`pack`], by name. Fails unless it holds the tasks.
+pub fn unpack(data: &[u8]) -> io::Result<BTreeMap<String, String>> {
+    let mut archive = tar::Archive::new(GzDecoder::new(data));
+    let mut files = BTreeMap::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let name = entry.path()?.to_string_lossy().into_owned();
+        let mut contents = String::new();
+        entry.read_to_string(&mut contents)?;
+        files.insert(name, contents);
+    }
+    if !files.contains_key(TASKS) {
+        return Err(io::Error::other(
+            "Not a todore backup: it has no tasks.json",
+        ));
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_and_unpack() {
+        let files = BTreeMap::from([
+            (TASKS, r#"{"tasks":[]}"#.to_string()),
+            (CONFIG, "stale_days = 3\n".to_string()),
+        ]);
+        let data = pack(&files).unwrap();
+        let unpacked = unpack(&data).unwrap();
+        assert_eq!(unpacked.len(), 2);
+        assert_eq!(unpacked[CONFIG], "stale_days = 3\n");
+
+        let no_tasks = pack(&BTreeMap::from([(CONFIG, String::new())])).unwrap();
+        assert!(unpack(&no_tasks).is_err());
+        assert!(unpack(b"not a tarball").is_err());
+    }
+}
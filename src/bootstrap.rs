@@ -0,0 +1,386 @@
+//! Finding the task file at startup, and setting one up on first run.
+
+use crate::Format;
+use crate::TaskList;
+use crate::storage::Storage;
+use directories::ProjectDirs;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// The platform's data directory, e.g. `~/.local/share/todore` on Linux,
+/// `~/Library/Application Support/todore` on macOS and `%APPDATA%\todore\data` on
+/// Windows. Falls back to the working directory when there is no home directory.
+fn data_dir() -> PathBuf {
+    ProjectDirs::from("", "", "todore")
+        .map(|dirs| dirs.data_dir().to_path_buf())
+        .unwrap_or_default()
+}
+
+/// Where tasks are kept unless `--file`, `TODORE_FILE` or the config say otherwise:
+/// `tasks.json` in the data directory, or in the profile's directory with `--profile`.
+pub fn default_file(profile: Option<&str>) -> PathBuf {
+    match profile {
+        Some(name) => profile_dir(name),
+        None => data_dir(),
+    }
+    .join("tasks.json")
+}
+
+/// Where a profile keeps everything: its tasks, `config.toml`, templates and prompt
+/// history. Without a profile those other files live in the working directory.
+pub fn profile_dir(name: &str) -> PathBuf {
+    data_dir().join("profiles").join(name)
+}
+
+/// Where `config.toml`, templates and prompt history are kept: the profile's directory,
+/// or the working directory without a profile.
+pub fn settings_dir(profile: Option<&str>) -> PathBuf {
+    profile.map(profile_dir).unwrap_or_default()
+}
+
+/// The config.toml the first run records the chosen task file in: the profile's, or
+/// the one in the platform's config directory, e.g. `~/.config/todore` on Linux, so
+/// the choice holds whichever directory todore is started from.
+pub fn user_config(profile: Option<&str>) -> PathBuf {
+    match profile {
+        Some(name) => profile_dir(name),
+        None => ProjectDirs::from("", "", "todore")
+            .map(|dirs| dirs.config_dir().to_path_buf())
+            .unwrap_or_default(),
+    }
+    .join("config.toml")
+}
+
+/// The task file recorded as `file` in the config at `path`, if any.
+pub fn recorded_file(path: &Path) -> Option<PathBuf> {
+    let table: toml::Table = toml::from_str(&fs::read_to_string(path).ok()?).ok()?;
+    table.get("file")?.as_str().map(PathBuf::from)
+}
+
+/// Checks a `--profile` name, which becomes a directory name.
+pub fn check_profile(name: &str) -> Result<(), String> {
+    let valid = |c: char| c.is_alphanumeric() || c == '-' || c == '_';
+    if name.is_empty() || !name.chars().all(valid) {
+        return Err(format!(
+            "Invalid profile name {}, use letters, digits, - and _",
+            name
+        ));
+    }
+    Ok(())
+}
+
+/// The names of every profile created so far, sorted.
+pub fn profiles() -> io::Result<Vec<String>> {
+    let dir = data_dir().join("profiles");
+    if !dir.exists() {
+        return Ok(vec![]);
+    }
+    let mut names = vec![];
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            names.push(entry.file_name().to_string_lossy().into_owned());
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+/// Moves a task file kept in the working directory by older versions to `file`, unless
+/// `file` already exists or `legacy` isn't a todore task list, as another program's
+/// `tasks.json` wouldn't be. `tell` is called before anything is moved. Returns whether
+/// anything was moved.
+pub fn migrate(legacy: &Path, file: &Path, tell: impl FnOnce()) -> io::Result<bool> {
+    if !legacy.is_file() || file.exists() || legacy == file {
+        return Ok(false);
+    }
+    let is_task_list = fs::read_to_string(legacy)
+        .is_ok_and(|text| serde_json::from_str::<TaskList>(&text).is_ok());
+    if !is_task_list {
+        return Ok(false);
+    }
+    tell();
+    if let Some(dir) = file.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    // A rename can't cross file systems, so fall back to copying.
+    if fs::rename(legacy, file).is_err() {
+        fs::copy(legacy, file)?;
+        fs::remove_file(legacy)?;
+    }
+    Ok(true)
+}
+
+/// Reads the task list in `storage`. A missing file gives an empty list, which is
+/// written out straight away when `create` is set so later saves have a directory to go to.
+/// A damaged one is rebuilt with [`recover`]. Also returns notes for the user about
+/// anything amiss.
+pub fn load(
+    storage: &dyn Storage,
+    create: bool,
+) -> Result<(TaskList, Vec<String>), Box<dyn std::error::Error>> {
+    let mut notes = vec![];
+    let mut tasks = match storage.load() {
+        Ok(tasks) => {
+            if let Err(warning) = storage.check() {
+                notes.push(format!("{}; using it as it is", warning));
+            }
+            tasks
+        }
+        Err(err) => {
+            let (tasks, report) = recover(storage)
+                .map_err(|reason| format!("The tasks can't be read ({}) and {}", err, reason))?;
+            notes.push(format!(
+                "The tasks can't be read ({}); {}. They are saved over the damaged copy \
+                 with the next change.",
+                err, report
+            ));
+            tasks
+        }
+    };
+    for id in tasks.break_cycles() {
+        notes.push(format!(
+            "Task {} was its own ancestor, so its parent was cleared",
+            id
+        ));
+    }
+    if create && !storage.exists() {
+        storage.save(&tasks)?;
+    }
+    Ok((tasks, notes))
+}
+
+/// Rebuilds the tasks of a damaged store from the newest good copy it has, the one kept
+/// from before the last save or a snapshot, and the journal entries made since. Without
+/// a copy the whole journal is replayed. Returns the tasks and what they came from.
+pub fn recover(storage: &dyn Storage) -> Result<(TaskList, String), String> {
+    let mut copies = vec![];
+    if let Ok(Some((tasks, at))) = storage.last_good() {
+        copies.push((tasks, at, "the copy from before the last save".to_string()));
+    }
+    for name in storage.snapshots().unwrap_or_default() {
+        if let Ok(snapshot) = storage.load_snapshot(&name) {
+            copies.push((
+                snapshot.tasks,
+                snapshot.created,
+                format!("snapshot {}", name),
+            ));
+        }
+    }
+    let events = storage.events().unwrap_or_default();
+    let newest = copies.into_iter().max_by_key(|(_, at, _)| *at);
+    let (mut tasks, since, source) = match newest {
+        Some((tasks, at, source)) => (tasks, Some(at), source),
+        None if !events.is_empty() => (TaskList::new(), None, "an empty list".to_string()),
+        None => return Err("there is no backup, snapshot or journal to recover from".into()),
+    };
+    let replayed: Vec<_> = events
+        .iter()
+        .filter(|event| since.is_none_or(|since| event.at > since))
+        .collect();
+    for event in &replayed {
+        event.apply(&mut tasks);
+    }
+    let report = format!(
+        "recovered {} task(s) from {} and {} journal entries",
+        tasks.tasks.len(),
+        source,
+        replayed.len()
+    );
+    Ok((tasks, report))
+}
+
+/// Asks where to keep tasks, offering `default`, and whether to start from an existing
+/// export. Returns the chosen file and the imported tasks, if a new file should start
+/// with them; saving them is left to the caller, which knows how the file is stored.
+pub fn first_run(
+    default: &Path,
+    mut ask: impl FnMut(&str) -> io::Result<String>,
+) -> Result<(PathBuf, Option<TaskList>), Box<dyn std::error::Error>> {
+    let answer = ask(&format!(
+        "No task file yet. Where should tasks be kept? [{}] ",
+        default.display()
+    ))?;
+    let file = match answer.trim() {
+        "" => default.to_path_buf(),
+        path => PathBuf::from(path),
+    };
+    if file.exists() {
+        return Ok((file, None));
+    }
+    let answer = ask(
+        "Import tasks from an existing json, yaml, toml or txt file? [path, or Enter to skip] ",
+    )?;
+    let tasks = match answer.trim() {
+        "" => None,
+        path => Some(Format::of_path(Path::new(path)).parse(&fs::read_to_string(path)?)?),
+    };
+    Ok((file, tasks))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Task;
+    use crate::storage::Event;
+    use crate::storage::FileStorage;
+    use chrono::Local;
+    use std::env;
+    use std::process;
+
+    #[test]
+    fn test_first_run_imports_and_creates() {
+        let dir = env::temp_dir().join(format!("todore-first-run-{}", process::id()));
+        let export = env::temp_dir().join(format!("todore-first-run-{}.yaml", process::id()));
+        let mut old = TaskList::new();
+        old.add(Task::new(4, "Buy milk".to_string()));
+        fs::write(&export, serde_yaml::to_string(&old).unwrap()).unwrap();
+
+        let mut answers = vec![String::new(), export.display().to_string()].into_iter();
+        let default = dir.join("todore").join("tasks.json");
+        let (file, tasks) = first_run(&default, |_| Ok(answers.next().unwrap())).unwrap();
+        assert_eq!(file, default);
+        assert_eq!(tasks.unwrap().tasks[0].description, "Buy milk");
+        assert!(!file.exists());
+
+        // A location that already holds tasks is used as it is.
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("old.json"), "{}").unwrap();
+        let (file, tasks) = first_run(&dir.join("other.json"), |_| {
+            Ok(dir.join("old.json").display().to_string())
+        })
+        .unwrap();
+        assert_eq!(file, dir.join("old.json"));
+        assert!(tasks.is_none());
+
+        // The choice is recorded where any later run finds it.
+        let config = dir.join("config.toml");
+        assert_eq!(recorded_file(&config), None);
+        crate::config::record(config.to_str().unwrap(), "file", "/tmp/todo.json").unwrap();
+        assert_eq!(
+            recorded_file(&config),
+            Some(PathBuf::from("/tmp/todo.json"))
+        );
+        assert!(user_config(None).ends_with("config.toml"));
+        assert!(user_config(Some("work")).ends_with("profiles/work/config.toml"));
+        fs::remove_dir_all(&dir).unwrap();
+        fs::remove_file(&export).unwrap();
+    }
+
+    #[test]
+    fn test_default_file_and_migrate() {
+        assert!(default_file(None).ends_with("tasks.json"));
+        assert!(default_file(Some("work")).ends_with("profiles/work/tasks.json"));
+        assert!(check_profile("work-2").is_ok());
+        assert!(check_profile("../work").is_err());
+
+        let dir = env::temp_dir().join(format!("todore-migrate-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let legacy = dir.join("tasks.json");
+        let file = dir.join("data").join("tasks.json");
+        // Another program's tasks.json stays where it is.
+        fs::write(
+            &legacy,
+            r#"{"version": 2, "tasks": {"build": "cargo build"}}"#,
+        )
+        .unwrap();
+        assert!(!migrate(&legacy, &file, || panic!("nothing to tell")).unwrap());
+        assert!(legacy.exists());
+
+        fs::write(&legacy, r#"{"tasks":[]}"#).unwrap();
+        let mut told = false;
+        assert!(migrate(&legacy, &file, || told = true).unwrap());
+        assert!(told);
+        assert!(!legacy.exists());
+        assert!(
+            load(&FileStorage::new(&file), false)
+                .unwrap()
+                .0
+                .tasks
+                .is_empty()
+        );
+
+        fs::write(&legacy, r#"{"tasks":[]}"#).unwrap();
+        assert!(!migrate(&legacy, &file, || {}).unwrap());
+        assert!(legacy.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_load_recovers_damaged_file() {
+        let dir = env::temp_dir().join(format!("todore-recover-{}", process::id()));
+        let file = dir.join("tasks.json");
+        let storage = FileStorage::new(&file);
+        let mut tasks = TaskList::new();
+        tasks.add(Task::new(0, "Buy milk".to_string()));
+        storage.save(&tasks).unwrap();
+        tasks.add(Task::new(1, "Eggs".to_string()));
+        storage.save(&tasks).unwrap();
+        // A change made after the last save, only in the journal.
+        let mut call = Task::new(2, "Call mom".to_string());
+        call.touched = Some(Local::now());
+        storage
+            .append_event(&Event {
+                at: Local::now(),
+                command: "add Call mom".to_string(),
+                changes: [(2, Some(call))].into(),
+            })
+            .unwrap();
+
+        // A hand edit that still parses is used, with a warning.
+        fs::write(&file, r#"{"tasks":[]}"#).unwrap();
+        let (loaded, notes) = load(&storage, false).unwrap();
+        assert!(loaded.tasks.is_empty());
+        assert!(notes[0].contains("doesn't match the checksum"));
+
+        fs::write(&file, r#"{"tasks":[{"id":0,"#).unwrap();
+        let (loaded, notes) = load(&storage, false).unwrap();
+        let ids: Vec<u32> = loaded.tasks.iter().map(|task| task.id).collect();
+        assert_eq!(ids, vec![0, 2]);
+        assert!(notes[0].contains("from the copy from before the last save and 1 journal"));
+
+        fs::remove_dir_all(&dir).unwrap();
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&file, "garbage").unwrap();
+        assert!(load(&storage, false).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_load_missing_file() {
+        let file = env::temp_dir()
+            .join(format!("todore-load-{}", process::id()))
+            .join("t.json");
+        let storage = FileStorage::new(&file);
+        assert!(load(&storage, false).unwrap().0.tasks.is_empty());
+        assert!(!file.exists());
+        assert!(load(&storage, true).unwrap().0.tasks.is_empty());
+        assert!(file.exists());
+        fs::remove_dir_all(file.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_load_clears_parent_cycles() {
+        let file = env::temp_dir()
+            .join(format!("todore-cycle-{}", process::id()))
+            .join("t.json");
+        let storage = FileStorage::new(&file);
+        let mut tasks = TaskList::new();
+        tasks.add(Task::new(0, "Egg".to_string()));
+        tasks.add(Task::new(1, "Chicken".to_string()));
+        tasks.tasks[0].parent = Some(1);
+        tasks.tasks[1].parent = Some(0);
+        storage.save(&tasks).unwrap();
+        let (loaded, notes) = load(&storage, false).unwrap();
+        assert_eq!(loaded.tasks[0].parent, None);
+        assert_eq!(loaded.tasks[1].parent, Some(0));
+        assert_eq!(loaded.progress(&loaded.tasks[0]), Some(0));
+        assert_eq!(
+            notes,
+            vec!["Task 0 was its own ancestor, so its parent was cleared"]
+        );
+        fs::remove_dir_all(file.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,194 @@
+use crate::TaskList;
+use crate::help;
+use rustyline::Context;
+use rustyline::Helper;
+use rustyline::completion::Completer;
+use rustyline::completion::Pair;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use std::collections::BTreeSet;
+
+const FIELDS: &[&str] = &[
+    "description",
+    "status",
+    "due",
+    "scheduled",
+    "waiting-on",
+    "points",
+    "sprint",
+    "milestone",
+    "progress",
+    "parent",
+    "recur",
+    "energy",
+    "location",
+    "color",
+    "icon",
+    "url",
+    "tags",
+    "project",
+    "priority",
+];
+
+const STATUSES: &[&str] = &["not-started", "in-progress", "waiting", "completed"];
+
+/// Completes command names, task ids, tags, contexts, update fields and status values.
+/// Call [`TodoreHelper::refresh`] before each prompt so candidates follow the live task list.
+#[derive(Debug, Default)]
+pub struct TodoreHelper {
+    tasks: Vec<(u32, String)>,
+    tags: BTreeSet<String>,
+    contexts: BTreeSet<String>,
+}
+
+impl TodoreHelper {
+    pub fn refresh(&mut self, tasks: &TaskList) {
+        self.tasks = tasks
+            .tasks
+            .iter()
+            .map(|task| (task.id, task.description.clone()))
+            .collect();
+        self.tags = tasks
+            .tasks
+            .iter()
+            .flat_map(|task| task.tags.clone())
+            .collect();
+        self.contexts = tasks
+            .tasks
+            .iter()
+            .filter_map(|task| task.context.clone())
+            .collect();
+    }
+
+    /// Candidates for the word ending at the end of `line`, with the byte offset where that word starts.
+    fn candidates(&self, line: &str) -> (usize, Vec<Pair>) {
+        let start = line.rfind(' ').map_or(0, |space| space + 1);
+        let word = &line[start..];
+        let previous: Vec<&str> = line[..start].split_whitespace().collect();
+        let words = |options: &mut dyn Iterator<Item = String>| -> Vec<Pair> {
+            options
+                .filter(|option| option.starts_with(word))
+                .map(|option| Pair {
+                    display: option.clone(),
+                    replacement: option,
+                })
+                .collect()
+        };
+        let pairs = match previous.as_slice() {
+            [] => words(
+                &mut help::COMMANDS
+                    .iter()
+                    .flat_map(|spec| spec.names)
+                    .map(|name| name.to_string()),
+            ),
+            _ if word.starts_with('+') => {
+                words(&mut self.tags.iter().map(|tag| format!("+{}", tag)))
+            }
+            _ if word.starts_with('@') => {
+                words(&mut self.contexts.iter().map(|context| format!("@{}", context)))
+            }
+            [.., "--tag"] => words(&mut self.tags.iter().cloned()),
+            [.., "--status"] | ["u" | "update", _, "s" | "status"] => {
+                words(&mut STATUSES.iter().map(|status| status.to_string()))
+            }
+            ["u" | "update", _] => words(&mut FIELDS.iter().map(|field| field.to_string())),
+            [command] if help::takes_task_id(command) => self
+                .tasks
+                .iter()
+                .filter(|(id, _)| id.to_string().starts_with(word))
+                .map(|(id, description)| Pair {
+                    display: format!("{} {}", id, description),
+                    replacement: id.to_string(),
+                })
+                .collect(),
+            _ => vec![],
+        };
+        (start, pairs)
+    }
+}
+
+impl Completer for TodoreHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        Ok(self.candidates(&line[..pos]))
+    }
+}
+
+impl Hinter for TodoreHelper {
+    type Hint = String;
+}
+
+impl Highlighter for TodoreHelper {}
+
+impl Validator for TodoreHelper {}
+
+impl Helper for TodoreHelper {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Task;
+
+    fn helper() -> TodoreHelper {
+        let mut tasks = TaskList::new();
+        let mut milk = Task::new(1, "Buy milk".to_string());
+        milk.tags = vec!["home".to_string(), "errand".to_string()];
+        milk.context = Some("shop".to_string());
+        tasks.add(milk);
+        tasks.add(Task::new(12, "Call mom".to_string()));
+        let mut helper = TodoreHelper::default();
+        helper.refresh(&tasks);
+        helper
+    }
+
+    fn replacements(helper: &TodoreHelper, line: &str) -> Vec<String> {
+        helper
+            .candidates(line)
+            .1
+            .into_iter()
+            .map(|pair| pair.replacement)
+            .collect()
+    }
+
+    #[test]
+    fn test_complete_command_names() {
+        let helper = helper();
+        assert_eq!(replacements(&helper, "und"), vec!["undo"]);
+        assert_eq!(
+            replacements(&helper, "rem"),
+            vec!["remove", "remind", "reminders"]
+        );
+    }
+
+    #[test]
+    fn test_complete_task_ids_tags_and_contexts() {
+        let helper = helper();
+        assert_eq!(replacements(&helper, "done 1"), vec!["1", "12"]);
+        assert_eq!(helper.candidates("done 1").1[1].display, "12 Call mom");
+        assert_eq!(replacements(&helper, "add x +h"), vec!["+home"]);
+        assert_eq!(replacements(&helper, "list @"), vec!["@shop"]);
+        assert_eq!(replacements(&helper, "list --tag e"), vec!["errand"]);
+        assert_eq!(helper.candidates("add x +h").0, 6);
+    }
+
+    #[test]
+    fn test_complete_update_fields_and_statuses() {
+        let helper = helper();
+        assert_eq!(
+            replacements(&helper, "u 1 pr"),
+            vec!["progress", "project", "priority"]
+        );
+        assert_eq!(
+            replacements(&helper, "update 1 status in"),
+            vec!["in-progress"]
+        );
+        assert!(replacements(&helper, "find milk ").is_empty());
+    }
+}
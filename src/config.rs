@@ -0,0 +1,263 @@
+//! Settings come from three layers, each overriding the one before:
+//!
+//! 1. built-in defaults,
+//! 2. `config.toml`,
+//! 3. `TODORE_*` environment variables, e.g. `TODORE_STALE_DAYS=30`.
+//!
+//! Command-line flags such as `--format` and `--no-color` override all of them.
+
+use crate::Format;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// The variables read by [`Config::apply_env`] and at startup, for `--help` and the man page.
+/// `{formats}` in a description stands for the registered formats.
+pub const ENVIRONMENT: &[(&str, &str)] = &[
+    (
+        "TODORE_STALE_DAYS",
+        "Days without changes before a task counts as stale",
+    ),
+    (
+        "TODORE_AUTO_LIST",
+        "Print the default listing before every prompt",
+    ),
+    ("TODORE_CONFIRM", "Ask before destructive commands"),
+    (
+        "TODORE_WIP_LIMIT",
+        "The most tasks that may be In Progress at once, or none",
+    ),
+    ("TODORE_FORMAT", "How listings are printed: {formats}"),
+    (
+        "TODORE_PRETTY_PLAINTEXT",
+        "Print plaintext with status symbols instead of id: description lines",
+    ),
+    ("TODORE_PROJECT", "The project new tasks are added to"),
+    ("TODORE_NO_COLOR", "Never colour output"),
+    ("TODORE_AUTOSAVE", "Save the task file after every change"),
+    ("TODORE_ENCRYPT", "Encrypt the task file with a passphrase"),
+    (
+        "TODORE_REMOTE_TOKEN",
+        "A bearer token sent when the task file is an http(s) URL",
+    ),
+    (
+        "TODORE_S3_ENDPOINT",
+        "The S3-compatible server for s3:// task files, instead of AWS",
+    ),
+    (
+        "TODORE_KEY_FILE",
+        "A file whose contents are used instead of a passphrase",
+    ),
+    (
+        "TODORE_PASSPHRASE",
+        "The passphrase for an encrypted task file, instead of asking",
+    ),
+];
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// The task file, chosen on first run; `--file` and `TODORE_FILE` take precedence.
+    pub file: Option<PathBuf>,
+    pub stale_days: u32,
+    /// Print the default listing before every prompt.
+    pub auto_list: bool,
+    /// Ask before destructive commands unless they are given `--force`.
+    pub confirm: bool,
+    /// User-defined commands, e.g. `t = "update $1 status in-progress"`.
+    pub aliases: BTreeMap<String, String>,
+    /// The most tasks that may be In Progress at once.
+    pub wip_limit: Option<usize>,
+    /// How listings are printed when `--format` isn't given.
+    pub format: Option<Format>,
+    /// Write plaintext as `☐ 3 Buy milk ⚑ ⏰ 2025-03-01` lines, with symbols for the
+    /// status, priority and overdue dates. `import` can't read them back.
+    pub pretty_plaintext: bool,
+    /// Lay out each plaintext line with this string instead, e.g.
+    /// `"{id} [{status:short}] {description} (due {due})"`; see `help export`.
+    pub plaintext_format: Option<String>,
+    /// The project new tasks are added to.
+    pub project: Option<String>,
+    /// Never colour output, like `--no-color`.
+    pub no_color: bool,
+    /// Save the task file after commands that change it.
+    pub autosave: bool,
+    /// Saves happen at most this often; changes made in between are saved by the next
+    /// command after the delay, or on exit.
+    pub autosave_delay_ms: u64,
+    /// While the prompt waits, changes that aren't saved yet are saved in the background
+    /// after this many seconds, so a crash doesn't lose them. 0 turns this off.
+    pub autosave_interval_secs: u64,
+    /// Shell commands run with the affected task as JSON on stdin, keyed by `on-add`,
+    /// `on-done` or `on-export`.
+    pub hooks: BTreeMap<String, String>,
+    /// Encrypt the task file, its journal and snapshots, asking for a passphrase at
+    /// startup. An encrypted file is read either way.
+    pub encrypt: bool,
+    /// Use this file's contents as the secret instead of asking for a passphrase.
+    pub key_file: Option<PathBuf>,
+    /// Where `s3://bucket/key` task files live, e.g. `http://localhost:9000` for MinIO.
+    /// AWS S3 in `AWS_REGION` when unset.
+    pub s3_endpoint: Option<String>,
+    /// How `import jira` maps statuses, priorities and columns; see the jira module.
+    pub jira_mapping: Option<PathBuf>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            file: None,
+            stale_days: 14,
+            auto_list: false,
+            confirm: true,
+            aliases: BTreeMap::new(),
+            wip_limit: None,
+            format: None,
+            pretty_plaintext: false,
+            plaintext_format: None,
+            project: None,
+            no_color: false,
+            autosave: true,
+            autosave_delay_ms: 1000,
+            autosave_interval_secs: 30,
+            hooks: BTreeMap::new(),
+            encrypt: false,
+            key_file: None,
+            s3_endpoint: None,
+            jira_mapping: None,
+        }
+    }
+}
+
+impl Config {
+    /// Reads `path`, if it exists, and applies the environment on top.
+    pub fn load(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut config = if Path::new(path).exists() {
+            toml::from_str(&fs::read_to_string(path)?)?
+        } else {
+            Self::default()
+        };
+        config.apply_env(|name| env::var(name).ok())?;
+        Ok(config)
+    }
+
+    /// Overrides settings from `TODORE_*` variables looked up with `var`. Aliases can
+    /// only be set in the file.
+    pub fn apply_env(&mut self, var: impl Fn(&str) -> Option<String>) -> Result<(), String> {
+        let var = |name: &str| var(name).filter(|value| !value.is_empty());
+        let invalid = |name: &str, value: &str| format!("Invalid value {} for {}", value, name);
+        if let Some(value) = var("TODORE_STALE_DAYS") {
+            self.stale_days = value
+                .parse()
+                .map_err(|_| invalid("TODORE_STALE_DAYS", &value))?;
+        }
+        if let Some(value) = var("TODORE_WIP_LIMIT") {
+            self.wip_limit = match value.as_str() {
+                "none" => None,
+                _ => Some(
+                    value
+                        .parse()
+                        .map_err(|_| invalid("TODORE_WIP_LIMIT", &value))?,
+                ),
+            };
+        }
+        if let Some(value) = var("TODORE_FORMAT") {
+            self.format = Some(Format::from_str(&value.to_lowercase())?);
+        }
+        if let Some(value) = var("TODORE_PROJECT") {
+            self.project = Some(value);
+        }
+        if let Some(value) = var("TODORE_S3_ENDPOINT") {
+            self.s3_endpoint = Some(value);
+        }
+        if let Some(value) = var("TODORE_KEY_FILE") {
+            self.key_file = Some(PathBuf::from(value));
+        }
+        for (name, setting) in [
+            ("TODORE_AUTO_LIST", &mut self.auto_list),
+            ("TODORE_CONFIRM", &mut self.confirm),
+            ("TODORE_NO_COLOR", &mut self.no_color),
+            ("TODORE_AUTOSAVE", &mut self.autosave),
+            ("TODORE_ENCRYPT", &mut self.encrypt),
+            ("TODORE_PRETTY_PLAINTEXT", &mut self.pretty_plaintext),
+        ] {
+            if let Some(value) = var(name) {
+                *setting = parse_bool(&value).ok_or_else(|| invalid(name, &value))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Sets one top-level `key` in the config file at `path`, creating the file if needed
+/// and keeping the other settings.
+pub fn record(path: &str, key: &str, value: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let mut table: toml::Table = if Path::new(path).exists() {
+        toml::from_str(&fs::read_to_string(path)?)?
+    } else {
+        toml::Table::new()
+    };
+    table.insert(key.to_string(), toml::Value::String(value.to_string()));
+    fs::write(path, toml::to_string(&table)?)?;
+    Ok(())
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "0" | "false" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_env(vars: &[(&str, &str)]) -> Result<Config, String> {
+        let mut config: Config = toml::from_str("stale_days = 30\nformat = \"yaml\"").unwrap();
+        config.apply_env(|name| {
+            vars.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| value.to_string())
+        })?;
+        Ok(config)
+    }
+
+    #[test]
+    fn test_env_overrides_file() {
+        let config = with_env(&[]).unwrap();
+        assert_eq!(config.stale_days, 30);
+        assert_eq!(config.format, Some(Format::YAML));
+
+        let config = with_env(&[
+            ("TODORE_STALE_DAYS", "7"),
+            ("TODORE_FORMAT", "CSV"),
+            ("TODORE_PROJECT", "web"),
+            ("TODORE_NO_COLOR", "1"),
+            ("TODORE_CONFIRM", "off"),
+            ("TODORE_WIP_LIMIT", "3"),
+            ("TODORE_AUTO_LIST", ""),
+        ])
+        .unwrap();
+        assert_eq!(config.stale_days, 7);
+        assert_eq!(config.format, Some(Format("csv")));
+        assert_eq!(config.project.as_deref(), Some("web"));
+        assert!(config.no_color);
+        assert!(!config.confirm);
+        assert_eq!(config.wip_limit, Some(3));
+        assert!(!config.auto_list);
+    }
+
+    #[test]
+    fn test_env_rejects_invalid_values() {
+        assert!(with_env(&[("TODORE_STALE_DAYS", "soon")]).is_err());
+        assert!(with_env(&[("TODORE_CONFIRM", "maybe")]).is_err());
+        assert!(with_env(&[("TODORE_FORMAT", "pdf")]).is_err());
+    }
+}
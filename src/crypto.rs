@@ -0,0 +1,145 @@
+//! Encryption at rest. Data is sealed with ChaCha20-Poly1305 under a key derived from a
+//! passphrase or key file with Argon2, and laid out as [`MAGIC`], the 16 byte salt, the
+//! 12 byte nonce and the ciphertext, so a sealed file says how to open it.
+
+use argon2::Argon2;
+use chacha20poly1305::ChaCha20Poly1305;
+use chacha20poly1305::Key;
+use chacha20poly1305::Nonce;
+use chacha20poly1305::aead::Aead;
+use chacha20poly1305::aead::Generate;
+use chacha20poly1305::aead::KeyInit;
+use std::cell::RefCell;
+use std::env;
+use std::fs;
+use std::io;
+use std::io::IsTerminal;
+use std::path::Path;
+
+/// How every sealed file starts.
+pub const MAGIC: &[u8] = b"todore-encrypted-1\n";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// Whether `data` was written by [`Cipher::seal`].
+pub fn is_sealed(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// Seals and opens data with one secret. Deriving a key is deliberately slow, so keys
+/// are kept for every salt seen and new data is always sealed under the same salt.
+pub struct Cipher {
+    secret: Vec<u8>,
+    salt: [u8; SALT_LEN],
+    keys: RefCell<Vec<([u8; SALT_LEN], Key)>>,
+}
+
+impl Cipher {
+    pub fn new(secret: impl Into<Vec<u8>>) -> Self {
+        Cipher {
+            secret: secret.into(),
+            salt: <[u8; SALT_LEN]>::generate(),
+            keys: RefCell::new(vec![]),
+        }
+    }
+
+    fn cipher(&self, salt: [u8; SALT_LEN]) -> Result<ChaCha20Poly1305, String> {
+        let mut keys = self.keys.borrow_mut();
+        if let Some((_, key)) = keys.iter().find(|(known, _)| *known == salt) {
+            return Ok(ChaCha20Poly1305::new(key));
+        }
+        let mut key = Key::default();
+        Argon2::default()
+            .hash_password_into(&self.secret, &salt, &mut key)
+            .map_err(|err| format!("Can't derive a key: {}", err))?;
+        keys.push((salt, key));
+        Ok(ChaCha20Poly1305::new(&key))
+    }
+
+    pub fn seal(&self, plaintext: &[u8]) -> Result<Vec<u8>, String> {
+        let nonce = Nonce::generate();
+        let ciphertext = self
+            .cipher(self.salt)?
+            .encrypt(&nonce, plaintext)
+            .map_err(|_| "Can't encrypt".to_string())?;
+        Ok([MAGIC, &self.salt, &nonce, &ciphertext].concat())
+    }
+
+    pub fn open(&self, data: &[u8]) -> Result<Vec<u8>, String> {
+        let body = data
+            .strip_prefix(MAGIC)
+            .filter(|body| body.len() >= SALT_LEN + NONCE_LEN)
+            .ok_or("Not an encrypted todore file")?;
+        let (salt, body) = body.split_at(SALT_LEN);
+        let (nonce, ciphertext) = body.split_at(NONCE_LEN);
+        self.cipher(salt.try_into().unwrap())?
+            .decrypt(&Nonce::try_from(nonce).unwrap(), ciphertext)
+            .map_err(|_| "Wrong passphrase or key file, or the file is damaged".to_string())
+    }
+}
+
+/// The secret for an encrypted task file: the contents of `key_file`, else
+/// `TODORE_PASSPHRASE`, else a passphrase typed at the terminal, asked twice when `new`.
+pub fn secret(key_file: Option<&Path>, new: bool) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    if let Some(key_file) = key_file {
+        let key = fs::read(key_file)
+            .map_err(|err| format!("Can't read key file {}: {}", key_file.display(), err))?;
+        return Ok(key.trim_ascii_end().to_vec());
+    }
+    if let Some(passphrase) = env::var("TODORE_PASSPHRASE").ok().filter(|p| !p.is_empty()) {
+        return Ok(passphrase.into_bytes());
+    }
+    if !io::stdin().is_terminal() {
+        return Err(
+            "An encrypted task file needs key_file or TODORE_PASSPHRASE when not at a terminal"
+                .into(),
+        );
+    }
+    let passphrase = rpassword::prompt_password("Passphrase: ")?;
+    if passphrase.is_empty() {
+        return Err("The passphrase can't be empty".into());
+    }
+    if new && rpassword::prompt_password("Repeat passphrase: ")? != passphrase {
+        return Err("The passphrases don't match".into());
+    }
+    Ok(passphrase.into_bytes())
+}
+
+/// Lowercase hex, for sealed journal lines.
+pub fn to_hex(data: &[u8]) -> String {
+    data.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+pub fn from_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.is_ascii() || !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_and_open() {
+        let cipher = Cipher::new("correct horse");
+        let sealed = cipher.seal(b"Buy milk").unwrap();
+        assert!(is_sealed(&sealed));
+        assert_ne!(sealed, cipher.seal(b"Buy milk").unwrap());
+        assert_eq!(cipher.open(&sealed).unwrap(), b"Buy milk");
+        // Another cipher with the same secret has its own salt but can still open it.
+        assert_eq!(
+            Cipher::new("correct horse").open(&sealed).unwrap(),
+            b"Buy milk"
+        );
+        let err = Cipher::new("wrong").open(&sealed).unwrap_err();
+        assert!(err.contains("Wrong passphrase"));
+        assert!(cipher.open(b"{\"tasks\":[]}").is_err());
+        assert_eq!(from_hex(&to_hex(&sealed)), Some(sealed));
+        assert_eq!(from_hex("zz"), None);
+    }
+}
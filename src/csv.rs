@@ -0,0 +1,100 @@
+//! Reading CSV files exported by other apps, for the importers that take them.
+
+/// A CSV file: its header row and the rows below it. Columns are found by header name,
+/// ignoring case, and a name may head several columns, as Jira's `Labels` does.
+pub struct Table {
+    header: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut records = records(text.trim_start_matches('\u{feff}'))?.into_iter();
+        let header = records.next().ok_or("The CSV file is empty")?;
+        Ok(Table {
+            header: header
+                .iter()
+                .map(|name| name.trim().to_lowercase())
+                .collect(),
+            rows: records
+                .filter(|row| row.iter().any(|f| !f.is_empty()))
+                .collect(),
+        })
+    }
+
+    pub fn has(&self, name: &str) -> bool {
+        self.header.iter().any(|column| column == name)
+    }
+
+    /// The first non-empty value in `row` under `name`.
+    pub fn get<'a>(&self, row: &'a [String], name: &str) -> Option<&'a str> {
+        self.all(row, name).into_iter().next()
+    }
+
+    /// Every non-empty value in `row` under `name`.
+    pub fn all<'a>(&self, row: &'a [String], name: &str) -> Vec<&'a str> {
+        self.header
+            .iter()
+            .zip(row)
+            .filter(|(column, value)| *column == name && !value.trim().is_empty())
+            .map(|(_, value)| value.trim())
+            .collect()
+    }
+}
+
+/// Splits CSV text into records of fields, allowing quoted fields with commas, doubled
+/// quotes and line breaks in them.
+fn records(text: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut records = vec![];
+    let mut record = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.is_empty() => quoted = true,
+            ',' if !quoted => record.push(std::mem::take(&mut field)),
+            '\r' if !quoted && chars.peek() == Some(&'\n') => {}
+            '\n' if !quoted => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            c => field.push(c),
+        }
+    }
+    if quoted {
+        return Err("The CSV file ends inside a quoted field".into());
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table() {
+        let text = "\u{feff}Summary,Labels,Labels\r\n\"Fix \"\"login\"\", again\",auth,\r\n\
+                    \"Two\nlines\",,ui\r\n\r\n";
+        let table = Table::parse(text).unwrap();
+        assert!(table.has("labels"));
+        assert_eq!(table.rows.len(), 2);
+        assert_eq!(
+            table.get(&table.rows[0], "summary"),
+            Some("Fix \"login\", again")
+        );
+        assert_eq!(table.get(&table.rows[1], "summary"), Some("Two\nlines"));
+        assert_eq!(table.all(&table.rows[0], "labels"), vec!["auth"]);
+        assert_eq!(table.get(&table.rows[1], "labels"), Some("ui"));
+        assert!(Table::parse("a,\"b\n").is_err());
+    }
+}
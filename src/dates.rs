@@ -0,0 +1,270 @@
+use chrono::Datelike;
+use chrono::Days;
+use chrono::Months;
+use chrono::NaiveDate;
+use chrono::NaiveDateTime;
+use chrono::NaiveTime;
+use chrono::TimeDelta;
+use chrono::Weekday;
+
+/// Parses a date relative to `today`, or `none` to clear the date.
+///
+/// Accepts ISO dates (`2025-06-03`), `today`/`tomorrow`/`yesterday`, weekday names
+/// (`friday`, `next friday`), `next week|month`, `in 2 weeks` and month-day pairs
+/// (`jun 3`, `3 june`, `jun 3 2026`).
+pub fn parse_date(s: &str, today: NaiveDate) -> Result<Option<NaiveDate>, String> {
+    let s = s.trim().to_lowercase();
+    let words: Vec<&str> = s.split_whitespace().collect();
+    let date = match words.as_slice() {
+        ["none"] => return Ok(None),
+        ["today"] => Some(today),
+        ["tomorrow"] => today.checked_add_days(Days::new(1)),
+        ["yesterday"] => today.checked_sub_days(Days::new(1)),
+        ["next", "week"] => today.checked_add_days(Days::new(7)),
+        ["next", "month"] => today.checked_add_months(Months::new(1)),
+        ["next", weekday] | [weekday] if parse_weekday(weekday).is_some() => {
+            Some(next_weekday(today, parse_weekday(weekday).unwrap()))
+        }
+        ["in", amount, unit] => {
+            let amount: u32 = amount.parse().map_err(|_| format!("Invalid date {}", s))?;
+            match unit.trim_end_matches('s') {
+                "day" => today.checked_add_days(Days::new(amount.into())),
+                "week" => today.checked_add_days(Days::new(u64::from(amount) * 7)),
+                "month" => today.checked_add_months(Months::new(amount)),
+                "year" => today.checked_add_months(Months::new(amount * 12)),
+                _ => None,
+            }
+        }
+        [iso] if NaiveDate::parse_from_str(iso, "%Y-%m-%d").is_ok() => {
+            NaiveDate::parse_from_str(iso, "%Y-%m-%d").ok()
+        }
+        [month, day] | [day, month] if parse_month(month).is_some() => {
+            month_day(today, parse_month(month).unwrap(), day, None)
+        }
+        [month, day, year] | [day, month, year] if parse_month(month).is_some() => {
+            month_day(today, parse_month(month).unwrap(), day, Some(year))
+        }
+        _ => None,
+    };
+    date.map(Some).ok_or(format!(
+        "Invalid date {}, expected e.g. 2025-06-03, tomorrow, next friday, in 2 weeks or jun 3",
+        s
+    ))
+}
+
+/// Parses a date optionally followed by a time of day, such as `tomorrow 9am`,
+/// `next friday 14:30` or `2025-06-03`. A missing time of day defaults to 9am.
+pub fn parse_datetime(s: &str, now: NaiveDateTime) -> Result<NaiveDateTime, String> {
+    let s = s.trim().to_lowercase();
+    let (date, time) = match s.rsplit_once(' ') {
+        Some((date, time)) if parse_time(time).is_ok() => (date, parse_time(time)?),
+        _ => (s.as_str(), NaiveTime::from_hms_opt(9, 0, 0).unwrap()),
+    };
+    let date = parse_date(date, now.date())?.ok_or("A reminder needs a date")?;
+    Ok(date.and_time(time))
+}
+
+/// Parses a time of day written as `9am`, `5:30pm` or `14:30`.
+pub fn parse_time(s: &str) -> Result<NaiveTime, String> {
+    let err = || format!("Invalid time {}, expected e.g. 9am, 5:30pm or 14:30", s);
+    let (clock, offset) = match (s.strip_suffix("am"), s.strip_suffix("pm")) {
+        (Some(clock), _) => (clock, Some(0)),
+        (_, Some(clock)) => (clock, Some(12)),
+        _ if s.contains(':') => (s, None),
+        _ => return Err(err()),
+    };
+    let (hour, minute) = clock.split_once(':').unwrap_or((clock, "0"));
+    let mut hour: u32 = hour.parse().map_err(|_| err())?;
+    let minute: u32 = minute.parse().map_err(|_| err())?;
+    if let Some(offset) = offset {
+        if !(1..=12).contains(&hour) {
+            return Err(err());
+        }
+        hour = hour % 12 + offset;
+    }
+    NaiveTime::from_hms_opt(hour, minute, 0).ok_or_else(err)
+}
+
+/// Parses a duration such as `25m`, `2h`, `3d` or `1w`.
+pub fn parse_duration(s: &str) -> Result<TimeDelta, String> {
+    let err = || format!("Invalid duration {}, expected e.g. 25m, 2h, 3d or 1w", s);
+    let (unit_at, _) = s.char_indices().last().ok_or_else(err)?;
+    let amount: i64 = s[..unit_at].parse().map_err(|_| err())?;
+    let duration = match &s[unit_at..] {
+        "m" => TimeDelta::try_minutes(amount),
+        "h" => TimeDelta::try_hours(amount),
+        "d" => TimeDelta::try_days(amount),
+        "w" => TimeDelta::try_weeks(amount),
+        _ => None,
+    };
+    duration.ok_or_else(err)
+}
+
+/// Formats a duration compactly, e.g. `2d 3h`, `5h 10m` or `45m`.
+pub fn format_duration(duration: TimeDelta) -> String {
+    let minutes = duration.num_minutes().max(0);
+    let (days, hours, minutes) = (minutes / 1440, minutes / 60 % 24, minutes % 60);
+    match (days, hours) {
+        (0, 0) => format!("{}m", minutes),
+        (0, _) => format!("{}h {}m", hours, minutes),
+        _ => format!("{}d {}h", days, hours),
+    }
+}
+
+fn parse_weekday(s: &str) -> Option<Weekday> {
+    match s {
+        "mon" | "monday" => Some(Weekday::Mon),
+        "tue" | "tues" | "tuesday" => Some(Weekday::Tue),
+        "wed" | "wednesday" => Some(Weekday::Wed),
+        "thu" | "thurs" | "thursday" => Some(Weekday::Thu),
+        "fri" | "friday" => Some(Weekday::Fri),
+        "sat" | "saturday" => Some(Weekday::Sat),
+        "sun" | "sunday" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+fn parse_month(s: &str) -> Option<u32> {
+    const MONTHS: [&str; 12] = [
+        "january",
+        "february",
+        "march",
+        "april",
+        "may",
+        "june",
+        "july",
+        "august",
+        "september",
+        "october",
+        "november",
+        "december",
+    ];
+    if s.len() < 3 {
+        return None;
+    }
+    MONTHS
+        .iter()
+        .position(|month| month.starts_with(s))
+        .map(|index| index as u32 + 1)
+}
+
+/// The first `weekday` strictly after `today`.
+fn next_weekday(today: NaiveDate, weekday: Weekday) -> NaiveDate {
+    let ahead = (7 + weekday.num_days_from_monday() - today.weekday().num_days_from_monday()) % 7;
+    today + Days::new(if ahead == 0 { 7 } else { ahead.into() })
+}
+
+/// A month-day date; without an explicit year the next such date on or after `today` is used.
+fn month_day(today: NaiveDate, month: u32, day: &str, year: Option<&str>) -> Option<NaiveDate> {
+    let day: u32 = day.parse().ok()?;
+    match year {
+        Some(year) => NaiveDate::from_ymd_opt(year.parse().ok()?, month, day),
+        None => {
+            let date = NaiveDate::from_ymd_opt(today.year(), month, day)?;
+            if date < today {
+                NaiveDate::from_ymd_opt(today.year() + 1, month, day)
+            } else {
+                Some(date)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_parse_date_iso() {
+        let today = date(2025, 3, 10);
+        assert_eq!(
+            parse_date("2025-06-03", today).unwrap(),
+            Some(date(2025, 6, 3))
+        );
+        assert_eq!(parse_date("none", today).unwrap(), None);
+        assert!(parse_date("06/03/2025", today).is_err());
+        assert!(parse_date("someday", today).is_err());
+    }
+
+    #[test]
+    fn test_parse_date_relative() {
+        // 2025-03-10 is a Monday.
+        let today = date(2025, 3, 10);
+        let parse = |s| parse_date(s, today).unwrap().unwrap();
+        assert_eq!(parse("today"), today);
+        assert_eq!(parse("Tomorrow"), date(2025, 3, 11));
+        assert_eq!(parse("yesterday"), date(2025, 3, 9));
+        assert_eq!(parse("friday"), date(2025, 3, 14));
+        assert_eq!(parse("next friday"), date(2025, 3, 14));
+        assert_eq!(parse("monday"), date(2025, 3, 17));
+        assert_eq!(parse("next week"), date(2025, 3, 17));
+        assert_eq!(parse("next month"), date(2025, 4, 10));
+        assert_eq!(parse("in 2 weeks"), date(2025, 3, 24));
+        assert_eq!(parse("in 1 day"), date(2025, 3, 11));
+        assert_eq!(parse("in 3 months"), date(2025, 6, 10));
+        assert!(parse_date("in two weeks", today).is_err());
+    }
+
+    #[test]
+    fn test_parse_date_month_day() {
+        let today = date(2025, 3, 10);
+        let parse = |s| parse_date(s, today).unwrap().unwrap();
+        assert_eq!(parse("jun 3"), date(2025, 6, 3));
+        assert_eq!(parse("3 june"), date(2025, 6, 3));
+        assert_eq!(parse("jan 5"), date(2026, 1, 5));
+        assert_eq!(parse("jan 5 2027"), date(2027, 1, 5));
+        assert!(parse_date("feb 30", today).is_err());
+        assert!(parse_date("ju 3", today).is_err());
+    }
+
+    #[test]
+    fn test_parse_datetime() {
+        let now = date(2025, 3, 10).and_hms_opt(18, 0, 0).unwrap();
+        let at = |y, m, d, h, min| date(y, m, d).and_hms_opt(h, min, 0).unwrap();
+        assert_eq!(
+            parse_datetime("tomorrow 9am", now).unwrap(),
+            at(2025, 3, 11, 9, 0)
+        );
+        assert_eq!(
+            parse_datetime("today 5:30pm", now).unwrap(),
+            at(2025, 3, 10, 17, 30)
+        );
+        assert_eq!(
+            parse_datetime("2025-04-01 14:15", now).unwrap(),
+            at(2025, 4, 1, 14, 15)
+        );
+        assert_eq!(
+            parse_datetime("next friday 12am", now).unwrap(),
+            at(2025, 3, 14, 0, 0)
+        );
+        assert_eq!(parse_datetime("jun 3", now).unwrap(), at(2025, 6, 3, 9, 0));
+        assert_eq!(parse_datetime("today", now).unwrap(), at(2025, 3, 10, 9, 0));
+        assert!(parse_datetime("tomorrow 13pm", now).is_err());
+        assert!(parse_datetime("someday", now).is_err());
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("25m").unwrap(), TimeDelta::minutes(25));
+        assert_eq!(parse_duration("2h").unwrap(), TimeDelta::hours(2));
+        assert_eq!(parse_duration("3d").unwrap(), TimeDelta::days(3));
+        assert_eq!(parse_duration("1w").unwrap(), TimeDelta::weeks(1));
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("d").is_err());
+        assert!(parse_duration("3y").is_err());
+        assert!(parse_duration("3é").is_err());
+        assert!(parse_duration("9999999999999999w").is_err());
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(TimeDelta::minutes(45)), "45m");
+        assert_eq!(format_duration(TimeDelta::minutes(310)), "5h 10m");
+        assert_eq!(format_duration(TimeDelta::hours(51)), "2d 3h");
+        assert_eq!(format_duration(TimeDelta::minutes(-5)), "0m");
+    }
+}
@@ -0,0 +1,69 @@
+// Scoring loosely follows fzf: every matched character scores, runs of consecutive
+// matches and matches at the start of a word score extra, and skipped characters
+// between matches cost a little.
+const MATCH: i64 = 16;
+const CONSECUTIVE: i64 = 8;
+const WORD_START: i64 = 8;
+const GAP: i64 = 1;
+
+/// Scores `pattern` as a case-insensitive subsequence of `text`, e.g. `bymlk` in
+/// `buy milk`. Returns `None` when not every pattern character can be matched in order.
+pub fn score(pattern: &str, text: &str) -> Option<i64> {
+    let pattern: Vec<char> = pattern
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect();
+    if pattern.is_empty() {
+        return Some(0);
+    }
+    let text: Vec<char> = text.chars().flat_map(char::to_lowercase).collect();
+    let mut wanted = pattern.iter().peekable();
+    let mut total = 0;
+    let mut last_match: Option<usize> = None;
+    for (i, c) in text.iter().enumerate() {
+        let Some(&&want) = wanted.peek() else {
+            break;
+        };
+        if *c != want {
+            continue;
+        }
+        wanted.next();
+        total += MATCH;
+        if i == 0 || !text[i - 1].is_alphanumeric() {
+            total += WORD_START;
+        }
+        match last_match {
+            Some(last) if last + 1 == i => total += CONSECUTIVE,
+            Some(last) => total -= GAP * (i - last - 1) as i64,
+            None => {}
+        }
+        last_match = Some(i);
+    }
+    wanted.peek().is_none().then_some(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_score_subsequence() {
+        assert!(score("bymlk", "buy milk").is_some());
+        assert!(score("BUY", "buy milk").is_some());
+        assert!(score("buy milk", "Buy Milk").is_some());
+        assert!(score("", "anything").is_some());
+        assert_eq!(score("mb", "buy milk"), None);
+        assert_eq!(score("eggs", "buy milk"), None);
+    }
+
+    #[test]
+    fn test_score_ranks_tighter_matches_higher() {
+        let exact = score("milk", "buy milk").unwrap();
+        let scattered = score("milk", "make invoices like last week").unwrap();
+        assert!(exact > scattered);
+        let word_starts = score("bm", "buy milk").unwrap();
+        let inner = score("bm", "submit").unwrap();
+        assert!(word_starts > inner);
+    }
+}
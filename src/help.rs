@@ -0,0 +1,642 @@
+use crate::REGISTRY;
+use crate::config::ENVIRONMENT;
+use roff::Roff;
+use roff::bold;
+use roff::italic;
+use roff::roman;
+use std::io;
+
+/// Usage information for one REPL command; the menu and `help` are both generated from [`COMMANDS`].
+pub struct CommandSpec {
+    /// Every accepted spelling, shortest first, e.g. `["a", "add"]`.
+    pub names: &'static [&'static str],
+    /// See [`CommandSpec::args`] for the placeholders it may hold.
+    pub args: &'static str,
+    pub summary: &'static str,
+    pub examples: &'static [&'static str],
+}
+
+impl CommandSpec {
+    /// The one-line form shown in the menu, e.g. `[h | history] <TODO-item-id>`.
+    pub fn usage(&self) -> String {
+        let names = match self.names {
+            [name] => name.to_string(),
+            names => format!("[{}]", names.join(" | ")),
+        };
+        format!("{} {}", names, self.args()).trim_end().to_string()
+    }
+
+    /// The arguments, with `{formats}` and `{import_formats}` replaced by the formats
+    /// `export` writes and `import` reads.
+    pub fn args(&self) -> String {
+        self.args
+            .replace("{formats}", &REGISTRY.usage(false))
+            .replace("{import_formats}", &REGISTRY.usage(true))
+    }
+
+    /// The full `help <command>` text.
+    pub fn detail(&self) -> String {
+        let mut out = format!("{}\n\n{}\n", self.usage(), self.summary);
+        if self.names.len() > 1 {
+            out.push_str(&format!("\nShorthands: {}\n", self.names.join(", ")));
+        }
+        if !self.examples.is_empty() {
+            out.push_str("\nExamples:\n");
+            for example in self.examples {
+                out.push_str(&format!("  {}\n", example));
+            }
+        }
+        out
+    }
+}
+
+pub const COMMANDS: &[CommandSpec] = &[
+    CommandSpec {
+        names: &["a", "add"],
+        args: "<TODO-item> [@context] [+tag ...] | --from-template <template-name> | --from-file <path> [@context] [+tag ...] [--due <date>]",
+        summary: "Adds a task. @word sets the context, +word adds a tag and the other words form the description. \
+                  Quote text (\"...\" or '...') to keep its spacing. --from-file adds one task per non-empty line \
+                  of a file, each given the context, tags and due date that follow.",
+        examples: &[
+            "add Buy groceries @errands +home",
+            "a report --from-template weekly",
+            "add --from-file ideas.txt +someday --due friday",
+        ],
+    },
+    CommandSpec {
+        names: &["r", "remove"],
+        args: "<TODO-item-id> [-f | --force]",
+        summary: "Removes a task and any links pointing at it, after asking for confirmation unless --force is \
+                  given or confirm = false is set in config.toml.",
+        examples: &["remove 3", "r 3 -f"],
+    },
+    CommandSpec {
+        names: &["d", "done"],
+        args: "<TODO-item-id> [--archive]",
+        summary: "Marks a task completed, optionally moving it to the archive.",
+        examples: &["done 3", "d 3 --archive"],
+    },
+    CommandSpec {
+        names: &["archived"],
+        args: "",
+        summary: "Lists archived tasks.",
+        examples: &[],
+    },
+    CommandSpec {
+        names: &["ed", "edit"],
+        args: "<TODO-item-id>",
+        summary: "Opens a task as YAML in $VISUAL or $EDITOR and applies the saved changes.",
+        examples: &["edit 3"],
+    },
+    CommandSpec {
+        names: &["u", "update"],
+        args: "<TODO-item-id> <field> <new-value>",
+        summary: "Changes one field of a task. Fields (shorthand in brackets): [s] status, [d] description, due, \
+                  [sch] scheduled, [w] waiting-on, [p] points, sprint, [m] milestone, [pr] progress, parent, \
+                  [rc] recur, [en] energy, [loc] location, color, icon, url, [t] tags, [pj] project, \
+                  [pri] priority. Use none to clear a field.",
+        examples: &[
+            "update 3 status in-progress",
+            "u 3 due next friday",
+            "u 3 t work,urgent",
+            "u 3 pri none",
+        ],
+    },
+    CommandSpec {
+        names: &["append"],
+        args: "<TODO-item-id> <text>",
+        summary: "Adds text to the end of a task's description.",
+        examples: &["append 3 and eggs"],
+    },
+    CommandSpec {
+        names: &["prepend"],
+        args: "<TODO-item-id> <text>",
+        summary: "Adds text to the start of a task's description.",
+        examples: &["prepend 3 Urgent:"],
+    },
+    CommandSpec {
+        names: &["alias"],
+        args: "<TODO-item-id> <name> | none",
+        summary: "Names a task so that the name can be used in place of its id, e.g. done taxes. Names are \
+                  unique and can't be numbers.",
+        examples: &["alias 12 taxes", "done taxes", "alias taxes none"],
+    },
+    CommandSpec {
+        names: &["at", "attach"],
+        args: "<TODO-item-id> <file-path-or-url>",
+        summary: "Attaches a file path or URL to a task.",
+        examples: &["attach 3 ~/notes/plan.md"],
+    },
+    CommandSpec {
+        names: &["tpl", "template"],
+        args: "save <name> <id>[,<id>...] | remove <name> | list",
+        summary: "Manages task templates that add --from-template instantiates.",
+        examples: &["template save weekly 1,2,3", "tpl list"],
+    },
+    CommandSpec {
+        names: &["l", "list"],
+        args: "[@context] [--at <location>] [--status <status>] [--tag <tag>] [--project <project>] [--due <date> | overdue] [--due-before <date>] [--all]",
+        summary: "Lists tasks matching every given filter. Tasks scheduled in the future are hidden unless --all is given.",
+        examples: &[
+            "list @phone",
+            "l --tag work --due overdue",
+            "list --status ip --project web",
+        ],
+    },
+    CommandSpec {
+        names: &["ctx", "contexts"],
+        args: "",
+        summary: "Shows every context with its number of tasks.",
+        examples: &[],
+    },
+    CommandSpec {
+        names: &["remind"],
+        args: "<TODO-item-id> <date> [time]",
+        summary: "Sets a reminder; without a time it fires at 9am.",
+        examples: &["remind 3 tomorrow 9am", "remind 3 jun 3 14:30"],
+    },
+    CommandSpec {
+        names: &["reminders"],
+        args: "[--notify]",
+        summary: "Lists upcoming reminders; --notify also sends desktop notifications when they fire.",
+        examples: &[],
+    },
+    CommandSpec {
+        names: &["h", "history"],
+        args: "<TODO-item-id>",
+        summary: "Shows every recorded change to a task.",
+        examples: &["history 3"],
+    },
+    CommandSpec {
+        names: &["dg", "delegated"],
+        args: "",
+        summary: "Lists delegated tasks grouped by whom they are waiting on.",
+        examples: &[],
+    },
+    CommandSpec {
+        names: &["sprint"],
+        args: "summary [sprint]",
+        summary: "Summarises points per sprint, or for one sprint.",
+        examples: &["sprint summary", "sprint summary s12"],
+    },
+    CommandSpec {
+        names: &["ms", "milestone"],
+        args: "add <name> | remove <milestone-id>",
+        summary: "Adds or removes a milestone.",
+        examples: &["milestone add Beta launch", "ms remove 1"],
+    },
+    CommandSpec {
+        names: &["milestones"],
+        args: "",
+        summary: "Shows the progress of every milestone.",
+        examples: &[],
+    },
+    CommandSpec {
+        names: &["stale"],
+        args: "[days]",
+        summary: "Lists tasks untouched for the given number of days (the configured stale_days by default).",
+        examples: &["stale", "stale 30"],
+    },
+    CommandSpec {
+        names: &["skip"],
+        args: "<TODO-item-id>",
+        summary: "Skips the current occurrence of a recurring task.",
+        examples: &["skip 3"],
+    },
+    CommandSpec {
+        names: &["except"],
+        args: "<TODO-item-id> <date>",
+        summary: "Excludes a date from a recurring task's occurrences.",
+        examples: &["except 3 dec 25"],
+    },
+    CommandSpec {
+        names: &["link"],
+        args: "<TODO-item-id> [rel | relates-to] | [dup | duplicates] <other-id>",
+        summary: "Links two tasks.",
+        examples: &["link 3 rel 4", "link 5 dup 2"],
+    },
+    CommandSpec {
+        names: &["unlink"],
+        args: "<TODO-item-id> <other-id>",
+        summary: "Removes the links from one task to another.",
+        examples: &["unlink 3 4"],
+    },
+    CommandSpec {
+        names: &["agenda"],
+        args: "",
+        summary: "Shows open tasks grouped by when they are due: overdue, today, tomorrow, each day of the \
+                  coming week, later and without a due date.",
+        examples: &[],
+    },
+    CommandSpec {
+        names: &["today"],
+        args: "",
+        summary: "Shows the agenda for tasks that are overdue or due today.",
+        examples: &[],
+    },
+    CommandSpec {
+        names: &["week"],
+        args: "",
+        summary: "Shows the agenda for tasks that are overdue or due within the next seven days.",
+        examples: &[],
+    },
+    CommandSpec {
+        names: &["cal", "calendar"],
+        args: "[month]",
+        summary: "Shows a month as a grid with the number of open tasks due on each day. The month can be \
+                  given as 2025-03, a month name, next or prev; it defaults to the current one.",
+        examples: &["calendar", "cal march", "cal 2025-12"],
+    },
+    CommandSpec {
+        names: &["n", "next"],
+        args: "[count] [--energy low | medium | high]",
+        summary: "Suggests what to work on, ranked by due date urgency, priority, work in progress and age. \
+                  Tasks with open subtasks rank lower.",
+        examples: &["next", "next 5", "n --energy low"],
+    },
+    CommandSpec {
+        names: &["o", "open"],
+        args: "<TODO-item-id>",
+        summary: "Opens a task's url in the browser.",
+        examples: &["open 3"],
+    },
+    CommandSpec {
+        names: &["f", "find"],
+        args: "<query>",
+        summary: "Fuzzy-searches task descriptions, best match first.",
+        examples: &["find bymlk"],
+    },
+    CommandSpec {
+        names: &["sort"],
+        args: "by due | priority | id | created | status [asc | desc] | none",
+        summary: "Sets the order used by listings and exports. Priority sorts high first unless asc is given.",
+        examples: &["sort by due", "sort by created desc", "sort none"],
+    },
+    CommandSpec {
+        names: &["mv", "move"],
+        args: "<TODO-item-id> up | down | top | bottom | to <position>",
+        summary: "Changes where a task appears in the manual order used when no sort is set.",
+        examples: &["move 5 up", "mv 5 to 1"],
+    },
+    CommandSpec {
+        names: &["dup", "duplicate"],
+        args: "<TODO-item-id> [--subtasks] [--no-tags]",
+        summary: "Copies a task with a fresh id and Not Started status, optionally with copies of its subtasks.",
+        examples: &["dup 3", "dup 3 --subtasks --no-tags"],
+    },
+    CommandSpec {
+        names: &["clear"],
+        args: "[--older-than <duration>] [--archive] [-f | --force]",
+        summary: "Removes every completed task, or archives them with --archive. With --older-than only tasks \
+                  completed at least that long ago are cleared. Removing asks for confirmation unless --force \
+                  is given.",
+        examples: &["clear", "clear --older-than 30d --archive"],
+    },
+    CommandSpec {
+        names: &["s", "show"],
+        args: "<TODO-item-id>",
+        summary: "Shows every field of a task, its notes and its history.",
+        examples: &["show 3"],
+    },
+    CommandSpec {
+        names: &["start"],
+        args: "<TODO-item-id> [--track]",
+        summary: "Marks a task In Progress, starting its timer with --track. Respects wip_limit from config.toml.",
+        examples: &["start 3", "start 3 --track"],
+    },
+    CommandSpec {
+        names: &["pause"],
+        args: "<TODO-item-id>",
+        summary: "Moves an In Progress task back to Not Started and stops its timer.",
+        examples: &["pause 3"],
+    },
+    CommandSpec {
+        names: &["pomodoro"],
+        args: "<TODO-item-id> [length]",
+        summary: "Counts down a focus session for a task (25 minutes unless a length is given), adds it to the \
+                  task's time spent and rings when it ends. Press q to stop early.",
+        examples: &["pomodoro 3", "pomodoro 3 50m"],
+    },
+    CommandSpec {
+        names: &["merge"],
+        args: "<source-id> <target-id>",
+        summary: "Folds the source task into the target: descriptions and notes are joined, tags and attachments \
+                  combined, subtasks moved, and the source task removed. Use undo to reverse it.",
+        examples: &["merge 5 3"],
+    },
+    CommandSpec {
+        names: &["split"],
+        args: "<TODO-item-id> [<description> ...] [--subtasks]",
+        summary: "Replaces a task with one new task per description, the first taking over its subtasks. With \
+                  --subtasks the new tasks become subtasks of the original instead. Without descriptions you are \
+                  asked for them one per line.",
+        examples: &["split 3 'Book flights' 'Book hotel'", "split 3 --subtasks"],
+    },
+    CommandSpec {
+        names: &["move-to"],
+        args: "<TODO-item-id> <project>",
+        summary: "Moves a task and its subtasks into another project's file (project.json, in the same \
+                  directory as the task file), giving them new ids there. Links to tasks left behind are \
+                  dropped.",
+        examples: &["move-to 3 work", "move-to 3 ../home/tasks.json"],
+    },
+    CommandSpec {
+        names: &["copy-to"],
+        args: "<TODO-item-id> <project>",
+        summary: "Like move-to, but leaves the original tasks in place.",
+        examples: &["copy-to 3 work"],
+    },
+    CommandSpec {
+        names: &["roulette"],
+        args: "[--weighted]",
+        summary: "Picks a random open task without open subtasks and offers to start it. With --weighted higher \
+                  priority tasks come up more often.",
+        examples: &["roulette", "roulette --weighted"],
+    },
+    CommandSpec {
+        names: &["review"],
+        args: "",
+        summary: "Walks through every open task, asking whether to mark it done, snooze it (a day unless a \
+                  duration is given), remove it, edit it, move on to the next one or stop.",
+        examples: &[],
+    },
+    CommandSpec {
+        names: &["c", "count"],
+        args: "",
+        summary: "Summarises how many tasks there are by status and tag, and how many are overdue.",
+        examples: &[],
+    },
+    CommandSpec {
+        names: &["source"],
+        args: "<file> [--keep-going]",
+        summary: "Runs the commands in a file, one per line; blank lines and lines starting with # are skipped. \
+                  The first failing command stops the script unless --keep-going is given.",
+        examples: &["source setup.txt", "source setup.txt --keep-going"],
+    },
+    CommandSpec {
+        names: &["undo"],
+        args: "",
+        summary: "Reverts the last change to the task list.",
+        examples: &[],
+    },
+    CommandSpec {
+        names: &["redo"],
+        args: "",
+        summary: "Reapplies the last undone change.",
+        examples: &[],
+    },
+    CommandSpec {
+        names: &["z", "snooze"],
+        args: "<TODO-item-id> <duration, e.g. 3d | 1w>",
+        summary: "Pushes a task's due date (or today, if it has none) forward.",
+        examples: &["snooze 3 2d"],
+    },
+    CommandSpec {
+        names: &["e", "export"],
+        args: "{formats} <file> | --clipboard [<list filters>] \
+               | template <template-file> <file> [<list filters>] \
+               | exec <command> <file> [<list filters>]",
+        summary: "Writes all tasks to a file in the given format, or with list's filters just the \
+                  tasks they match, scheduled ones included. --clipboard copies the export instead, to \
+                  paste into chat or email; on Linux a clipboard manager keeps it once todore exits. \
+                  plaintext uses status symbols, priority flags and ⏰ for overdue dates with \
+                  pretty_plaintext = true in config.toml, or follows plaintext_format, a line layout \
+                  with the placeholders {id}, {description}, {status}, {status:short}, {priority}, \
+                  {due}, {scheduled}, {project}, {context}, {location}, {tags}, {parent} and \
+                  {progress}, and {{ and }} for literal braces. ndjson writes one task per line as JSON, \
+                  streamed, for jq and other line-based tools; markdown writes a - [ ] checklist grouped \
+                  by project and status; html a standalone page with a table that sorts by the clicked \
+                  column and shows overdue tasks in red; ics an iCalendar file of the tasks with a due date, \
+                  as all-day events and to-dos that repeat like the tasks do; org an org-mode outline with \
+                  subtasks nested under their parents; xml a <tasks> document of <task id status> elements \
+                  with a child element per field; outlook and jira CSV files those apps can import; \
+                  table aligned columns, and box the same inside box-drawing borders, also usable as \
+                  --format for listings. template renders a Jinja-style template of your own, looping over \
+                  tasks with fields such as description, status_text, due and tags, and given today; a \
+                  template named .html escapes what it prints. exec pipes the tasks as JSON to a shell \
+                  command and writes what it prints, for formats from other programs.",
+        examples: &[
+            "export json tasks-backup.json",
+            "export json done.json --status completed --tag work --due-before 2025-01-01",
+            "export md TODO.md",
+            "export md --clipboard --tag work",
+            "export html tasks.html",
+            "export ics due.ics",
+            "export org tasks.org",
+            "export template weekly-email.html email.html",
+            "export exec 'jq -r .tasks[].description' titles.txt",
+        ],
+    },
+    CommandSpec {
+        names: &["snap", "snapshot"],
+        args: "create <name> | restore <name> | list",
+        summary: "Saves a copy of every task, including the archive, and the templates under a name, e.g. \
+                  before a risky bulk change or import, and brings it back with restore after confirmation; \
+                  undo reverts the restored tasks.",
+        examples: &[
+            "snapshot create before-import",
+            "snapshot restore before-import",
+            "snap list",
+        ],
+    },
+    CommandSpec {
+        names: &["backup"],
+        args: "export <file.tar.gz> | import <file.tar.gz>",
+        summary: "Bundles the tasks, including the archive, with the templates, config.toml and prompt history \
+                  into one archive for moving to another machine. import replaces all of them from such a \
+                  bundle, after confirmation.",
+        examples: &["backup export todore.tar.gz", "backup import todore.tar.gz"],
+    },
+    CommandSpec {
+        names: &["profile", "profiles"],
+        args: "[list]",
+        summary: "Lists the profiles, marking the current one. Start todore with --profile <name> to keep \
+                  tasks, config, templates and history in a separate directory for that profile.",
+        examples: &["profile list"],
+    },
+    CommandSpec {
+        names: &["journal"],
+        args: "[count] | replay",
+        summary: "Every change is also appended to a journal next to the task file. journal lists the latest \
+                  entries (10 unless a count is given); journal replay rebuilds the tasks from the whole \
+                  journal, after confirmation, e.g. when the task file was damaged.",
+        examples: &["journal", "journal 50", "journal replay"],
+    },
+    CommandSpec {
+        names: &["import"],
+        args: "{import_formats} <file> [--merge | --replace]",
+        summary: "Reads tasks from a file written by export, the - [ ] checklist items of any \
+                  Markdown file, taking headings as projects or statuses, or the TODO headlines of \
+                  an org file. plaintext reads export's <id>: <description> lines, though not \
+                  pretty_plaintext or plaintext_format ones, and simple - [ ] lists. outlook reads \
+                  Outlook's task CSV export or Microsoft To Do tasks as Graph API JSON, keeping \
+                  due dates, importance, notes and categories. jira reads Jira's issue CSV export, \
+                  taking statuses by their category, labels and assignees as tags; a TOML file \
+                  named by jira_mapping in config.toml can map [status] and [priority] names and \
+                  choose the field each column fills under [fields]. By default they are added \
+                  alongside the current tasks under new ids; --merge matches them by id, keeping \
+                  whichever copy changed last, and adds the rest; --replace swaps the whole list \
+                  for the file's, after confirmation.",
+        examples: &[
+            "import yaml backup.yaml",
+            "import plaintext todo.txt",
+            "import json laptop.json --merge",
+            "import md README.md",
+            "import org inbox.org",
+            "import mstodo todo-export.json",
+            "import jira jira-export.csv",
+            "import json tasks-backup.json --replace",
+        ],
+    },
+    CommandSpec {
+        names: &["?", "help"],
+        args: "[command]",
+        summary: "Lists commands, or explains one command in detail. Commands taking a <TODO-item-id> open a \
+                  task picker when typed on their own at the prompt. Separate commands with ; to run several \
+                  at once, and type !! to repeat the previous line.",
+        examples: &["help", "help update"],
+    },
+    CommandSpec {
+        names: &["q", "quit"],
+        args: "",
+        summary: "Leaves todore.",
+        examples: &[],
+    },
+];
+
+/// An [`ENVIRONMENT`] description, with `{formats}` replaced by the registered formats.
+fn environment(summary: &str) -> String {
+    summary.replace("{formats}", &REGISTRY.list())
+}
+
+/// Finds a command by any of its names.
+pub fn lookup(name: &str) -> Option<&'static CommandSpec> {
+    COMMANDS.iter().find(|spec| spec.names.contains(&name))
+}
+
+/// Whether a command's first argument is a task id.
+pub fn takes_task_id(name: &str) -> bool {
+    lookup(&name.to_lowercase()).is_some_and(|spec| spec.args.starts_with("<TODO-item-id>"))
+}
+
+/// The short usage list printed by `help`.
+pub fn menu() -> String {
+    COMMANDS
+        .iter()
+        .map(CommandSpec::usage)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Appended to `--help`: every REPL command, which can also be run one-shot, and the
+/// environment variables.
+pub fn long_help() -> String {
+    let mut out = String::from("Commands:\n");
+    for spec in COMMANDS {
+        out.push_str(&format!("  {}\n          {}\n", spec.usage(), spec.summary));
+    }
+    out.push_str("\nEnvironment:\n");
+    for (name, summary) in ENVIRONMENT {
+        out.push_str(&format!("  {:<18} {}\n", name, environment(summary)));
+    }
+    out.push_str("\nSettings are read from config.toml first; the environment overrides them and flags override both.");
+    out
+}
+
+/// The `todore(1)` man page for `cli`, with sections for the commands and environment.
+pub fn man_page(cli: clap::Command) -> io::Result<String> {
+    let man = clap_mangen::Man::new(cli.clone());
+    let mut out = vec![];
+    man.render_title(&mut out)?;
+    man.render_name_section(&mut out)?;
+    man.render_synopsis_section(&mut out)?;
+    man.render_description_section(&mut out)?;
+    man.render_options_section(&mut out)?;
+
+    let mut roff = Roff::new();
+    roff.control("SH", ["COMMANDS"]);
+    roff.text([roman(
+        "Commands are typed at the todore> prompt, listed one per line in a --script file or \
+         piped in, or given after the options to run just one.",
+    )]);
+    for spec in COMMANDS {
+        roff.control("TP", []);
+        roff.text([bold(spec.names.join(", ")), roman(" "), italic(spec.args())]);
+        roff.text([roman(spec.summary)]);
+        for example in spec.examples {
+            roff.control("br", []);
+            roff.text([roman("e.g. "), bold(*example)]);
+        }
+    }
+    roff.control("SH", ["ENVIRONMENT"]);
+    for (name, summary) in ENVIRONMENT {
+        roff.control("TP", []);
+        roff.text([bold(*name)]);
+        roff.text([roman(environment(summary))]);
+    }
+    roff.control("TP", []);
+    roff.text([bold("NO_COLOR")]);
+    roff.text([roman("Print without colours, like --no-color")]);
+    roff.control("SH", ["FILES"]);
+    roff.control("TP", []);
+    roff.text([bold("tasks.json")]);
+    roff.text([roman(
+        "The task list, kept in the platform data directory unless --file, TODORE_FILE or file in \
+         config.toml names another. The first interactive run asks where to keep it and records the \
+         answer in the config.toml of the platform's config directory, e.g. ~/.config/todore, or \
+         the profile's, where every later run finds it. It is saved after every change unless autosave = false is set, \
+         and changes still unsaved while the prompt waits are saved in the background after \
+         autosave_interval_secs. A name ending in .gz or .zst, e.g. tasks.json.gz, keeps it compressed with gzip or zstd; \
+         one ending in .redb keeps each task as a separate record in an embedded database, so saves \
+         only write the tasks that changed. Each save records a checksum in <file>.sha256 and keeps \
+         the previous version as <file>.bak; a file that can't be read is rebuilt from that copy or \
+         the newest snapshot plus the journal.",
+    )]);
+    roff.text([roman(
+        "With encrypt = true in config.toml the file, its journal and snapshots are encrypted with \
+         a passphrase asked for at startup, or the contents of key_file. An encrypted file always \
+         needs its passphrase. A .redb or remote task file can't be encrypted, and todore won't \
+         start with encrypt set for one.",
+    )]);
+    roff.control("TP", []);
+    roff.text([bold("config.toml")]);
+    roff.text([roman(
+        "Settings, read from the working directory, or the profile directory with --profile. The environment overrides them and flags override both.",
+    )]);
+    roff.text([roman(
+        "A [hooks] table runs shell commands after tasks change: on-add and on-done receive the \
+         task as JSON on stdin, on-export the file name and every task.",
+    )]);
+    roff.control("TP", []);
+    roff.text([bold("templates.json")]);
+    roff.text([roman(
+        "Saved task templates, read from the working directory, or the profile directory with --profile.",
+    )]);
+    roff.to_writer(&mut out)?;
+
+    man.render_version_section(&mut out)?;
+    Ok(String::from_utf8_lossy(&out).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_usage() {
+        assert_eq!(lookup("h").unwrap().usage(), "[h | history] <TODO-item-id>");
+        assert_eq!(lookup("undo").unwrap().usage(), "undo");
+        assert!(lookup("frobnicate").is_none());
+        assert!(takes_task_id("Done"));
+        assert!(!takes_task_id("list"));
+    }
+
+    #[test]
+    fn test_detail_lists_shorthands_and_examples() {
+        let detail = lookup("add").unwrap().detail();
+        assert!(detail.starts_with("[a | add] <TODO-item>"));
+        assert!(detail.contains("Shorthands: a, add"));
+        assert!(detail.contains("  add Buy groceries @errands +home"));
+        assert!(!lookup("undo").unwrap().detail().contains("Examples"));
+    }
+}
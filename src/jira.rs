@@ -0,0 +1,331 @@
+//! Importing Jira's issue CSV export. Statuses go by their status category where the
+//! export has one, and a mapping file (`jira_mapping` in config.toml) can name the
+//! todore status and priority for custom workflows and choose the field each column
+//! fills, e.g.:
+//!
+//! ```toml
+//! base_url = "https://example.atlassian.net/browse/"
+//!
+//! [status]
+//! "In Review" = "in-progress"
+//! "Blocked" = "waiting"
+//!
+//! [priority]
+//! "Blocker" = "high"
+//!
+//! [fields]
+//! assignee = "waiting-on"
+//! "custom field (story points)" = "points"
+//! ```
+
+use crate::Formatter;
+use crate::Priority;
+use crate::Task;
+use crate::TaskList;
+use crate::TaskStatus;
+use crate::csv::Table;
+use crate::csv_field;
+use chrono::NaiveDate;
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+/// The column each todore field is read from unless the mapping says otherwise.
+/// Assignees become tags, since tasks have no owner.
+const FIELDS: &[(&str, &str)] = &[
+    ("summary", "description"),
+    ("description", "notes"),
+    ("priority", "priority"),
+    ("due date", "due"),
+    ("labels", "tags"),
+    ("assignee", "tags"),
+    ("sprint", "sprint"),
+    ("project name", "project"),
+    ("custom field (story points)", "points"),
+];
+
+/// The fields a column can be mapped to.
+const TARGETS: &[&str] = &[
+    "description",
+    "notes",
+    "status",
+    "priority",
+    "due",
+    "scheduled",
+    "waiting-on",
+    "points",
+    "sprint",
+    "project",
+    "context",
+    "location",
+    "tags",
+    "none",
+];
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Mapping {
+    /// Jira status names and the todore status each means.
+    #[serde(default)]
+    status: BTreeMap<String, String>,
+    /// Jira priority names and the todore priority each means; `none` for no priority.
+    #[serde(default)]
+    priority: BTreeMap<String, String>,
+    /// Column names and the todore field each fills, or `none` to skip the column.
+    #[serde(default)]
+    fields: BTreeMap<String, String>,
+    /// Prefixed to an issue's key to give the task's url.
+    base_url: Option<String>,
+}
+
+impl Mapping {
+    /// Reads the mapping file at `path`; no file is the default mapping.
+    pub fn load(path: Option<&Path>) -> Result<Self, Box<dyn std::error::Error>> {
+        let Some(path) = path else {
+            return Ok(Mapping::default());
+        };
+        let text = fs::read_to_string(path)
+            .map_err(|err| format!("Can't read {}: {}", path.display(), err))?;
+        let mapping: Mapping = toml::from_str(&text)?;
+        for status in mapping.status.values() {
+            TaskStatus::from_str(status)?;
+        }
+        for priority in mapping.priority.values() {
+            crate::parse_optional::<Priority>(priority)?;
+        }
+        if let Some(field) = mapping
+            .fields
+            .values()
+            .find(|f| !TARGETS.contains(&f.as_str()))
+        {
+            return Err(format!("Unknown field {} in {}", field, path.display()).into());
+        }
+        Ok(mapping)
+    }
+
+    /// The column → field pairs to read, the defaults overridden by the mapping.
+    fn fields(&self) -> Vec<(String, &str)> {
+        let mapped = |column: &str| {
+            self.fields
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(column))
+        };
+        let defaults = FIELDS
+            .iter()
+            .filter(|(column, _)| mapped(column).is_none())
+            .map(|(column, field)| (column.to_string(), *field));
+        let custom = self
+            .fields
+            .iter()
+            .map(|(column, field)| (column.to_lowercase(), field.as_str()));
+        defaults.chain(custom).collect()
+    }
+
+    fn status(&self, name: &str, category: Option<&str>) -> TaskStatus {
+        if let Some((_, status)) = self
+            .status
+            .iter()
+            .find(|(jira, _)| jira.eq_ignore_ascii_case(name))
+        {
+            return TaskStatus::from_str(status).unwrap_or_default();
+        }
+        match category.unwrap_or(name).to_lowercase().as_str() {
+            "in progress" | "in review" | "in development" => TaskStatus::InProgress,
+            "blocked" | "waiting" | "on hold" => TaskStatus::Waiting,
+            "done" | "closed" | "resolved" | "complete" | "completed" => TaskStatus::Completed,
+            _ => TaskStatus::NotStarted,
+        }
+    }
+
+    fn priority(&self, name: &str) -> Option<Priority> {
+        if let Some((_, priority)) = self
+            .priority
+            .iter()
+            .find(|(jira, _)| jira.eq_ignore_ascii_case(name))
+        {
+            return crate::parse_optional(priority).ok().flatten();
+        }
+        match name.to_lowercase().as_str() {
+            "highest" | "blocker" | "critical" | "high" => Some(Priority::High),
+            "medium" | "major" => Some(Priority::Medium),
+            "low" | "lowest" | "minor" | "trivial" => Some(Priority::Low),
+            _ => None,
+        }
+    }
+}
+
+/// Reads a Jira issue CSV export. Each task's description starts with the issue key.
+pub fn parse(text: &str, mapping: &Mapping) -> Result<TaskList, String> {
+    let table = Table::parse(text)?;
+    if !table.has("summary") {
+        return Err("Not a Jira export: it has no Summary column".into());
+    }
+    let fields = mapping.fields();
+    let mut tasks = TaskList::new();
+    for row in &table.rows {
+        let mut task = Task::new(tasks.tasks.len() as u32, String::new());
+        if let Some(status) = table.get(row, "status") {
+            task.status = mapping.status(status, table.get(row, "status category"));
+        }
+        for (column, field) in &fields {
+            for value in table.all(row, column) {
+                set(&mut task, field, value, mapping);
+            }
+        }
+        if task.description.is_empty() {
+            continue;
+        }
+        if let Some(key) = table.get(row, "issue key") {
+            task.description = format!("{}: {}", key, task.description);
+            if let Some(base_url) = &mapping.base_url {
+                task.url = Some(format!("{}{}", base_url, key));
+            }
+        }
+        tasks.tasks.push(task);
+    }
+    Ok(tasks)
+}
+
+/// Fills `field` of `task` from a column's `value`, ignoring values that don't fit.
+fn set(task: &mut Task, field: &str, value: &str, mapping: &Mapping) {
+    let text = Some(value.to_string());
+    match field {
+        "description" => task.description = value.to_string(),
+        "notes" => task.notes = text,
+        "status" => task.status = mapping.status(value, None),
+        "priority" => task.priority = mapping.priority(value),
+        "due" => task.due = date(value),
+        "scheduled" => task.scheduled = date(value),
+        "waiting-on" => task.waiting_on = text,
+        "points" => task.points = value.parse::<f64>().ok().map(|points| points as u32),
+        "sprint" => task.sprint = text,
+        "project" => task.project = text,
+        "context" => task.context = text,
+        "location" => task.location = text,
+        "tags" => {
+            let tag = value.to_lowercase().replace(char::is_whitespace, "-");
+            if !task.tags.contains(&tag) {
+                task.tags.push(tag);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// A date as Jira writes them, e.g. `05/Mar/25 10:00 AM`, or as ISO.
+fn date(text: &str) -> Option<NaiveDate> {
+    ["%d/%b/%y %I:%M %p", "%Y-%m-%d %H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+        .map(|at| at.date())
+        .or_else(|| {
+            ["%d/%b/%y", "%Y-%m-%d"]
+                .iter()
+                .find_map(|format| NaiveDate::parse_from_str(text, format).ok())
+        })
+}
+
+/// The tasks as a CSV for Jira's importer, with one Labels column per tag.
+pub struct JiraFormatter;
+
+impl Formatter for JiraFormatter {
+    fn format(&self, tasks: &TaskList) -> Result<String, Box<dyn std::error::Error>> {
+        let labels = tasks
+            .tasks
+            .iter()
+            .map(|task| task.tags.len())
+            .max()
+            .unwrap_or(0);
+        let mut header = vec!["Summary", "Status", "Priority", "Due Date", "Description"];
+        header.extend(std::iter::repeat_n("Labels", labels));
+        let mut out = header.join(",") + "\n";
+        for task in tasks.ordered() {
+            let mut fields = vec![
+                task.description.clone(),
+                match task.status {
+                    TaskStatus::NotStarted | TaskStatus::Waiting => "To Do",
+                    TaskStatus::InProgress => "In Progress",
+                    TaskStatus::Completed => "Done",
+                }
+                .to_string(),
+                match task.priority {
+                    Some(Priority::High) => "High",
+                    Some(Priority::Medium) => "Medium",
+                    Some(Priority::Low) => "Low",
+                    None => "",
+                }
+                .to_string(),
+                task.due.map(|due| due.to_string()).unwrap_or_default(),
+                task.notes.clone().unwrap_or_default(),
+            ];
+            fields.extend(task.tags.iter().cloned());
+            fields.resize(header.len(), String::new());
+            let row: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+            out.push_str(&row.join(","));
+            out.push('\n');
+        }
+        Ok(out)
+    }
+
+    fn parse(&self, text: &str) -> Result<TaskList, Box<dyn std::error::Error>> {
+        Ok(parse(text, &Mapping::default())?)
+    }
+
+    fn imports(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXPORT: &str = "Summary,Issue key,Status,Status Category,Priority,Assignee,Labels,Labels,\
+                          Due Date,Custom field (Story Points)\n\
+                          Fix login,WEB-12,In Review,In Progress,Highest,Jane Doe,auth,urgent,\
+                          05/Mar/25 10:00 AM,3.0\n\
+                          Write docs,WEB-13,Blocked,To Do,Low,,,,,\n";
+
+    #[test]
+    fn test_parse_jira_csv() {
+        let tasks = parse(EXPORT, &Mapping::default()).unwrap();
+        let login = &tasks.tasks[0];
+        assert_eq!(login.description, "WEB-12: Fix login");
+        assert_eq!(login.status, TaskStatus::InProgress);
+        assert_eq!(login.priority, Some(Priority::High));
+        assert_eq!(login.tags, vec!["auth", "urgent", "jane-doe"]);
+        assert_eq!(login.due, NaiveDate::from_ymd_opt(2025, 3, 5));
+        assert_eq!(login.points, Some(3));
+        assert_eq!(tasks.tasks[1].status, TaskStatus::NotStarted);
+        assert!(parse("Title\nx\n", &Mapping::default()).is_err());
+    }
+
+    #[test]
+    fn test_jira_mapping() {
+        let mapping: Mapping = toml::from_str(
+            "base_url = \"https://jira.example.com/browse/\"\n\
+             [status]\nblocked = \"waiting\"\n\
+             [priority]\nhighest = \"medium\"\n\
+             [fields]\nAssignee = \"waiting-on\"\nlabels = \"none\"\n",
+        )
+        .unwrap();
+        let tasks = parse(EXPORT, &mapping).unwrap();
+        let login = &tasks.tasks[0];
+        assert_eq!(login.priority, Some(Priority::Medium));
+        assert_eq!(login.waiting_on.as_deref(), Some("Jane Doe"));
+        assert!(login.tags.is_empty());
+        assert_eq!(
+            login.url.as_deref(),
+            Some("https://jira.example.com/browse/WEB-12")
+        );
+        assert_eq!(tasks.tasks[1].status, TaskStatus::Waiting);
+        assert!(toml::from_str::<Mapping>("[fields]\nx = 1\n").is_err());
+
+        let exported = JiraFormatter.format(&tasks).unwrap();
+        assert!(exported.starts_with("Summary,Status,Priority,Due Date,Description\n"));
+        assert!(exported.contains("WEB-13: Write docs,To Do,Low,,\n"));
+    }
+}
@@ -0,0 +1,211 @@
+//! An embedded key-value store for very large lists, chosen by a `.redb` task file. Each
+//! task is its own record keyed by id, so a save only writes the tasks that changed
+//! instead of the whole list. Tasks come back in id order.
+
+use crate::TaskList;
+use crate::storage::Event;
+use crate::storage::Storage;
+use redb::Database;
+use redb::ReadableDatabase;
+use redb::ReadableTable;
+use redb::ReadableTableMetadata;
+use redb::TableDefinition;
+use serde_json::Value;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::path::PathBuf;
+
+const TASKS: TableDefinition<u32, &str> = TableDefinition::new("tasks");
+/// Everything in the list besides the tasks, e.g. milestones, as one JSON record.
+const LIST: TableDefinition<&str, &str> = TableDefinition::new("list");
+const EVENTS: TableDefinition<u64, &str> = TableDefinition::new("events");
+
+pub struct KvStorage {
+    path: PathBuf,
+    /// Opened on first use, so a run that never saves doesn't create the file.
+    db: RefCell<Option<Database>>,
+    /// Each task as last loaded or saved, to find the ones a save has to write.
+    stored: RefCell<BTreeMap<u32, String>>,
+}
+
+impl KvStorage {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        KvStorage {
+            path: path.into(),
+            db: RefCell::new(None),
+            stored: RefCell::new(BTreeMap::new()),
+        }
+    }
+
+    /// Whether `file` should be kept in this store rather than as a single file.
+    pub fn handles(file: &Path) -> bool {
+        file.extension().is_some_and(|ext| ext == "redb")
+    }
+
+    fn with_db<T>(
+        &self,
+        f: impl FnOnce(&Database) -> Result<T, Box<dyn std::error::Error>>,
+    ) -> Result<T, Box<dyn std::error::Error>> {
+        let mut db = self.db.borrow_mut();
+        if db.is_none() {
+            if let Some(dir) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+                std::fs::create_dir_all(dir)?;
+            }
+            let created = Database::create(&self.path)?;
+            // Create the tables so reads never find them missing.
+            let txn = created.begin_write()?;
+            txn.open_table(TASKS)?;
+            txn.open_table(LIST)?;
+            txn.open_table(EVENTS)?;
+            txn.commit()?;
+            *db = Some(created);
+        }
+        f(db.as_ref().unwrap())
+    }
+}
+
+impl Storage for KvStorage {
+    fn load(&self) -> Result<TaskList, Box<dyn std::error::Error>> {
+        if !self.path.exists() {
+            return Ok(TaskList::new());
+        }
+        let (list, tasks) = self.with_db(|db| {
+            let txn = db.begin_read()?;
+            let list = txn
+                .open_table(LIST)?
+                .get("list")?
+                .map(|v| v.value().to_string());
+            let mut tasks = BTreeMap::new();
+            for entry in txn.open_table(TASKS)?.iter()? {
+                let (id, task) = entry?;
+                tasks.insert(id.value(), task.value().to_string());
+            }
+            Ok((list, tasks))
+        })?;
+        let mut value: Value = match list {
+            Some(list) => serde_json::from_str(&list)?,
+            None => serde_json::json!({}),
+        };
+        let records: Vec<Value> = tasks
+            .values()
+            .map(|task| serde_json::from_str(task))
+            .collect::<Result<_, _>>()?;
+        value["tasks"] = Value::Array(records);
+        let list = serde_json::from_value(value)?;
+        *self.stored.borrow_mut() = tasks;
+        Ok(list)
+    }
+
+    fn save(&self, tasks: &TaskList) -> Result<(), Box<dyn std::error::Error>> {
+        let mut value = serde_json::to_value(tasks)?;
+        let records: BTreeMap<u32, String> = tasks
+            .tasks
+            .iter()
+            .map(|task| Ok((task.id, serde_json::to_string(task)?)))
+            .collect::<Result<_, serde_json::Error>>()?;
+        value.as_object_mut().unwrap().remove("tasks");
+        let list = value.to_string();
+        let mut stored = self.stored.borrow_mut();
+        self.with_db(|db| {
+            let txn = db.begin_write()?;
+            {
+                let mut table = txn.open_table(TASKS)?;
+                for id in stored.keys().filter(|id| !records.contains_key(id)) {
+                    table.remove(id)?;
+                }
+                for (id, record) in &records {
+                    if stored.get(id) != Some(record) {
+                        table.insert(id, record.as_str())?;
+                    }
+                }
+                txn.open_table(LIST)?.insert("list", list.as_str())?;
+            }
+            txn.commit()?;
+            Ok(())
+        })?;
+        *stored = records;
+        Ok(())
+    }
+
+    fn append_event(&self, event: &Event) -> Result<(), Box<dyn std::error::Error>> {
+        let event = serde_json::to_string(event)?;
+        self.with_db(|db| {
+            let txn = db.begin_write()?;
+            {
+                let mut table = txn.open_table(EVENTS)?;
+                let next = table.len()?;
+                table.insert(next, event.as_str())?;
+            }
+            txn.commit()?;
+            Ok(())
+        })
+    }
+
+    fn events(&self) -> Result<Vec<Event>, Box<dyn std::error::Error>> {
+        if !self.path.exists() {
+            return Ok(vec![]);
+        }
+        self.with_db(|db| {
+            let txn = db.begin_read()?;
+            let mut events = vec![];
+            for entry in txn.open_table(EVENTS)?.iter()? {
+                events.push(serde_json::from_str(entry?.1.value())?);
+            }
+            Ok(events)
+        })
+    }
+
+    fn exists(&self) -> bool {
+        self.path.exists()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Task;
+    use chrono::Local;
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    #[test]
+    fn test_kv_storage_round_trip() {
+        let dir = env::temp_dir().join(format!("todore-kv-{}", process::id()));
+        let path = dir.join("tasks.redb");
+        assert!(KvStorage::handles(&path));
+        let storage = KvStorage::new(&path);
+        assert!(storage.load().unwrap().tasks.is_empty());
+        assert!(!storage.exists());
+
+        let mut tasks = TaskList::new();
+        for id in 0..3 {
+            tasks.add(Task::new(id, format!("Task {}", id)));
+        }
+        storage.save(&tasks).unwrap();
+        tasks.tasks[1].description = "Buy milk".to_string();
+        tasks.remove(2);
+        storage.save(&tasks).unwrap();
+        storage
+            .append_event(&Event {
+                at: Local::now(),
+                command: "edit 1".to_string(),
+                changes: BTreeMap::new(),
+            })
+            .unwrap();
+        drop(storage);
+
+        let reopened = KvStorage::new(&path);
+        let loaded = reopened.load().unwrap();
+        let descriptions: Vec<&str> = loaded
+            .tasks
+            .iter()
+            .map(|task| task.description.as_str())
+            .collect();
+        assert_eq!(descriptions, vec!["Task 0", "Buy milk"]);
+        assert_eq!(reopened.events().unwrap()[0].command, "edit 1");
+        drop(reopened);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
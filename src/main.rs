@@ -1,32 +1,261 @@
+mod backup;
+mod bootstrap;
+mod completion;
+mod config;
+mod crypto;
+mod csv;
+mod dates;
+mod fuzzy;
+mod help;
+mod jira;
+mod kv;
+mod markdown;
+mod outlook;
+mod picker;
+mod pomodoro;
+mod remote;
+mod shell;
+mod storage;
+mod view;
+mod xml;
+
+use chrono::DateTime;
+use chrono::Days;
+use chrono::Local;
+use chrono::Months;
+use chrono::NaiveDate;
+use chrono::NaiveDateTime;
+use chrono::TimeDelta;
+use chrono::Utc;
+use clap::CommandFactory;
+use clap::FromArgMatches;
+use clap::Parser;
+use completion::TodoreHelper;
+use config::Config;
+use crypto::Cipher;
+use dates::format_duration;
+use dates::parse_date;
+use dates::parse_datetime;
+use dates::parse_duration;
+use kv::KvStorage;
+use remote::RemoteStorage;
+use rustyline::Editor;
+use rustyline::error::ReadlineError;
+use rustyline::history::DefaultHistory;
 use serde::Deserialize;
 use serde::Serialize;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::env;
 use std::fmt;
 use std::fs;
+use std::hash::BuildHasher;
+use std::hash::Hasher;
+use std::hash::RandomState;
 use std::io;
+use std::io::IsTerminal;
+use std::io::Write;
+use std::ops::ControlFlow;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process;
 use std::str::FromStr;
+use std::sync::LazyLock;
+use std::time::Duration;
+use std::time::Instant;
+use storage::BackgroundSave;
+use storage::Event;
+use storage::FileLock;
+use storage::FileStorage;
+use storage::Snapshot;
+use storage::Storage;
 
 trait Formatter {
     fn format(&self, tasks: &TaskList) -> Result<String, Box<dyn std::error::Error>>;
+
+    /// Writes the formatted tasks to `out`. Formatters that can produce their output bit
+    /// by bit override this to stream it instead of building it whole.
+    fn write(
+        &self,
+        tasks: &TaskList,
+        out: &mut dyn Write,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        out.write_all(self.format(tasks)?.as_bytes())?;
+        Ok(())
+    }
+
+    /// Applies the settings in config.toml that change this format's output.
+    fn configure(&mut self, _config: &Config) {}
+
+    /// Reads tasks written in this format, for `import`. Most formats are export only.
+    fn parse(&self, _text: &str) -> Result<TaskList, Box<dyn std::error::Error>> {
+        Err("Files in this format can't be imported".into())
+    }
+
+    /// Whether [`parse`](Formatter::parse) is implemented, for `import`'s usage.
+    fn imports(&self) -> bool {
+        false
+    }
 }
 
-struct PlaintextFormatter;
+/// One task per line. Pretty lines, with `pretty_plaintext` set, trade the
+/// `<id>: <description>\t<status>` lines `import` reads for status symbols that are
+/// easier to scan.
+struct PlaintextFormatter {
+    pretty: bool,
+    /// The `plaintext_format` line layout, which takes precedence over `pretty`.
+    layout: Option<String>,
+    today: NaiveDate,
+}
 
 impl Formatter for PlaintextFormatter {
     fn format(&self, tasks: &TaskList) -> Result<String, Box<dyn std::error::Error>> {
         Ok(tasks
-            .tasks
-            .iter()
-            .map(|task| format!("{}: {}\t{}", task.id, task.description, task.status))
-            .collect::<Vec<_>>()
+            .ordered()
+            .into_iter()
+            .map(|task| match &self.layout {
+                Some(layout) => layout_line(layout, tasks, task),
+                None if self.pretty => Ok(self.pretty_line(tasks, task)),
+                None => Ok(tasks.line(task)),
+            })
+            .collect::<Result<Vec<_>, _>>()?
             .join("\n"))
     }
+
+    fn configure(&mut self, config: &Config) {
+        self.pretty = config.pretty_plaintext;
+        self.layout = config.plaintext_format.clone();
+    }
+
+    fn parse(&self, text: &str) -> Result<TaskList, Box<dyn std::error::Error>> {
+        Ok(parse_plaintext(text)?)
+    }
+
+    fn imports(&self) -> bool {
+        true
+    }
 }
 
 impl PlaintextFormatter {
     fn new() -> Self {
-        Self
+        Self {
+            pretty: false,
+            layout: None,
+            today: Local::now().date_naive(),
+        }
+    }
+
+    /// `☐ 3 Buy milk ⚑⚑ @shop +errand ⏰ 2025-03-01`: a symbol for the status, a flag
+    /// per priority level and an alarm clock in front of an overdue date.
+    fn pretty_line(&self, tasks: &TaskList, task: &Task) -> String {
+        let symbol = match task.status {
+            TaskStatus::NotStarted => '☐',
+            TaskStatus::InProgress => '◐',
+            TaskStatus::Waiting => '⧗',
+            TaskStatus::Completed => '☑',
+        };
+        let mut line = format!("{} {} {}", symbol, task.id, task.description);
+        if let Some(priority) = task.priority {
+            let flags = match priority {
+                Priority::Low => 1,
+                Priority::Medium => 2,
+                Priority::High => 3,
+            };
+            line.push_str(&format!(" {}", "⚑".repeat(flags)));
+        }
+        if let Some(context) = &task.context {
+            line.push_str(&format!(" @{}", context));
+        }
+        for tag in &task.tags {
+            line.push_str(&format!(" +{}", tag));
+        }
+        if let Some(due) = task.due {
+            if due < self.today && task.status != TaskStatus::Completed {
+                line.push_str(&format!(" ⏰ {}", due));
+            } else {
+                line.push_str(&format!(" (due {})", due));
+            }
+        }
+        // ☑ already says a completed task is all done.
+        if let Some(progress) = tasks
+            .progress(task)
+            .filter(|_| task.status != TaskStatus::Completed)
+        {
+            line.push_str(&format!(" {}", progress_bar(progress)));
+        }
+        line
     }
 }
+
+/// A task's line laid out by a `plaintext_format` string such as
+/// `{id} [{status:short}] {description} (due {due})`. The placeholders are listed in
+/// `help export`; fields a task doesn't have are left empty, and `{{` and `}}` are
+/// literal braces.
+fn layout_line(layout: &str, tasks: &TaskList, task: &Task) -> Result<String, String> {
+    let text = |value: &Option<String>| value.clone().unwrap_or_default();
+    let date = |date: Option<NaiveDate>| date.map(|date| date.to_string()).unwrap_or_default();
+    let mut line = String::new();
+    let mut chars = layout.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                line.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                line.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => name.push(c),
+                        None => return Err("Unclosed { in plaintext_format".into()),
+                    }
+                }
+                let value = match name.as_str() {
+                    "id" => task.id.to_string(),
+                    "description" => task.description.clone(),
+                    "status" => task.status.to_string(),
+                    "status:short" => match task.status {
+                        TaskStatus::NotStarted => "ns",
+                        TaskStatus::InProgress => "ip",
+                        TaskStatus::Waiting => "w",
+                        TaskStatus::Completed => "c",
+                    }
+                    .to_string(),
+                    "priority" => task.priority.map(|p| p.to_string()).unwrap_or_default(),
+                    "due" => date(task.due),
+                    "scheduled" => date(task.scheduled),
+                    "project" => text(&task.project),
+                    "context" => text(&task.context),
+                    "location" => text(&task.location),
+                    "tags" => task.tags.join(" "),
+                    "parent" => task.parent.map(|p| p.to_string()).unwrap_or_default(),
+                    "progress" => tasks
+                        .progress(task)
+                        .map(|progress| format!("{}%", progress))
+                        .unwrap_or_default(),
+                    _ => {
+                        return Err(format!(
+                            "Unknown placeholder {{{}}} in plaintext_format",
+                            name
+                        ));
+                    }
+                };
+                line.push_str(&value);
+            }
+            c => line.push(c),
+        }
+    }
+    Ok(line)
+}
+
 impl JsonFormatter {
     fn new() -> Self {
         Self
@@ -42,7 +271,15 @@ struct JsonFormatter;
 
 impl Formatter for JsonFormatter {
     fn format(&self, tasks: &TaskList) -> Result<String, Box<dyn std::error::Error>> {
-        Ok(serde_json::to_string_pretty(tasks)?)
+        Ok(serde_json::to_string_pretty(&tasks.sorted())?)
+    }
+
+    fn parse(&self, text: &str) -> Result<TaskList, Box<dyn std::error::Error>> {
+        Ok(serde_json::from_str(text)?)
+    }
+
+    fn imports(&self) -> bool {
+        true
     }
 }
 
@@ -50,454 +287,8109 @@ struct YamlFormatter;
 
 impl Formatter for YamlFormatter {
     fn format(&self, tasks: &TaskList) -> Result<String, Box<dyn std::error::Error>> {
-        Ok(serde_yaml::to_string(tasks)?)
+        Ok(serde_yaml::to_string(&tasks.sorted())?)
     }
-}
 
-#[derive(Debug, Serialize, Deserialize)]
-struct Task {
-    id: u32,
-    description: String,
-    status: TaskStatus,
-}
+    fn parse(&self, text: &str) -> Result<TaskList, Box<dyn std::error::Error>> {
+        Ok(serde_yaml::from_str(text)?)
+    }
 
-impl Task {
-    fn new(id: u32, description: String) -> Self {
-        Self {
-            id,
-            description,
-            status: TaskStatus::NotStarted,
-        }
+    fn imports(&self) -> bool {
+        true
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-enum TaskStatus {
-    NotStarted,
-    InProgress,
-    Completed,
-}
+/// JSON Lines: one task per line as JSON, streamed, for `jq` and other line-based tools.
+struct NdjsonFormatter;
 
-impl FromStr for TaskStatus {
-    type Err = String;
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "not started" | "ns" => Ok(TaskStatus::NotStarted),
-            "in progress" | "ip" => Ok(TaskStatus::InProgress),
-            "completed" | "c" => Ok(TaskStatus::Completed),
-            _ => Err("Error while parsing task status".into()),
+impl Formatter for NdjsonFormatter {
+    fn format(&self, tasks: &TaskList) -> Result<String, Box<dyn std::error::Error>> {
+        let mut out = vec![];
+        self.write(tasks, &mut out)?;
+        Ok(String::from_utf8(out)?)
+    }
+
+    fn write(
+        &self,
+        tasks: &TaskList,
+        out: &mut dyn Write,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        for task in tasks.ordered() {
+            serde_json::to_writer(&mut *out, task)?;
+            out.write_all(b"\n")?;
         }
+        Ok(())
     }
-}
 
-impl fmt::Display for TaskStatus {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let s = match self {
-            TaskStatus::NotStarted => "Not Started",
-            TaskStatus::InProgress => "In Progress",
-            TaskStatus::Completed => "Completed",
-        };
+    fn parse(&self, text: &str) -> Result<TaskList, Box<dyn std::error::Error>> {
+        let mut tasks = TaskList::new();
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            tasks.tasks.push(serde_json::from_str(line)?);
+        }
+        Ok(tasks)
+    }
 
-        write!(f, "{}", s)
+    fn imports(&self) -> bool {
+        true
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct TaskList {
-    tasks: Vec<Task>,
-}
-impl TaskList {
-    fn new() -> Self {
-        TaskList { tasks: vec![] }
-    }
+/// The list as TOML, each task a `[[tasks]]` table, for hand editing.
+struct TomlFormatter;
 
-    fn add(&mut self, task: Task) {
-        self.tasks.push(task);
+impl Formatter for TomlFormatter {
+    fn format(&self, tasks: &TaskList) -> Result<String, Box<dyn std::error::Error>> {
+        Ok(toml::to_string_pretty(&tasks.sorted())?)
     }
 
-    fn remove(&mut self, task_id: u32) {
-        self.tasks.retain(|task| task.id != task_id);
+    fn parse(&self, text: &str) -> Result<TaskList, Box<dyn std::error::Error>> {
+        Ok(toml::from_str(text)?)
     }
 
-    fn update_status(&mut self, task_id: u32, new_status: TaskStatus) -> Result<(), String> {
-        if let Some(task) = self.tasks.iter_mut().find(|task| task.id == task_id) {
-            task.status = new_status;
-            Ok(())
-        } else {
-            Err(format!("Task with id {} was not found", task_id))
-        }
-    }
-    fn update_description(&mut self, task_id: u32, new_description: String) -> Result<(), String> {
-        if let Some(task) = self.tasks.iter_mut().find(|task| task.id == task_id) {
-            task.description = new_description;
-            Ok(())
-        } else {
-            Err(format!("Task with id {} was not found", task_id))
-        }
+    fn imports(&self) -> bool {
+        true
     }
+}
 
-    fn export_to_string<T: Formatter>(
-        &self,
-        formatter: &dyn Formatter,
-    ) -> Result<String, Box<dyn std::error::Error>> {
-        formatter.format(self)
-    }
+/// One row per task with the fields most useful in a spreadsheet; tags are space separated.
+struct CsvFormatter;
 
-    fn import(&mut self, tasks: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let imported: TaskList = serde_json::from_str(tasks)?;
-        self.tasks = imported.tasks;
-        Ok(())
+impl Formatter for CsvFormatter {
+    fn format(&self, tasks: &TaskList) -> Result<String, Box<dyn std::error::Error>> {
+        let mut out = String::from("id,description,status,due,priority,project,context,tags\n");
+        for task in tasks.ordered() {
+            let fields = [
+                task.id.to_string(),
+                task.description.clone(),
+                task.status.to_string(),
+                task.due.map(|due| due.to_string()).unwrap_or_default(),
+                task.priority
+                    .map(|priority| priority.to_string())
+                    .unwrap_or_default(),
+                task.project.clone().unwrap_or_default(),
+                task.context.clone().unwrap_or_default(),
+                task.tags.join(" "),
+            ];
+            let row: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+            out.push_str(&row.join(","));
+            out.push('\n');
+        }
+        Ok(out)
     }
 }
 
-#[derive(Debug)]
-enum Command {
-    Add {
-        val: String,
-    },
-    Remove {
-        id: u32,
-    },
-    Update {
-        id: u32,
-        new_val: String,
-        field: TaskField,
-    },
-    Export {
-        format: Format,
-        out_file: String,
-    },
-    Quit,
+/// Aligned columns of id, status, due date, tags and description, cut to fit `width` with
+/// an ellipsis, and optionally boxed in with box-drawing characters.
+struct TableFormatter {
+    borders: bool,
+    width: usize,
 }
 
-#[derive(Debug)]
-enum Format {
-    Json,
-    Yaml,
-    Plaintext,
+impl TableFormatter {
+    /// As wide as the terminal, or 100 columns when stdout isn't one.
+    fn new(borders: bool) -> Self {
+        let width = io::stdout()
+            .is_terminal()
+            .then(crossterm::terminal::size)
+            .and_then(Result::ok)
+            .map_or(100, |(columns, _)| columns as usize);
+        TableFormatter { borders, width }
+    }
 }
 
-impl FromStr for Format {
-    type Err = String;
+impl Formatter for TableFormatter {
+    fn format(&self, tasks: &TaskList) -> Result<String, Box<dyn std::error::Error>> {
+        let header = ["ID", "Status", "Due", "Tags", "Description"].map(String::from);
+        let mut rows = vec![header];
+        for task in tasks.ordered() {
+            rows.push([
+                task.id.to_string(),
+                task.status.to_string(),
+                task.due.map(|due| due.to_string()).unwrap_or_default(),
+                task.tags.join(" "),
+                task.description.replace('\n', " "),
+            ]);
+        }
+        let widest = |column: usize| {
+            rows.iter()
+                .map(|row| row[column].chars().count())
+                .max()
+                .unwrap_or(0)
+        };
+        let mut widths = [widest(0), widest(1), widest(2), widest(3).min(20), 0];
+        let separators = if self.borders { 16 } else { 8 };
+        let rest = self
+            .width
+            .saturating_sub(widths.iter().sum::<usize>() + separators);
+        widths[4] = widest(4).min(rest.max(11));
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "j" | "json" => Ok(Format::Json),
-            "y" | "yaml" => Ok(Format::Yaml),
-            "p" | "plaintext" => Ok(Format::Plaintext),
-            _ => Err("Invalid export format.".into()),
+        let cells = |row: &[String; 5]| -> Vec<String> {
+            row.iter()
+                .zip(widths)
+                .map(|(cell, width)| format!("{:<width$}", truncate(cell, width)))
+                .collect()
+        };
+        let rule = |left: &str, middle: &str, right: &str| {
+            let lines: Vec<String> = widths.iter().map(|width| "─".repeat(width + 2)).collect();
+            format!("{}{}{}", left, lines.join(middle), right)
+        };
+        let mut lines = vec![];
+        if self.borders {
+            lines.push(rule("┌", "┬", "┐"));
+        }
+        for (i, row) in rows.iter().enumerate() {
+            if self.borders {
+                lines.push(format!("│ {} │", cells(row).join(" │ ")));
+                if i == 0 {
+                    lines.push(rule("├", "┼", "┤"));
+                }
+            } else {
+                lines.push(cells(row).join("  ").trim_end().to_string());
+            }
         }
+        if self.borders {
+            lines.push(rule("└", "┴", "┘"));
+        }
+        Ok(lines.join("\n"))
     }
 }
 
-#[derive(Debug)]
-enum TaskField {
-    Description,
-    Status,
+/// Renders the tasks into a user's Jinja-style template, given `tasks`, each as stored
+/// with its status also spelled out as `status_text`, and `today`. Templates named
+/// `.html` or `.xml` escape what they print.
+struct TemplateFormatter {
+    name: String,
+    source: String,
+    today: NaiveDate,
 }
 
-impl FromStr for TaskField {
-    type Err = String;
+impl TemplateFormatter {
+    fn load(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let source =
+            fs::read_to_string(path).map_err(|err| format!("Can't read {}: {}", path, err))?;
+        Ok(TemplateFormatter {
+            name: path.to_string(),
+            source,
+            today: Local::now().date_naive(),
+        })
+    }
+}
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "description" | "d" => Ok(TaskField::Description),
-            "status" | "s" => Ok(TaskField::Status),
-            _ => Err("Invalid field argument".into()),
+impl Formatter for TemplateFormatter {
+    fn format(&self, tasks: &TaskList) -> Result<String, Box<dyn std::error::Error>> {
+        let mut records = vec![];
+        for task in tasks.ordered() {
+            let mut record = serde_json::to_value(task)?;
+            record["status_text"] = task.status.to_string().into();
+            records.push(record);
         }
+        let context = serde_json::json!({ "tasks": records, "today": self.today });
+        let env = minijinja::Environment::new();
+        let template = env.template_from_named_str(&self.name, &self.source)?;
+        Ok(template.render(minijinja::value::Serde(context))?)
     }
 }
 
-impl Command {
-    fn from_str(val: &str) -> Result<Self, Box<dyn std::error::Error>> {
-        let parts: Vec<_> = val.split(" ").collect();
-        match parts[0] {
-            "a" | "add" => {
-                if parts.len() < 2 {
-                    return Err("Invalid arguments for add.".into());
-                }
-                let val = parts[1].into();
-                Ok(Command::Add { val })
-            }
-            "r" | "remove" => {
-                if parts.len() < 2 {
-                    return Err("Invalid arguments for remove.".into());
-                }
-                let id = parts[1].parse::<u32>()?;
-                Ok(Command::Remove { id })
-            }
-            "u" | "update" => {
-                if parts.len() < 4 {
-                    return Err("Invalid arguments for update.".into());
-                }
-                let id = parts[1].parse::<u32>()?;
-                let field = TaskField::from_str(&parts[2].to_lowercase())?;
-                let new_val = parts[3].into();
+/// Pipes the tasks as JSON, as `export json` writes them, to a shell command and takes
+/// what it prints, so formats todore doesn't know can come from other programs.
+struct ExecFormatter {
+    command: String,
+}
 
-                Ok(Command::Update { id, new_val, field })
-            }
-            "q" | "quit" => Ok(Command::Quit),
-            "e" | "export" => {
-                if parts.len() < 3 {
-                    return Err("Invalid arguments for export.".into());
-                }
-                let format = Format::from_str(parts[1])?;
-                Ok(Command::Export {
-                    format,
-                    out_file: parts[2].into(),
-                })
-            }
-            _ => Err("Invalid argument.".into()),
+impl Formatter for ExecFormatter {
+    fn format(&self, tasks: &TaskList) -> Result<String, Box<dyn std::error::Error>> {
+        let json = JsonFormatter::new().format(tasks)?;
+        let mut child = shell(&self.command)
+            .stdin(process::Stdio::piped())
+            .stdout(process::Stdio::piped())
+            .spawn()
+            .map_err(|err| format!("Can't run {}: {}", self.command, err))?;
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        // Written from another thread, so a program that prints before reading all of
+        // its input doesn't block on a full pipe. One that stops reading early is fine.
+        let output = std::thread::scope(|scope| {
+            scope.spawn(move || stdin.write_all(json.as_bytes()));
+            child.wait_with_output()
+        })?;
+        if !output.status.success() {
+            return Err(format!("{} exited with {}", self.command, output.status).into());
         }
+        Ok(String::from_utf8(output.stdout)?)
     }
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let mut task_list = TaskList::new();
-    println!("Welcome to the Todore in-memory TODO list!");
-
-    // lets load the tasks from a file in a shared location, if it exists
-    // for testing purposes, lets make this file directly under the pwd
-    let tasks_file = "tasks.json";
-    let existing_tasks = fs::read_to_string(tasks_file)?;
-    task_list.import(&existing_tasks)?;
-
-    let mut input = String::new();
-    let jf = JsonFormatter::new();
-    let yf = YamlFormatter::new();
-    let ptf = PlaintextFormatter::new();
-    let mut counter = 0;
-    loop {
-        if !task_list.tasks.is_empty() {
-            println!("Here are your current tasks:");
-            println!("{}", task_list.export_to_string::<JsonFormatter>(&jf)?);
-        }
-        println!("Below are the options:");
-        println!("[a | add] <TODO-item>");
-        println!("[r | remove] <TODO-item-id>");
-        println!("[u | update] <TODO-item-id> [s | status] | [d | description] <new-value>");
-        println!("[e | export] [j | json] | [y | yaml] | [p | plaintext]");
-        println!("[q | quit]");
+/// `text` cut to `width` characters, ending in an ellipsis when anything was cut.
+fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    let mut cut: String = text.chars().take(width.saturating_sub(1)).collect();
+    cut.push('…');
+    cut
+}
 
-        io::stdin().read_line(&mut input)?;
+/// The list as `- [ ] description` checklists under a heading per status, and per project
+/// above those once any task has one, for README files and notes apps.
+struct MarkdownFormatter;
 
-        println!("You chose: {}", input.trim());
-        let command = Command::from_str(&input.trim().to_lowercase())?;
-        match command {
-            Command::Add { val } => {
-                task_list.add(Task::new(counter, val));
-                counter += 1;
+impl Formatter for MarkdownFormatter {
+    fn format(&self, tasks: &TaskList) -> Result<String, Box<dyn std::error::Error>> {
+        let ordered = tasks.ordered();
+        let mut projects: Vec<Option<&str>> =
+            ordered.iter().map(|task| task.project.as_deref()).collect();
+        projects.sort();
+        projects.dedup();
+        let by_project = projects != [None];
+        let mut sections = vec![];
+        for project in projects {
+            let mut section = vec![];
+            if by_project {
+                section.push(format!("## {}", project.unwrap_or("No project")));
             }
-            Command::Remove { id } => task_list.remove(id),
-            Command::Update { id, new_val, field } => match field {
-                TaskField::Description => task_list.update_description(id, new_val)?,
-                TaskField::Status => {
-                    task_list.update_status(id, TaskStatus::from_str(&new_val)?)?
-                }
-            },
-            Command::Quit => break,
-            Command::Export { format, out_file } => match format {
-                Format::Json => {
-                    let content = task_list.export_to_string::<JsonFormatter>(&jf)?;
-                    fs::write(out_file, content)?;
-                }
-                Format::Yaml => {
-                    let content = task_list.export_to_string::<YamlFormatter>(&yf)?;
-                    fs::write(out_file, content)?;
+            for status in [
+                TaskStatus::NotStarted,
+                TaskStatus::InProgress,
+                TaskStatus::Waiting,
+                TaskStatus::Completed,
+            ] {
+                let items: Vec<String> = ordered
+                    .iter()
+                    .filter(|task| task.project.as_deref() == project && task.status == status)
+                    .map(|task| {
+                        let check = if status == TaskStatus::Completed {
+                            'x'
+                        } else {
+                            ' '
+                        };
+                        format!("- [{}] {}", check, task.description)
+                    })
+                    .collect();
+                if !items.is_empty() {
+                    let level = if by_project { "###" } else { "##" };
+                    section.push(format!("\n{} {}\n", level, status));
+                    section.extend(items);
                 }
-                Format::Plaintext => {
-                    let content = task_list.export_to_string::<PlaintextFormatter>(&ptf)?;
-                    fs::write(out_file, content)?;
-                }
-            },
+            }
+            sections.push(section.join("\n"));
         }
+        Ok(sections.join("\n\n").trim_start().to_string())
+    }
 
-        input.clear();
+    fn parse(&self, text: &str) -> Result<TaskList, Box<dyn std::error::Error>> {
+        Ok(parse_markdown(text))
     }
 
-    Ok(())
+    fn imports(&self) -> bool {
+        true
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_update_status() {
-        let mut list = TaskList::new();
-
-        list.add(Task::new(1, "Test".into()));
-        assert!(matches!(list.tasks.len(), 1));
+/// Reads the checklist items in Markdown such as [`MarkdownFormatter`] writes, numbering
+/// them in order. A heading naming a status applies it to the unchecked items below; any
+/// other heading is the project of the items below it. Checked items are completed.
+fn parse_markdown(text: &str) -> TaskList {
+    let mut tasks = TaskList::new();
+    let mut project = None;
+    let mut status = TaskStatus::NotStarted;
+    for line in text.lines().map(str::trim) {
+        if let Some(heading) = line.strip_prefix('#') {
+            let heading = heading.trim_start_matches('#').trim();
+            match TaskStatus::from_str(&heading.to_lowercase()) {
+                Ok(heading) => status = heading,
+                Err(_) => {
+                    project = Some(heading.to_string()).filter(|p| p != "No project");
+                    status = TaskStatus::NotStarted;
+                }
+            }
+            continue;
+        }
+        let Some((checked, description)) = checklist_item(line) else {
+            continue;
+        };
+        let mut task = Task::new(tasks.tasks.len() as u32, description.to_string());
+        task.project = project.clone();
+        task.status = match status {
+            _ if checked => TaskStatus::Completed,
+            TaskStatus::Completed => TaskStatus::NotStarted,
+            ref status => status.clone(),
+        };
+        tasks.tasks.push(task);
+    }
+    tasks
+}
 
-        list.update_status(1, TaskStatus::InProgress).unwrap();
-        assert!(matches!(list.tasks[0].status, TaskStatus::InProgress));
+/// Whether a `- [ ] item` or `- [x] item` line is checked, and its text.
+fn checklist_item(line: &str) -> Option<(bool, &str)> {
+    let item = line
+        .trim()
+        .strip_prefix(['-', '*', '+'])?
+        .trim_start()
+        .strip_prefix('[')?;
+    match item.split_once(']')? {
+        (" ", description) => Some((false, description.trim())),
+        ("x" | "X", description) => Some((true, description.trim())),
+        _ => None,
     }
+}
 
-    #[test]
-    fn test_update_description() {
-        let mut list = TaskList::new();
+/// A standalone HTML page with a table of the tasks: status badges, overdue rows in red,
+/// and columns that sort when their header is clicked.
+struct HtmlFormatter {
+    today: NaiveDate,
+}
 
-        list.add(Task::new(2, "Test2".into()));
-        let new_description = "Test123";
+const HTML_STYLE: &str = "body{font-family:system-ui,sans-serif;margin:2em;color:#222}
+table{border-collapse:collapse;width:100%}
+th,td{padding:.4em .6em;border-bottom:1px solid #ddd;text-align:left}
+th{cursor:pointer;user-select:none;background:#f4f4f4}
+tr.overdue td{color:#b00020}
+tr.completed td{color:#888}
+.badge{display:inline-block;padding:.1em .6em;border-radius:1em;font-size:.85em;color:#fff}
+.not-started{background:#6c757d}.in-progress{background:#d4a000}
+.waiting{background:#6f42c1}.completed{background:#2e7d32}";
 
-        list.update_description(2, new_description.into()).unwrap();
-        assert_eq!(list.tasks[0].description, new_description);
+const HTML_SCRIPT: &str = "document.querySelectorAll('th').forEach((th, column) => {
+  th.addEventListener('click', () => {
+    const body = th.closest('table').tBodies[0];
+    const key = row => row.cells[column].dataset.sort ?? row.cells[column].textContent;
+    const up = th.dataset.order !== 'up';
+    th.dataset.order = up ? 'up' : 'down';
+    [...body.rows]
+      .sort((a, b) => key(a).localeCompare(key(b), undefined, {numeric: true}) * (up ? 1 : -1))
+      .forEach(row => body.appendChild(row));
+  });
+});";
+
+impl Formatter for HtmlFormatter {
+    fn format(&self, tasks: &TaskList) -> Result<String, Box<dyn std::error::Error>> {
+        let mut rows = vec![];
+        for task in tasks.ordered() {
+            let status = task.status.to_string();
+            let badge = status.to_lowercase().replace(' ', "-");
+            let class = match task.status {
+                TaskStatus::Completed => " class=\"completed\"",
+                _ if task.due.is_some_and(|due| due < self.today) => " class=\"overdue\"",
+                _ => "",
+            };
+            let rank = match task.priority {
+                Some(Priority::High) => 0,
+                Some(Priority::Medium) => 1,
+                Some(Priority::Low) => 2,
+                None => 3,
+            };
+            rows.push(format!(
+                "<tr{}><td>{}</td><td>{}</td><td><span class=\"badge {}\">{}</span></td>\
+                 <td>{}</td><td data-sort=\"{}\">{}</td><td>{}</td><td>{}</td></tr>",
+                class,
+                task.id,
+                html_escape(&task.description),
+                badge,
+                status,
+                task.due.map(|due| due.to_string()).unwrap_or_default(),
+                rank,
+                task.priority
+                    .map(|priority| priority.to_string())
+                    .unwrap_or_default(),
+                html_escape(task.project.as_deref().unwrap_or_default()),
+                html_escape(&task.tags.join(" ")),
+            ));
+        }
+        Ok(format!(
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>Tasks</title>\n<style>\n{}\n</style>\n</head>\n<body>\n<h1>Tasks</h1>\n\
+             <table>\n<thead><tr><th>Id</th><th>Description</th><th>Status</th><th>Due</th>\
+             <th>Priority</th><th>Project</th><th>Tags</th></tr></thead>\n<tbody>\n{}\n</tbody>\n\
+             </table>\n<script>\n{}\n</script>\n</body>\n</html>\n",
+            HTML_STYLE,
+            rows.join("\n"),
+            HTML_SCRIPT
+        ))
     }
+}
 
-    #[test]
-    fn test_remove() {
-        let mut list = TaskList::new();
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
 
-        list.add(Task::new(1, "Test1".into()));
-        assert_eq!(list.tasks.len(), 1);
+/// An iCalendar file of the tasks with a due date, for calendar apps. Each becomes an
+/// all-day VEVENT on its due date, unless it's completed, for calendars such as Google's
+/// that ignore to-dos, and a VTODO for apps that show them; recurrences become RRULEs.
+struct IcsFormatter {
+    stamp: DateTime<Utc>,
+}
 
-        list.remove(1);
-        assert_eq!(list.tasks.len(), 0);
+impl Formatter for IcsFormatter {
+    fn format(&self, tasks: &TaskList) -> Result<String, Box<dyn std::error::Error>> {
+        let stamp = self.stamp.format("%Y%m%dT%H%M%SZ").to_string();
+        let mut lines = vec![
+            "BEGIN:VCALENDAR".to_string(),
+            "VERSION:2.0".to_string(),
+            "PRODID:-//todore//todore//EN".to_string(),
+            "CALSCALE:GREGORIAN".to_string(),
+        ];
+        for task in tasks.ordered() {
+            let Some(due) = task.due else {
+                continue;
+            };
+            let date = |date: NaiveDate| date.format("%Y%m%d").to_string();
+            let mut common = vec![
+                format!("DTSTAMP:{}", stamp),
+                format!("SUMMARY:{}", ics_text(&task.description)),
+            ];
+            if let Some(notes) = &task.notes {
+                common.push(format!("DESCRIPTION:{}", ics_text(notes)));
+            }
+            if !task.tags.is_empty() {
+                let tags: Vec<String> = task.tags.iter().map(|tag| ics_text(tag)).collect();
+                common.push(format!("CATEGORIES:{}", tags.join(",")));
+            }
+            if let Some(recurrence) = &task.recurrence {
+                common.push(format!(
+                    "RRULE:FREQ={}",
+                    recurrence.every.to_string().to_uppercase()
+                ));
+                if !recurrence.exceptions.is_empty() {
+                    let dates: Vec<String> =
+                        recurrence.exceptions.iter().map(|d| date(*d)).collect();
+                    common.push(format!("EXDATE;VALUE=DATE:{}", dates.join(",")));
+                }
+            }
+
+            if task.status != TaskStatus::Completed {
+                lines.push("BEGIN:VEVENT".to_string());
+                lines.push(format!("UID:todore-event-{}", task.id));
+                lines.push(format!("DTSTART;VALUE=DATE:{}", date(due)));
+                let next = due.succ_opt().unwrap_or(due);
+                lines.push(format!("DTEND;VALUE=DATE:{}", date(next)));
+                lines.extend(common.iter().cloned());
+                lines.push("END:VEVENT".to_string());
+            }
+
+            lines.push("BEGIN:VTODO".to_string());
+            lines.push(format!("UID:todore-todo-{}", task.id));
+            lines.push(format!("DUE;VALUE=DATE:{}", date(due)));
+            let status = match task.status {
+                TaskStatus::NotStarted | TaskStatus::Waiting => "NEEDS-ACTION",
+                TaskStatus::InProgress => "IN-PROCESS",
+                TaskStatus::Completed => "COMPLETED",
+            };
+            lines.push(format!("STATUS:{}", status));
+            if let Some(at) = task.completed_at {
+                let at = at.with_timezone(&Utc).format("%Y%m%dT%H%M%SZ");
+                lines.push(format!("COMPLETED:{}", at));
+            }
+            if let Some(priority) = task.priority {
+                let priority = match priority {
+                    Priority::High => 1,
+                    Priority::Medium => 5,
+                    Priority::Low => 9,
+                };
+                lines.push(format!("PRIORITY:{}", priority));
+            }
+            lines.extend(common);
+            lines.push("END:VTODO".to_string());
+        }
+        lines.push("END:VCALENDAR".to_string());
+        let lines: Vec<String> = lines.iter().map(|line| ics_fold(line)).collect();
+        Ok(lines.join("\r\n") + "\r\n")
+    }
+}
+
+/// The list as an org-mode outline: `* TODO description :tag:` headlines with subtasks
+/// nested under their parent, SCHEDULED and DEADLINE lines, and notes as the body.
+struct OrgFormatter;
+
+impl Formatter for OrgFormatter {
+    fn format(&self, tasks: &TaskList) -> Result<String, Box<dyn std::error::Error>> {
+        let ordered = tasks.ordered();
+        let mut written = BTreeSet::new();
+        let mut out = String::new();
+        let roots = ordered
+            .iter()
+            .filter(|task| task.parent.is_none_or(|parent| tasks.task(parent).is_err()));
+        // Tasks whose parents form a cycle have no root above them, so they come last.
+        for task in roots.chain(&ordered) {
+            org_headline(&ordered, task, 1, &mut written, &mut out);
+        }
+        Ok(out)
+    }
+
+    fn parse(&self, text: &str) -> Result<TaskList, Box<dyn std::error::Error>> {
+        Ok(parse_org(text))
+    }
+
+    fn imports(&self) -> bool {
+        true
+    }
+}
+
+/// Writes `task` as a headline at `level`, followed by its subtasks one level down.
+fn org_headline(
+    ordered: &[&Task],
+    task: &Task,
+    level: usize,
+    written: &mut BTreeSet<u32>,
+    out: &mut String,
+) {
+    if !written.insert(task.id) {
+        return;
+    }
+    let keyword = match task.status {
+        TaskStatus::NotStarted => "TODO",
+        TaskStatus::InProgress => "STARTED",
+        TaskStatus::Waiting => "WAITING",
+        TaskStatus::Completed => "DONE",
+    };
+    let mut headline = format!("{} {}", "*".repeat(level), keyword);
+    if let Some(priority) = task.priority {
+        let cookie = match priority {
+            Priority::High => 'A',
+            Priority::Medium => 'B',
+            Priority::Low => 'C',
+        };
+        headline.push_str(&format!(" [#{}]", cookie));
+    }
+    headline.push(' ');
+    headline.push_str(&task.description.replace('\n', " "));
+    if !task.tags.is_empty() {
+        let tags: Vec<String> = task
+            .tags
+            .iter()
+            .map(|tag| tag.replace(|c: char| !c.is_alphanumeric() && !"_@#%".contains(c), "_"))
+            .collect();
+        headline.push_str(&format!(" :{}:", tags.join(":")));
+    }
+    out.push_str(&headline);
+    out.push('\n');
+    let indent = " ".repeat(level + 1);
+    let stamp = |date: NaiveDate| date.format("<%Y-%m-%d %a>").to_string();
+    let mut planning = vec![];
+    if let Some(scheduled) = task.scheduled {
+        planning.push(format!("SCHEDULED: {}", stamp(scheduled)));
+    }
+    if let Some(due) = task.due {
+        planning.push(format!("DEADLINE: {}", stamp(due)));
+    }
+    if !planning.is_empty() {
+        out.push_str(&format!("{}{}\n", indent, planning.join(" ")));
+    }
+    for line in task.notes.iter().flat_map(|notes| notes.lines()) {
+        out.push_str(format!("{}{}", indent, line).trim_end());
+        out.push('\n');
+    }
+    for child in ordered.iter().filter(|child| child.parent == Some(task.id)) {
+        org_headline(ordered, child, level + 1, written, out);
+    }
+}
+
+/// Reads the TODO headlines of an org file such as [`OrgFormatter`] writes, numbering
+/// them in order. Headlines without a TODO keyword are skipped; a task's subtasks are the
+/// TODO headlines nested under it, and the text below a headline is its notes.
+fn parse_org(text: &str) -> TaskList {
+    let mut tasks = TaskList::new();
+    // The level and id of each task enclosing the current headline.
+    let mut outline: Vec<(usize, u32)> = vec![];
+    let mut current = None;
+    for line in text.lines() {
+        let level = line.chars().take_while(|c| *c == '*').count();
+        if level > 0 && line[level..].starts_with(' ') {
+            outline.retain(|(above, _)| *above < level);
+            current = None;
+            let mut words = line[level..].split_whitespace().peekable();
+            let status = match words.next() {
+                Some("TODO") => TaskStatus::NotStarted,
+                Some("STARTED" | "NEXT") => TaskStatus::InProgress,
+                Some("WAITING") => TaskStatus::Waiting,
+                Some("DONE") => TaskStatus::Completed,
+                _ => continue,
+            };
+            let priority = match words.peek() {
+                Some(&"[#A]") => Some(Priority::High),
+                Some(&"[#B]") => Some(Priority::Medium),
+                Some(&"[#C]") => Some(Priority::Low),
+                _ => None,
+            };
+            if priority.is_some() {
+                words.next();
+            }
+            let mut words: Vec<&str> = words.collect();
+            let mut tags = vec![];
+            if let Some(last) = words.last()
+                && last.len() > 1
+                && last.starts_with(':')
+                && last.ends_with(':')
+            {
+                tags = last
+                    .split(':')
+                    .filter(|tag| !tag.is_empty())
+                    .map(String::from)
+                    .collect();
+                words.pop();
+            }
+            let id = tasks.tasks.len() as u32;
+            let mut task = Task::new(id, words.join(" "));
+            task.status = status;
+            task.priority = priority;
+            task.tags = tags;
+            task.parent = outline.last().map(|(_, parent)| *parent);
+            tasks.tasks.push(task);
+            outline.push((level, id));
+            current = Some(tasks.tasks.len() - 1);
+            continue;
+        }
+        let Some(index) = current else {
+            continue;
+        };
+        let task = &mut tasks.tasks[index];
+        let trimmed = line.trim();
+        let date_after = |keyword: &str| {
+            let (_, rest) = trimmed.split_once(keyword)?;
+            let rest = rest.trim_start().strip_prefix(['<', '['])?;
+            NaiveDate::parse_from_str(rest.get(..10)?, "%Y-%m-%d").ok()
+        };
+        if trimmed.starts_with("SCHEDULED:") || trimmed.starts_with("DEADLINE:") {
+            task.scheduled = date_after("SCHEDULED:");
+            task.due = date_after("DEADLINE:");
+            continue;
+        }
+        let notes = task.notes.get_or_insert_default();
+        if !notes.is_empty() || !trimmed.is_empty() {
+            notes.push_str(trimmed);
+            notes.push('\n');
+        }
+    }
+    for task in &mut tasks.tasks {
+        task.notes = task
+            .notes
+            .take()
+            .map(|notes| notes.trim_end().to_string())
+            .filter(|notes| !notes.is_empty());
+    }
+    tasks
+}
+
+/// Escapes an iCalendar TEXT value.
+fn ics_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Splits a content line into lines of at most 75 bytes, each continuation starting
+/// with a space, as iCalendar requires.
+fn ics_fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded
+}
+
+/// Quotes a CSV field when it contains a comma, quote or line break.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Task {
+    id: u32,
+    description: String,
+    status: TaskStatus,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    attachments: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    context: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    due: Option<NaiveDate>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    scheduled: Option<NaiveDate>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    reminders: Vec<Reminder>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    snoozes: u32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    history: Vec<TaskEvent>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    waiting_on: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    points: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sprint: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    milestone: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    touched: Option<DateTime<Local>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    progress: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    parent: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    recurrence: Option<Recurrence>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    relations: Vec<Relation>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    energy: Option<Energy>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    location: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    color: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    icon: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    created_at: Option<DateTime<Local>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    completed_at: Option<DateTime<Local>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    completed_by: Option<String>,
+    /// Seconds from creation to completion.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cycle_time: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    project: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    priority: Option<Priority>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    notes: Option<String>,
+    /// Manual ordering set by `move`; tasks without a position follow in insertion order.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    position: Option<u32>,
+    /// When the running timer was started by `start --track`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    started_at: Option<DateTime<Local>>,
+    /// Tracked time in seconds, not counting a running timer.
+    #[serde(default, skip_serializing_if = "is_zero_i64")]
+    time_spent: i64,
+    /// A unique name usable wherever the task's id is, set by `alias`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    alias: Option<String>,
+}
+
+/// The ANSI foreground colour code for a colour name.
+fn ansi_color(name: &str) -> Option<u8> {
+    match name {
+        "black" => Some(30),
+        "red" => Some(31),
+        "green" => Some(32),
+        "yellow" => Some(33),
+        "blue" => Some(34),
+        "magenta" => Some(35),
+        "cyan" => Some(36),
+        "white" => Some(37),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+enum Priority {
+    Low,
+    Medium,
+    High,
+}
+
+impl FromStr for Priority {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "low" | "l" => Ok(Priority::Low),
+            "medium" | "m" => Ok(Priority::Medium),
+            "high" | "h" => Ok(Priority::High),
+            _ => Err("Invalid priority, expected low, medium or high".into()),
+        }
+    }
+}
+
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Priority::Low => "low",
+            Priority::Medium => "medium",
+            Priority::High => "high",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+enum Energy {
+    Low,
+    Medium,
+    High,
+}
+
+impl FromStr for Energy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "low" | "l" => Ok(Energy::Low),
+            "medium" | "m" => Ok(Energy::Medium),
+            "high" | "h" => Ok(Energy::High),
+            _ => Err("Invalid energy level, expected low, medium or high".into()),
+        }
+    }
+}
+
+impl fmt::Display for Energy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Energy::Low => "low",
+            Energy::Medium => "medium",
+            Energy::High => "high",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Relation {
+    kind: RelationKind,
+    task: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+enum RelationKind {
+    RelatesTo,
+    Duplicates,
+}
+
+impl FromStr for RelationKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "relates-to" | "rel" => Ok(RelationKind::RelatesTo),
+            "duplicates" | "dup" => Ok(RelationKind::Duplicates),
+            _ => Err("Invalid relation, expected relates-to or duplicates".into()),
+        }
+    }
+}
+
+impl fmt::Display for RelationKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            RelationKind::RelatesTo => "relates to",
+            RelationKind::Duplicates => "duplicates",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Recurrence {
+    every: Frequency,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    exceptions: Vec<NaiveDate>,
+}
+
+impl Recurrence {
+    /// The first occurrence after `date` that isn't an exception date.
+    fn next_after(&self, date: NaiveDate) -> Option<NaiveDate> {
+        let mut next = date;
+        loop {
+            next = match self.every {
+                Frequency::Daily => next.checked_add_days(Days::new(1))?,
+                Frequency::Weekly => next.checked_add_days(Days::new(7))?,
+                Frequency::Monthly => next.checked_add_months(Months::new(1))?,
+            };
+            if !self.exceptions.contains(&next) {
+                return Some(next);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl FromStr for Frequency {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "daily" | "day" => Ok(Frequency::Daily),
+            "weekly" | "week" => Ok(Frequency::Weekly),
+            "monthly" | "month" => Ok(Frequency::Monthly),
+            _ => Err("Invalid recurrence, expected daily, weekly or monthly".into()),
+        }
+    }
+}
+
+impl fmt::Display for Frequency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Frequency::Daily => "daily",
+            Frequency::Weekly => "weekly",
+            Frequency::Monthly => "monthly",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TaskEvent {
+    at: DateTime<Local>,
+    change: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    from: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    to: Option<String>,
+}
+
+impl fmt::Display for TaskEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.at.format("%Y-%m-%d %H:%M"), self.change)?;
+        match (&self.from, &self.to) {
+            (Some(from), Some(to)) => write!(f, ": {} -> {}", from, to),
+            (None, Some(to)) => write!(f, ": {}", to),
+            (Some(from), None) => write!(f, ": {} -> none", from),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+fn is_zero(n: &u32) -> bool {
+    *n == 0
+}
+
+fn is_zero_i64(n: &i64) -> bool {
+    *n == 0
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Reminder {
+    at: NaiveDateTime,
+    #[serde(default)]
+    fired: bool,
+}
+
+impl Task {
+    fn new(id: u32, description: String) -> Self {
+        Self {
+            id,
+            description,
+            status: TaskStatus::NotStarted,
+            ..Default::default()
+        }
+    }
+
+    fn record(&mut self, change: &str, from: Option<String>, to: Option<String>) {
+        let now = Local::now();
+        self.touched = Some(now);
+        self.history.push(TaskEvent {
+            at: now,
+            change: change.into(),
+            from,
+            to,
+        });
+    }
+
+    /// An open task that hasn't been touched in `days` days. Tasks from before
+    /// touch tracking existed count as stale.
+    fn is_stale(&self, now: DateTime<Local>, days: u32) -> bool {
+        !matches!(self.status, TaskStatus::Completed)
+            && self
+                .touched
+                .is_none_or(|touched| now - touched > TimeDelta::days(days.into()))
+    }
+
+    /// A copy of this task under a new id with its progress, history and completion reset.
+    fn fresh_copy(&self, id: u32) -> Task {
+        Task {
+            id,
+            status: TaskStatus::NotStarted,
+            snoozes: 0,
+            history: vec![],
+            touched: None,
+            progress: None,
+            created_at: None,
+            completed_at: None,
+            completed_by: None,
+            cycle_time: None,
+            position: None,
+            started_at: None,
+            time_spent: 0,
+            alias: None,
+            ..self.clone()
+        }
+    }
+
+    fn mark_completed(&mut self, at: DateTime<Local>, by: Option<String>) {
+        self.cycle_time = self
+            .created_at
+            .map(|created_at| (at - created_at).num_seconds());
+        self.completed_at = Some(at);
+        self.completed_by = by;
+    }
+
+    /// Adds the running timer, if any, to the tracked time.
+    fn stop_timer(&mut self, now: DateTime<Local>) {
+        if let Some(started_at) = self.started_at.take() {
+            self.time_spent += (now - started_at).num_seconds().max(0);
+            self.record(
+                "timer stopped",
+                None,
+                Some(format_duration(TimeDelta::seconds(self.time_spent))),
+            );
+        }
+    }
+
+    fn clear_completion(&mut self) {
+        self.completed_at = None;
+        self.completed_by = None;
+        self.cycle_time = None;
+    }
+
+    fn is_scheduled_after(&self, today: NaiveDate) -> bool {
+        self.scheduled.is_some_and(|scheduled| scheduled > today)
+    }
+}
+
+impl fmt::Display for Task {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}\t{}", self.id, self.description, self.status)?;
+        if let Some(context) = &self.context {
+            write!(f, " @{}", context)?;
+        }
+        for tag in &self.tags {
+            write!(f, " +{}", tag)?;
+        }
+        if let Some(project) = &self.project {
+            write!(f, " (project {})", project)?;
+        }
+        if let Some(location) = &self.location {
+            write!(f, " (at {})", location)?;
+        }
+        if let Some(scheduled) = self.scheduled {
+            write!(f, " (scheduled {})", scheduled)?;
+        }
+        if let Some(due) = self.due {
+            write!(f, " (due {})", due)?;
+        }
+        if let Some(recurrence) = &self.recurrence {
+            write!(f, " (repeats {})", recurrence.every)?;
+        }
+        if let Some(waiting_on) = &self.waiting_on {
+            write!(f, " (waiting on {})", waiting_on)?;
+        }
+        if let Some(priority) = self.priority {
+            write!(f, " ({} priority)", priority)?;
+        }
+        if let Some(energy) = self.energy {
+            write!(f, " ({} energy)", energy)?;
+        }
+        if let Some(points) = self.points {
+            write!(f, " ({} pts)", points)?;
+        }
+        if let Some(sprint) = &self.sprint {
+            write!(f, " (sprint {})", sprint)?;
+        }
+        if let Some(cycle_time) = self.cycle_time {
+            write!(
+                f,
+                " (done in {}",
+                format_duration(TimeDelta::seconds(cycle_time))
+            )?;
+            if let Some(completed_by) = &self.completed_by {
+                write!(f, " by {}", completed_by)?;
+            }
+            write!(f, ")")?;
+        }
+        if self.snoozes > 0 {
+            write!(f, " (snoozed {}x)", self.snoozes)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+enum TaskStatus {
+    #[default]
+    NotStarted,
+    InProgress,
+    Waiting,
+    Completed,
+}
+
+impl FromStr for TaskStatus {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "not started" | "not-started" | "ns" => Ok(TaskStatus::NotStarted),
+            "in progress" | "in-progress" | "ip" => Ok(TaskStatus::InProgress),
+            "waiting" | "w" => Ok(TaskStatus::Waiting),
+            "completed" | "c" => Ok(TaskStatus::Completed),
+            _ => Err("Error while parsing task status".into()),
+        }
+    }
+}
+
+impl fmt::Display for TaskStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            TaskStatus::NotStarted => "Not Started",
+            TaskStatus::InProgress => "In Progress",
+            TaskStatus::Waiting => "Waiting",
+            TaskStatus::Completed => "Completed",
+        };
+
+        write!(f, "{}", s)
+    }
+}
+
+/// The user-editable part of a task, round-tripped through `$EDITOR` as YAML by `edit`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct TaskEdit {
+    description: String,
+    #[serde(default)]
+    notes: Option<String>,
+    #[serde(default)]
+    status: TaskStatus,
+    #[serde(default)]
+    context: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    project: Option<String>,
+    #[serde(default)]
+    priority: Option<Priority>,
+    #[serde(default)]
+    due: Option<NaiveDate>,
+    #[serde(default)]
+    scheduled: Option<NaiveDate>,
+    #[serde(default)]
+    waiting_on: Option<String>,
+    #[serde(default)]
+    location: Option<String>,
+    #[serde(default)]
+    url: Option<String>,
+}
+
+impl From<&Task> for TaskEdit {
+    fn from(task: &Task) -> Self {
+        TaskEdit {
+            description: task.description.clone(),
+            notes: task.notes.clone(),
+            status: task.status.clone(),
+            context: task.context.clone(),
+            tags: task.tags.clone(),
+            project: task.project.clone(),
+            priority: task.priority,
+            due: task.due,
+            scheduled: task.scheduled,
+            waiting_on: task.waiting_on.clone(),
+            location: task.location.clone(),
+            url: task.url.clone(),
+        }
+    }
+}
+
+/// Opens `text` in `$VISUAL`/`$EDITOR` (falling back to `vi`) and returns the saved result.
+fn edit_in_editor(text: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    let mut words = editor.split_whitespace();
+    let program = words.next().ok_or("$EDITOR is empty")?;
+    let path = env::temp_dir().join(format!("todore-edit-{}.yaml", process::id()));
+    fs::write(&path, text)?;
+    let status = process::Command::new(program)
+        .args(words)
+        .arg(&path)
+        .status();
+    let edited = fs::read_to_string(&path);
+    fs::remove_file(&path)?;
+    if !status?.success() {
+        return Err(format!(
+            "{} exited with an error, the task was left unchanged",
+            program
+        )
+        .into());
+    }
+    Ok(edited?)
+}
+
+/// The task file of another project: `work` means `work.json` in `dir`, the directory
+/// of the task file in use.
+fn project_file(project: &str, dir: &Path) -> PathBuf {
+    if project.ends_with(".json") {
+        dir.join(project)
+    } else {
+        dir.join(format!("{}.json", project))
+    }
+}
+
+/// Asks a yes/no question on the prompt; anything but `y` or `yes` declines.
+fn confirm<H: rustyline::Helper>(
+    editor: &mut Editor<H, DefaultHistory>,
+    question: &str,
+) -> Result<bool, ReadlineError> {
+    match editor.readline(question) {
+        Ok(answer) => Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")),
+        Err(ReadlineError::Interrupted | ReadlineError::Eof) => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+/// Parses an optional update value, where `none` clears the field.
+fn parse_optional<T: FromStr>(s: &str) -> Result<Option<T>, T::Err> {
+    match s {
+        "none" => Ok(None),
+        s => s.parse().map(Some),
+    }
+}
+
+/// Whether output should be coloured: stdout is a terminal, `--no-color` wasn't given
+/// and `NO_COLOR` isn't set (see no-color.org).
+fn color_enabled(no_color: bool) -> bool {
+    !no_color
+        && env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+        && io::stdout().is_terminal()
+}
+
+/// The ANSI style for a task in listings: its own colour if it has one, otherwise red
+/// when overdue, yellow when in progress and dim when completed.
+fn task_style(task: &Task, today: NaiveDate) -> Option<u8> {
+    if let Some(code) = task.color.as_deref().and_then(ansi_color) {
+        return Some(code);
+    }
+    match task.status {
+        TaskStatus::Completed => Some(2),
+        _ if task.due.is_some_and(|due| due < today) => Some(31),
+        TaskStatus::InProgress => Some(33),
+        _ => None,
+    }
+}
+
+/// Formats a task for the terminal, rendering Markdown in its description when `color` is set.
+fn display_task(tasks: &TaskList, task: &Task, color: bool) -> String {
+    let mut line = if color {
+        tasks.line(&Task {
+            description: markdown::render(&task.description),
+            ..task.clone()
+        })
+    } else {
+        tasks.line(task)
+    };
+    if let Some(icon) = &task.icon {
+        line = format!("{} {}", icon, line);
+    }
+    match task_style(task, Local::now().date_naive()) {
+        Some(code) if color => format!("\x1b[{}m{}\x1b[0m", code, line),
+        _ => line,
+    }
+}
+
+/// Every field of a task as an aligned `label: value` card, followed by its history.
+fn task_card(tasks: &TaskList, task: &Task, render_markdown: bool) -> String {
+    let text = |s: &str| {
+        if render_markdown {
+            markdown::render(s)
+        } else {
+            s.to_string()
+        }
+    };
+    let mut rows: Vec<(&str, String)> = vec![
+        ("Task", task.id.to_string()),
+        ("Description", text(&task.description)),
+        ("Status", task.status.to_string()),
+    ];
+    let mut row = |label, value: Option<String>| {
+        if let Some(value) = value.filter(|value| !value.is_empty()) {
+            rows.push((label, value));
+        }
+    };
+    row(
+        "Context",
+        task.context.as_ref().map(|context| format!("@{}", context)),
+    );
+    row(
+        "Tags",
+        Some(
+            task.tags
+                .iter()
+                .map(|tag| format!("+{}", tag))
+                .collect::<Vec<_>>()
+                .join(" "),
+        ),
+    );
+    row("Alias", task.alias.clone());
+    row("Project", task.project.clone());
+    row(
+        "Priority",
+        task.priority.map(|priority| priority.to_string()),
+    );
+    row("Energy", task.energy.map(|energy| energy.to_string()));
+    row("Location", task.location.clone());
+    row("Due", task.due.map(|due| due.to_string()));
+    row(
+        "Scheduled",
+        task.scheduled.map(|scheduled| scheduled.to_string()),
+    );
+    row(
+        "Repeats",
+        task.recurrence
+            .as_ref()
+            .map(|recurrence| recurrence.every.to_string()),
+    );
+    row("Waiting on", task.waiting_on.clone());
+    row("Points", task.points.map(|points| points.to_string()));
+    row("Sprint", task.sprint.clone());
+    row(
+        "Milestone",
+        task.milestone.map(|id| {
+            tasks
+                .milestones
+                .iter()
+                .find(|milestone| milestone.id == id)
+                .map_or(id.to_string(), |milestone| milestone.name.clone())
+        }),
+    );
+    row("Progress", tasks.progress(task).map(progress_bar));
+    row("Parent", task.parent.map(|parent| parent.to_string()));
+    row(
+        "Subtasks",
+        Some(
+            tasks
+                .subtasks(task.id)
+                .map(|child| child.id.to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+        ),
+    );
+    row(
+        "Relations",
+        Some(
+            task.relations
+                .iter()
+                .map(|relation| format!("{} {}", relation.kind, relation.task))
+                .collect::<Vec<_>>()
+                .join(", "),
+        ),
+    );
+    row(
+        "Reminders",
+        Some(
+            task.reminders
+                .iter()
+                .filter(|reminder| !reminder.fired)
+                .map(|reminder| reminder.at.format("%Y-%m-%d %H:%M").to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+        ),
+    );
+    row("Attachments", Some(task.attachments.join(", ")));
+    row("Url", task.url.clone());
+    row(
+        "Created",
+        task.created_at
+            .map(|at| at.format("%Y-%m-%d %H:%M").to_string()),
+    );
+    row(
+        "Completed",
+        task.completed_at.map(|at| {
+            let mut completed = at.format("%Y-%m-%d %H:%M").to_string();
+            if let Some(by) = &task.completed_by {
+                completed.push_str(&format!(" by {}", by));
+            }
+            if let Some(seconds) = task.cycle_time {
+                completed.push_str(&format!(
+                    " ({})",
+                    format_duration(TimeDelta::seconds(seconds))
+                ));
+            }
+            completed
+        }),
+    );
+    row(
+        "Snoozed",
+        (task.snoozes > 0).then(|| format!("{}x", task.snoozes)),
+    );
+    row(
+        "Time spent",
+        (task.time_spent > 0 || task.started_at.is_some()).then(|| {
+            let mut spent = format_duration(TimeDelta::seconds(task.time_spent));
+            if task.started_at.is_some() {
+                spent.push_str(" (timer running)");
+            }
+            spent
+        }),
+    );
+
+    let width = rows.iter().map(|(label, _)| label.len()).max().unwrap_or(0);
+    let mut card: Vec<String> = rows
+        .iter()
+        .map(|(label, value)| format!("{:>width$}: {}", label, value, width = width))
+        .collect();
+    if let Some(notes) = &task.notes {
+        card.push(String::new());
+        card.extend(text(notes).lines().map(|line| format!("  {}", line)));
+    }
+    if !task.history.is_empty() {
+        card.push(String::new());
+        card.push("History:".to_string());
+        card.extend(task.history.iter().map(|event| format!("  {}", event)));
+    }
+    card.join("\n")
+}
+
+fn progress_bar(percent: u8) -> String {
+    let filled = usize::from(percent) / 10;
+    format!(
+        "[{}{}] {}%",
+        "#".repeat(filled),
+        "-".repeat(10 - filled),
+        percent
+    )
+}
+
+/// `command` run by the platform's shell.
+fn shell(command: &str) -> process::Command {
+    let (program, flag) = if cfg!(target_os = "windows") {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+    let mut shell = process::Command::new(program);
+    shell.args([flag, command]);
+    shell
+}
+
+/// Runs a hook command through the shell with `input` on stdin. The hook's name is
+/// passed in `TODORE_HOOK`.
+fn run_hook(name: &str, command: &str, input: &str) -> io::Result<()> {
+    let mut child = shell(command)
+        .env("TODORE_HOOK", name)
+        .stdin(process::Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(input.as_bytes())?;
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(io::Error::other(format!(
+            "{} exited with {}",
+            command, status
+        )));
+    }
+    Ok(())
+}
+
+fn open_in_browser(url: &str) -> io::Result<()> {
+    let status = if cfg!(target_os = "macos") {
+        process::Command::new("open").arg(url).status()?
+    } else if cfg!(target_os = "windows") {
+        process::Command::new("cmd")
+            .args(["/C", "start", "", url])
+            .status()?
+    } else {
+        process::Command::new("xdg-open").arg(url).status()?
+    };
+    if !status.success() {
+        return Err(io::Error::other(format!("Could not open {}", url)));
+    }
+    Ok(())
+}
+
+fn send_notification(summary: &str, body: &str) {
+    let result = if cfg!(target_os = "macos") {
+        process::Command::new("osascript")
+            .arg("-e")
+            .arg(format!(
+                "display notification {:?} with title {:?}",
+                body, summary
+            ))
+            .status()
+    } else {
+        process::Command::new("notify-send")
+            .arg(summary)
+            .arg(body)
+            .status()
+    };
+    if let Err(e) = result {
+        eprintln!("Could not send desktop notification: {}", e);
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct SprintSummary {
+    tasks: usize,
+    committed: u32,
+    completed: u32,
+}
+
+impl fmt::Display for SprintSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}/{} points completed across {} tasks",
+            self.completed, self.committed, self.tasks
+        )
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Counts {
+    total: usize,
+    overdue: usize,
+    by_status: Vec<(TaskStatus, usize)>,
+    by_tag: BTreeMap<String, usize>,
+}
+
+impl fmt::Display for Counts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} tasks, {} overdue", self.total, self.overdue)?;
+        let statuses: Vec<String> = self
+            .by_status
+            .iter()
+            .map(|(status, count)| format!("{}: {}", status, count))
+            .collect();
+        write!(f, "\n{}", statuses.join("  "))?;
+        if !self.by_tag.is_empty() {
+            let tags: Vec<String> = self
+                .by_tag
+                .iter()
+                .map(|(tag, count)| format!("+{}: {}", tag, count))
+                .collect();
+            write!(f, "\n{}", tags.join("  "))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+struct ListFilter {
+    context: Option<String>,
+    location: Option<String>,
+    status: Option<TaskStatus>,
+    tag: Option<String>,
+    project: Option<String>,
+    due_by: Option<NaiveDate>,
+    due_before: Option<NaiveDate>,
+    all: bool,
+}
+
+impl ListFilter {
+    fn parse(args: &[&str], today: NaiveDate) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut filter = ListFilter::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or("Invalid arguments for list.");
+            match *arg {
+                "--all" => filter.all = true,
+                "--at" => filter.location = Some(value()?.to_string()),
+                "--status" => filter.status = Some(TaskStatus::from_str(&value()?.to_lowercase())?),
+                "--tag" => filter.tag = Some(value()?.trim_start_matches('+').to_lowercase()),
+                "--project" => filter.project = Some(value()?.to_string()),
+                "--due" => {
+                    filter.due_by = match value()?.to_lowercase().as_str() {
+                        "overdue" => today.pred_opt(),
+                        due => parse_date(due, today)?,
+                    }
+                }
+                "--due-before" => filter.due_before = parse_date(&value()?.to_lowercase(), today)?,
+                _ if arg.starts_with('@') => filter.context = Some(arg[1..].to_string()),
+                _ => return Err("Invalid arguments for list.".into()),
+            }
+        }
+        Ok(filter)
+    }
+
+    fn matches(&self, task: &Task, today: NaiveDate) -> bool {
+        (self.context.is_none() || task.context == self.context)
+            && self.location.as_ref().is_none_or(|at| {
+                task.location
+                    .as_ref()
+                    .is_some_and(|location| location.eq_ignore_ascii_case(at))
+            })
+            && self
+                .status
+                .as_ref()
+                .is_none_or(|status| task.status == *status)
+            && self.tag.as_ref().is_none_or(|tag| task.tags.contains(tag))
+            && (self.project.is_none() || task.project == self.project)
+            && self
+                .due_by
+                .is_none_or(|due_by| task.due.is_some_and(|due| due <= due_by))
+            && self
+                .due_before
+                .is_none_or(|before| task.due.is_some_and(|due| due < before))
+            && (self.all || !task.is_scheduled_after(today))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Milestone {
+    id: u32,
+    name: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+enum SortKey {
+    Due,
+    Priority,
+    Id,
+    Created,
+    Status,
+}
+
+impl FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "due" => Ok(SortKey::Due),
+            "priority" | "pri" => Ok(SortKey::Priority),
+            "id" => Ok(SortKey::Id),
+            "created" => Ok(SortKey::Created),
+            "status" => Ok(SortKey::Status),
+            _ => Err("Invalid sort key, expected due, priority, id, created or status".into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum MoveTarget {
+    Up,
+    Down,
+    Top,
+    Bottom,
+    /// A 1-based position in the manual order.
+    To(usize),
+}
+
+/// An answer to the `review` prompt.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ReviewAction {
+    Done,
+    Snooze(TimeDelta),
+    Remove,
+    Edit,
+    Skip,
+    Quit,
+}
+
+impl FromStr for ReviewAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let words: Vec<&str> = s.split_whitespace().collect();
+        match words.as_slice() {
+            ["d" | "done"] => Ok(ReviewAction::Done),
+            ["s" | "snooze"] => Ok(ReviewAction::Snooze(TimeDelta::days(1))),
+            ["s" | "snooze", by] => Ok(ReviewAction::Snooze(parse_duration(by)?)),
+            ["r" | "remove" | "delete"] => Ok(ReviewAction::Remove),
+            ["e" | "edit"] => Ok(ReviewAction::Edit),
+            [] | ["n" | "next" | "skip"] => Ok(ReviewAction::Skip),
+            ["q" | "quit"] => Ok(ReviewAction::Quit),
+            _ => Err("Expected done, snooze [duration], remove, edit, next or quit".into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct SortOrder {
+    key: SortKey,
+    descending: bool,
+}
+
+impl SortOrder {
+    /// Orders two tasks by this key; tasks without a due date, priority or creation
+    /// time always go last, and ties fall back to the task id.
+    fn compare(&self, a: &Task, b: &Task) -> Ordering {
+        fn missing_last<T: Ord>(a: Option<T>, b: Option<T>, descending: bool) -> Ordering {
+            match (a, b) {
+                (Some(a), Some(b)) if descending => b.cmp(&a),
+                (Some(a), Some(b)) => a.cmp(&b),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            }
+        }
+        let ordering = match self.key {
+            SortKey::Due => missing_last(a.due, b.due, self.descending),
+            SortKey::Priority => missing_last(a.priority, b.priority, self.descending),
+            SortKey::Created => missing_last(a.created_at, b.created_at, self.descending),
+            SortKey::Status if self.descending => b.status.cmp(&a.status),
+            SortKey::Status => a.status.cmp(&b.status),
+            SortKey::Id if self.descending => b.id.cmp(&a.id),
+            SortKey::Id => a.id.cmp(&b.id),
+        };
+        ordering.then(a.id.cmp(&b.id))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TaskList {
+    tasks: Vec<Task>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    milestones: Vec<Milestone>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sort: Option<SortOrder>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    archived: Vec<Task>,
+}
+impl TaskList {
+    fn new() -> Self {
+        TaskList {
+            tasks: vec![],
+            milestones: vec![],
+            sort: None,
+            archived: vec![],
+        }
+    }
+
+    /// Compares by the configured sort order, or by manual position when none is set.
+    fn compare(&self, a: &Task, b: &Task) -> Ordering {
+        match &self.sort {
+            Some(sort) => sort.compare(a, b),
+            None => match (a.position, b.position) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            },
+        }
+    }
+
+    /// All tasks in the configured sort order, or manual then insertion order when none is set.
+    fn ordered(&self) -> Vec<&Task> {
+        let mut tasks: Vec<&Task> = self.tasks.iter().collect();
+        tasks.sort_by(|a, b| self.compare(a, b));
+        tasks
+    }
+
+    /// A copy of the list with its tasks in the configured sort order, for serializing formatters.
+    fn sorted(&self) -> TaskList {
+        let mut sorted = self.clone();
+        sorted.tasks.sort_by(|a, b| self.compare(a, b));
+        sorted
+    }
+
+    /// Moves a task within the manual order and renumbers every task's position from 1.
+    fn move_task(&mut self, task_id: u32, target: MoveTarget) -> Result<(), String> {
+        self.task(task_id)?;
+        let mut tasks: Vec<&Task> = self.tasks.iter().collect();
+        tasks.sort_by_key(|task| task.position.unwrap_or(u32::MAX));
+        let mut order: Vec<u32> = tasks.iter().map(|task| task.id).collect();
+        let from = order.iter().position(|id| *id == task_id).unwrap();
+        let to = match target {
+            MoveTarget::Up => from.saturating_sub(1),
+            MoveTarget::Down => from + 1,
+            MoveTarget::Top => 0,
+            MoveTarget::Bottom => order.len(),
+            MoveTarget::To(position) => position.saturating_sub(1),
+        }
+        .min(order.len() - 1);
+        order.remove(from);
+        order.insert(to, task_id);
+        for task in &mut self.tasks {
+            let position = order.iter().position(|id| *id == task.id).unwrap() as u32 + 1;
+            if task.id == task_id {
+                task.record(
+                    "position",
+                    task.position.map(|position| position.to_string()),
+                    Some(position.to_string()),
+                );
+            }
+            task.position = Some(position);
+        }
+        Ok(())
+    }
+
+    fn set_sort(&mut self, sort: Option<SortOrder>) {
+        self.sort = sort;
+    }
+
+    fn task_mut(&mut self, task_id: u32) -> Result<&mut Task, String> {
+        self.tasks
+            .iter_mut()
+            .find(|task| task.id == task_id)
+            .ok_or(format!("Task with id {} was not found", task_id))
+    }
+
+    fn task(&self, task_id: u32) -> Result<&Task, String> {
+        self.tasks
+            .iter()
+            .find(|task| task.id == task_id)
+            .ok_or(format!("Task with id {} was not found", task_id))
+    }
+
+    fn add(&mut self, mut task: Task) {
+        let description = task.description.clone();
+        task.record("created", None, Some(description));
+        task.created_at.get_or_insert(Local::now());
+        self.tasks.push(task);
+    }
+
+    fn remove(&mut self, task_id: u32) {
+        self.tasks.retain(|task| task.id != task_id);
+        for task in &mut self.tasks {
+            task.relations.retain(|relation| relation.task != task_id);
+        }
+    }
+
+    /// Folds `source` into `target`: descriptions and notes are concatenated, tags and
+    /// attachments unioned, the earlier creation time kept and subtasks re-parented,
+    /// then `source` is removed.
+    fn merge(&mut self, source: u32, target: u32) -> Result<(), String> {
+        if source == target {
+            return Err("A task cannot be merged into itself".into());
+        }
+        let mut ancestor = self.task(target)?.parent;
+        while let Some(ancestor_id) = ancestor {
+            if ancestor_id == source {
+                return Err(format!(
+                    "Task {} cannot be merged into its subtask {}",
+                    source, target
+                ));
+            }
+            ancestor = self.task(ancestor_id).ok().and_then(|task| task.parent);
+        }
+        let merged = self.task(source)?.clone();
+        let task = self.task_mut(target)?;
+        task.record(
+            "merged",
+            None,
+            Some(format!("{}: {}", merged.id, merged.description)),
+        );
+        task.description = format!("{}; {}", task.description, merged.description);
+        task.notes = match (task.notes.take(), merged.notes) {
+            (Some(notes), Some(other)) => Some(format!("{}\n\n{}", notes, other)),
+            (notes, other) => notes.or(other),
+        };
+        for tag in merged.tags {
+            if !task.tags.contains(&tag) {
+                task.tags.push(tag);
+            }
+        }
+        for attachment in merged.attachments {
+            if !task.attachments.contains(&attachment) {
+                task.attachments.push(attachment);
+            }
+        }
+        task.created_at = match (task.created_at, merged.created_at) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        for child in self
+            .tasks
+            .iter_mut()
+            .filter(|task| task.parent == Some(source))
+        {
+            child.record("parent", Some(source.to_string()), Some(target.to_string()));
+            child.parent = Some(target);
+        }
+        self.remove(source);
+        Ok(())
+    }
+
+    /// Turns a task into one new task per part, numbered from `first_id`. With
+    /// `as_subtasks` the parts become subtasks of the original; otherwise they take its
+    /// place, the first part inheriting its subtasks. Returns the new tasks' ids.
+    fn split(
+        &mut self,
+        task_id: u32,
+        parts: Vec<String>,
+        first_id: u32,
+        as_subtasks: bool,
+    ) -> Result<Vec<u32>, String> {
+        if parts.is_empty() {
+            return Err("Nothing to split into".into());
+        }
+        let original = self.task(task_id)?.clone();
+        let mut ids = vec![];
+        for (id, description) in (first_id..).zip(parts) {
+            let mut part = original.fresh_copy(id);
+            part.description = description;
+            if as_subtasks {
+                part.parent = Some(task_id);
+            }
+            self.add(part);
+            self.task_mut(id)?
+                .record("split", Some(task_id.to_string()), None);
+            ids.push(id);
+        }
+        let list = ids.iter().map(u32::to_string).collect::<Vec<_>>().join(",");
+        if as_subtasks {
+            self.task_mut(task_id)?.record("split", None, Some(list));
+        } else {
+            for child in self
+                .tasks
+                .iter_mut()
+                .filter(|task| task.parent == Some(task_id))
+            {
+                child.record(
+                    "parent",
+                    Some(task_id.to_string()),
+                    Some(first_id.to_string()),
+                );
+                child.parent = Some(first_id);
+            }
+            self.remove(task_id);
+        }
+        Ok(ids)
+    }
+
+    /// Moves a completed task out of the active list into the archive.
+    fn archive(&mut self, task_id: u32) -> Result<(), String> {
+        if !matches!(self.task(task_id)?.status, TaskStatus::Completed) {
+            return Err(format!("Task with id {} is not completed", task_id));
+        }
+        let index = self
+            .tasks
+            .iter()
+            .position(|task| task.id == task_id)
+            .unwrap();
+        let mut task = self.tasks.remove(index);
+        task.record("archived", None, None);
+        self.archived.push(task);
+        Ok(())
+    }
+
+    /// Removes (or archives) completed tasks, optionally only those completed at least
+    /// `older_than` ago, and returns how many were cleared. Tasks with no recorded
+    /// completion time are only cleared when no age is given.
+    fn clear_completed(
+        &mut self,
+        now: DateTime<Local>,
+        older_than: Option<TimeDelta>,
+        archive: bool,
+    ) -> Result<usize, String> {
+        let ids: Vec<u32> = self
+            .tasks
+            .iter()
+            .filter(|task| matches!(task.status, TaskStatus::Completed))
+            .filter(|task| match older_than {
+                Some(age) => task.completed_at.is_some_and(|at| now - at >= age),
+                None => true,
+            })
+            .map(|task| task.id)
+            .collect();
+        for id in &ids {
+            if archive {
+                self.archive(*id)?;
+            } else {
+                self.remove(*id);
+            }
+        }
+        Ok(ids.len())
+    }
+
+    fn link(&mut self, task_id: u32, kind: RelationKind, other: u32) -> Result<(), String> {
+        if task_id == other {
+            return Err("A task cannot be linked to itself".into());
+        }
+        self.task(other)?;
+        let task = self.task_mut(task_id)?;
+        let relation = Relation { kind, task: other };
+        if !task.relations.contains(&relation) {
+            task.record("link", None, Some(format!("{} {}", relation.kind, other)));
+            task.relations.push(relation);
+        }
+        Ok(())
+    }
+
+    fn unlink(&mut self, task_id: u32, other: u32) -> Result<(), String> {
+        let task = self.task_mut(task_id)?;
+        let before = task.relations.len();
+        task.relations.retain(|relation| relation.task != other);
+        if task.relations.len() == before {
+            return Err(format!("Task {} is not linked to task {}", task_id, other));
+        }
+        task.record("unlink", Some(other.to_string()), None);
+        Ok(())
+    }
+
+    /// Updates a task's status. Completing a recurring task instead rolls it over to
+    /// its next occurrence.
+    fn update_status(&mut self, task_id: u32, new_status: TaskStatus) -> Result<(), String> {
+        let task = self.task_mut(task_id)?;
+        // Worked out first, so a task that can't recur is left as it was.
+        let next = match (&new_status, &task.recurrence) {
+            (TaskStatus::Completed, Some(_)) => Some(Self::next_occurrence(task)?),
+            _ => None,
+        };
+        let from = task.status.to_string();
+        task.record("status", Some(from), Some(new_status.to_string()));
+        let was_completed = matches!(task.status, TaskStatus::Completed);
+        task.status = new_status;
+        if task.status != TaskStatus::InProgress {
+            task.stop_timer(Local::now());
+        }
+        match (&task.status, next) {
+            (TaskStatus::Completed, Some(next)) => {
+                task.record(
+                    "recurred",
+                    task.due.map(|due| due.to_string()),
+                    Some(next.to_string()),
+                );
+                task.due = Some(next);
+                task.status = TaskStatus::NotStarted;
+            }
+            (TaskStatus::Completed, None) if !was_completed => {
+                let user = env::var("USER").or_else(|_| env::var("USERNAME")).ok();
+                task.mark_completed(Local::now(), user);
+            }
+            (TaskStatus::Completed, None) => {}
+            _ => task.clear_completion(),
+        }
+        Ok(())
+    }
+
+    /// Fails when moving another task to In Progress would exceed `limit`.
+    fn check_wip(&self, task_id: u32, limit: Option<usize>) -> Result<(), String> {
+        let Some(limit) = limit else {
+            return Ok(());
+        };
+        let in_progress = |task: &&Task| task.status == TaskStatus::InProgress;
+        if !in_progress(&self.task(task_id)?)
+            && self.tasks.iter().filter(in_progress).count() >= limit
+        {
+            return Err(format!("WIP limit of {} tasks in progress reached", limit));
+        }
+        Ok(())
+    }
+
+    /// Marks a task In Progress, optionally starting its timer.
+    fn start(&mut self, task_id: u32, track: bool, now: DateTime<Local>) -> Result<(), String> {
+        if self.task(task_id)?.status != TaskStatus::InProgress {
+            self.update_status(task_id, TaskStatus::InProgress)?;
+        }
+        let task = self.task_mut(task_id)?;
+        if track && task.started_at.is_none() {
+            task.record("timer started", None, None);
+            task.started_at = Some(now);
+        }
+        Ok(())
+    }
+
+    /// Moves an In Progress task back to Not Started, stopping its timer.
+    fn pause(&mut self, task_id: u32, now: DateTime<Local>) -> Result<(), String> {
+        let task = self.task_mut(task_id)?;
+        if task.status != TaskStatus::InProgress {
+            return Err(format!("Task with id {} is not in progress", task_id));
+        }
+        task.stop_timer(now);
+        self.update_status(task_id, TaskStatus::NotStarted)
+    }
+
+    /// Adds a finished work session, e.g. a pomodoro, to a task's tracked time.
+    fn log_time(&mut self, task_id: u32, spent: TimeDelta) -> Result<(), String> {
+        let task = self.task_mut(task_id)?;
+        task.time_spent += spent.num_seconds();
+        task.record("pomodoro", None, Some(format_duration(spent)));
+        Ok(())
+    }
+
+    fn advance_recurrence(task: &mut Task, change: &str) -> Result<(), String> {
+        let next = Self::next_occurrence(task)?;
+        task.record(
+            change,
+            task.due.map(|due| due.to_string()),
+            Some(next.to_string()),
+        );
+        task.due = Some(next);
+        Ok(())
+    }
+
+    /// The due date a recurring task moves to next.
+    fn next_occurrence(task: &Task) -> Result<NaiveDate, String> {
+        let Some(recurrence) = &task.recurrence else {
+            return Err(format!("Task with id {} is not recurring", task.id));
+        };
+        let Some(due) = task.due else {
+            return Err(format!(
+                "Task with id {} recurs but has no due date to recur from",
+                task.id
+            ));
+        };
+        recurrence
+            .next_after(due)
+            .ok_or_else(|| "No further occurrences are possible".into())
+    }
+
+    fn update_recurrence(&mut self, task_id: u32, every: Option<Frequency>) -> Result<(), String> {
+        let task = self.task_mut(task_id)?;
+        if every.is_some() && task.due.is_none() {
+            return Err(format!(
+                "Task with id {} needs a due date to recur",
+                task_id
+            ));
+        }
+        let from = task.recurrence.as_ref().map(|r| r.every.to_string());
+        task.record(
+            "recurrence",
+            from,
+            every.as_ref().map(|every| every.to_string()),
+        );
+        task.recurrence = every.map(|every| Recurrence {
+            every,
+            exceptions: task
+                .recurrence
+                .take()
+                .map(|recurrence| recurrence.exceptions)
+                .unwrap_or_default(),
+        });
+        Ok(())
+    }
+
+    /// Skips the next occurrence of a recurring task without completing it.
+    fn skip(&mut self, task_id: u32) -> Result<(), String> {
+        Self::advance_recurrence(self.task_mut(task_id)?, "skipped")
+    }
+
+    /// Excludes a date from a recurring task's occurrences.
+    fn add_exception(&mut self, task_id: u32, date: NaiveDate) -> Result<(), String> {
+        let task = self.task_mut(task_id)?;
+        let Some(recurrence) = &mut task.recurrence else {
+            return Err(format!("Task with id {} is not recurring", task_id));
+        };
+        if !recurrence.exceptions.contains(&date) {
+            recurrence.exceptions.push(date);
+            recurrence.exceptions.sort();
+        }
+        task.record("exception", None, Some(date.to_string()));
+        Ok(())
+    }
+    fn update_description(&mut self, task_id: u32, new_description: String) -> Result<(), String> {
+        let task = self.task_mut(task_id)?;
+        let from = task.description.clone();
+        task.record("description", Some(from), Some(new_description.clone()));
+        task.description = new_description;
+        Ok(())
+    }
+
+    /// Adds `text` to the end of a description, or to the start with `prepend`.
+    fn extend_description(
+        &mut self,
+        task_id: u32,
+        text: &str,
+        prepend: bool,
+    ) -> Result<(), String> {
+        let current = &self.task(task_id)?.description;
+        let description = if prepend {
+            format!("{} {}", text, current)
+        } else {
+            format!("{} {}", current, text)
+        };
+        self.update_description(task_id, description)
+    }
+
+    fn update_context(&mut self, task_id: u32, context: Option<String>) -> Result<(), String> {
+        let task = self.task_mut(task_id)?;
+        let from = task.context.clone();
+        task.record("context", from, context.clone());
+        task.context = context;
+        Ok(())
+    }
+
+    fn update_notes(&mut self, task_id: u32, notes: Option<String>) -> Result<(), String> {
+        let task = self.task_mut(task_id)?;
+        task.record("notes", None, None);
+        task.notes = notes;
+        Ok(())
+    }
+
+    /// Applies the fields of an edited task that differ from the stored task.
+    fn apply_edit(&mut self, task_id: u32, edit: TaskEdit) -> Result<(), String> {
+        if edit.description.trim().is_empty() {
+            return Err("A task needs a description".into());
+        }
+        let current = TaskEdit::from(self.task(task_id)?);
+        if edit.description != current.description {
+            self.update_description(task_id, edit.description)?;
+        }
+        if edit.notes != current.notes {
+            self.update_notes(task_id, edit.notes.filter(|notes| !notes.trim().is_empty()))?;
+        }
+        if edit.context != current.context {
+            self.update_context(task_id, edit.context)?;
+        }
+        if edit.tags != current.tags {
+            let tags = edit.tags.iter().map(|tag| tag.to_lowercase()).collect();
+            self.update_tags(task_id, tags)?;
+        }
+        if edit.project != current.project {
+            self.update_project(task_id, edit.project)?;
+        }
+        if edit.priority != current.priority {
+            self.update_priority(task_id, edit.priority)?;
+        }
+        if edit.due != current.due {
+            self.update_due(task_id, edit.due)?;
+        }
+        if edit.scheduled != current.scheduled {
+            self.update_scheduled(task_id, edit.scheduled)?;
+        }
+        if edit.waiting_on != current.waiting_on {
+            self.update_waiting_on(task_id, edit.waiting_on)?;
+        }
+        if edit.location != current.location {
+            self.update_location(task_id, edit.location)?;
+        }
+        if edit.url != current.url {
+            self.update_url(task_id, edit.url)?;
+        }
+        if edit.status != self.task(task_id)?.status {
+            self.update_status(task_id, edit.status)?;
+        }
+        Ok(())
+    }
+
+    fn update_due(&mut self, task_id: u32, due: Option<NaiveDate>) -> Result<(), String> {
+        let task = self.task_mut(task_id)?;
+        let from = task.due.map(|date| date.to_string());
+        task.record("due", from, due.map(|date| date.to_string()));
+        task.due = due;
+        Ok(())
+    }
+
+    fn update_scheduled(
+        &mut self,
+        task_id: u32,
+        scheduled: Option<NaiveDate>,
+    ) -> Result<(), String> {
+        let task = self.task_mut(task_id)?;
+        let from = task.scheduled.map(|date| date.to_string());
+        task.record("scheduled", from, scheduled.map(|date| date.to_string()));
+        task.scheduled = scheduled;
+        Ok(())
+    }
+
+    /// Delegates a task to someone and marks it Waiting, or clears the delegation with `None`.
+    fn update_waiting_on(
+        &mut self,
+        task_id: u32,
+        waiting_on: Option<String>,
+    ) -> Result<(), String> {
+        let task = self.task_mut(task_id)?;
+        let from = task.waiting_on.clone();
+        task.record("waiting on", from, waiting_on.clone());
+        match waiting_on {
+            Some(_) => task.status = TaskStatus::Waiting,
+            None if matches!(task.status, TaskStatus::Waiting) => {
+                task.status = TaskStatus::NotStarted
+            }
+            None => {}
+        }
+        task.waiting_on = waiting_on;
+        Ok(())
+    }
+
+    /// Open delegated tasks grouped by whom they are waiting on.
+    fn delegated(&self) -> BTreeMap<&str, Vec<&Task>> {
+        let mut groups: BTreeMap<&str, Vec<&Task>> = BTreeMap::new();
+        for task in &self.tasks {
+            if let (Some(waiting_on), false) = (
+                task.waiting_on.as_deref(),
+                matches!(task.status, TaskStatus::Completed),
+            ) {
+                groups.entry(waiting_on).or_default().push(task);
+            }
+        }
+        groups
+    }
+
+    fn update_progress(&mut self, task_id: u32, progress: Option<u8>) -> Result<(), String> {
+        if progress.is_some_and(|progress| progress > 100) {
+            return Err("Progress must be between 0 and 100".into());
+        }
+        let task = self.task_mut(task_id)?;
+        let from = task.progress.map(|progress| format!("{}%", progress));
+        task.record(
+            "progress",
+            from,
+            progress.map(|progress| format!("{}%", progress)),
+        );
+        task.progress = progress;
+        Ok(())
+    }
+
+    /// Makes a task a subtask of `parent`, refusing to create cycles.
+    fn update_parent(&mut self, task_id: u32, parent: Option<u32>) -> Result<(), String> {
+        let mut ancestor = parent;
+        while let Some(ancestor_id) = ancestor {
+            if ancestor_id == task_id {
+                return Err(format!("Task {} cannot be its own ancestor", task_id));
+            }
+            ancestor = self.task(ancestor_id)?.parent;
+        }
+        let task = self.task_mut(task_id)?;
+        let from = task.parent.map(|id| id.to_string());
+        task.record("parent", from, parent.map(|id| id.to_string()));
+        task.parent = parent;
+        Ok(())
+    }
+
+    /// Clears the parent of a task that turns out to be its own ancestor, which only a
+    /// file edited by hand or imported can hold, so subtasks can be walked without looping.
+    /// Returns the ids whose parent was cleared.
+    fn break_cycles(&mut self) -> Vec<u32> {
+        let mut cleared = vec![];
+        for index in 0..self.tasks.len() {
+            let task_id = self.tasks[index].id;
+            let mut ancestor = self.tasks[index].parent;
+            // Any chain longer than the list has entered a cycle, possibly one above this task.
+            for _ in 0..self.tasks.len() {
+                match ancestor {
+                    Some(ancestor_id) if ancestor_id == task_id => {
+                        let task = &mut self.tasks[index];
+                        task.record("parent", Some(ancestor_id.to_string()), None);
+                        task.parent = None;
+                        cleared.push(task_id);
+                        break;
+                    }
+                    Some(ancestor_id) => {
+                        ancestor = self.task(ancestor_id).ok().and_then(|task| task.parent);
+                    }
+                    None => break,
+                }
+            }
+        }
+        cleared
+    }
+
+    fn subtasks(&self, task_id: u32) -> impl Iterator<Item = &Task> {
+        self.tasks
+            .iter()
+            .filter(move |task| task.parent == Some(task_id))
+    }
+
+    /// Fresh copies of a task, numbered from `first_id`, optionally followed by copies of
+    /// its subtasks (recursively) re-parented onto the copy.
+    fn duplicate(
+        &self,
+        task_id: u32,
+        first_id: u32,
+        with_subtasks: bool,
+        keep_tags: bool,
+    ) -> Result<Vec<Task>, String> {
+        let mut copies = vec![];
+        let mut pending = vec![(task_id, self.task(task_id)?.parent)];
+        while let Some((original, parent)) = pending.pop() {
+            let id = first_id + copies.len() as u32;
+            let mut copy = self.task(original)?.fresh_copy(id);
+            copy.parent = parent;
+            if !keep_tags {
+                copy.tags.clear();
+            }
+            copies.push(copy);
+            if with_subtasks {
+                let children: Vec<u32> = self.subtasks(original).map(|child| child.id).collect();
+                pending.extend(children.into_iter().rev().map(|child| (child, Some(id))));
+            }
+        }
+        Ok(copies)
+    }
+
+    /// Copies of a task and all of its subtasks, parents before children.
+    fn subtree(&self, task_id: u32) -> Result<Vec<Task>, String> {
+        let mut tasks = vec![];
+        let mut pending = vec![task_id];
+        while let Some(id) = pending.pop() {
+            tasks.push(self.task(id)?.clone());
+            pending.extend(self.subtasks(id).map(|child| child.id));
+        }
+        Ok(tasks)
+    }
+
+    /// Takes in tasks from another list under fresh ids, keeping parent links and
+    /// relations within the group and dropping those that pointed outside it, along with
+    /// milestones, which are per list. Returns the `(old, new)` id pairs.
+    fn adopt(&mut self, tasks: Vec<Task>) -> Vec<(u32, u32)> {
+        let first_id = self.next_id();
+        let ids: BTreeMap<u32, u32> = tasks.iter().map(|task| task.id).zip(first_id..).collect();
+        for mut task in tasks {
+            let old_id = task.id;
+            task.id = ids[&old_id];
+            task.parent = task.parent.and_then(|parent| ids.get(&parent).copied());
+            task.relations
+                .retain(|relation| ids.contains_key(&relation.task));
+            for relation in &mut task.relations {
+                relation.task = ids[&relation.task];
+            }
+            task.milestone = None;
+            task.position = None;
+            if task
+                .alias
+                .as_deref()
+                .is_some_and(|alias| self.resolve(alias).is_some())
+            {
+                task.alias = None;
+            }
+            task.record("transferred", Some(old_id.to_string()), None);
+            self.tasks.push(task);
+        }
+        ids.into_iter().collect()
+    }
+
+    /// A task's progress, averaged over its subtasks when it has any. Completed tasks
+    /// without an explicit progress count as 100%, other tasks as 0%.
+    fn progress(&self, task: &Task) -> Option<u8> {
+        let children: Vec<u32> = self
+            .subtasks(task.id)
+            .map(|child| u32::from(self.progress(child).unwrap_or(0)))
+            .collect();
+        if !children.is_empty() {
+            return Some((children.iter().sum::<u32>() / children.len() as u32) as u8);
+        }
+        match (task.progress, &task.status) {
+            (Some(progress), _) => Some(progress),
+            (None, TaskStatus::Completed) => Some(100),
+            (None, _) => None,
+        }
+    }
+
+    /// A task's plaintext line, with a progress bar when it has progress to show.
+    fn line(&self, task: &Task) -> String {
+        match self.progress(task) {
+            Some(progress) => format!("{} {}", task, progress_bar(progress)),
+            None => task.to_string(),
+        }
+    }
+
+    fn update_energy(&mut self, task_id: u32, energy: Option<Energy>) -> Result<(), String> {
+        let task = self.task_mut(task_id)?;
+        let from = task.energy.map(|energy| energy.to_string());
+        task.record("energy", from, energy.map(|energy| energy.to_string()));
+        task.energy = energy;
+        Ok(())
+    }
+
+    /// Tasks that can be worked on right now, optionally limited to an energy level.
+    /// Most urgent first; see [`TaskList::next_score`].
+    fn next_candidates(&self, energy: Option<Energy>, today: NaiveDate) -> Vec<&Task> {
+        let mut candidates: Vec<(i64, &Task)> = self
+            .tasks
+            .iter()
+            .filter(|task| matches!(task.status, TaskStatus::NotStarted | TaskStatus::InProgress))
+            .filter(|task| !task.is_scheduled_after(today))
+            .filter(|task| energy.is_none() || task.energy == energy)
+            .map(|task| (self.next_score(task, today), task))
+            .collect();
+        candidates.sort_by(|(a, a_task), (b, b_task)| b.cmp(a).then(a_task.id.cmp(&b_task.id)));
+        candidates.into_iter().map(|(_, task)| task).collect()
+    }
+
+    /// How pressing a task is: overdue and soon-due tasks score highest, then priority,
+    /// work already in progress and age; tasks still waiting on open subtasks are held back.
+    fn next_score(&self, task: &Task, today: NaiveDate) -> i64 {
+        let urgency = match task.due.map(|due| (due - today).num_days()) {
+            Some(days) if days < 0 => 100 + 5 * (-days).min(20),
+            Some(0) => 80,
+            Some(days) if days <= 7 => 60 - 5 * days,
+            Some(_) => 10,
+            None => 0,
+        };
+        let priority = match task.priority {
+            Some(Priority::High) => 40,
+            Some(Priority::Medium) => 20,
+            None => 10,
+            Some(Priority::Low) => 0,
+        };
+        let started = if matches!(task.status, TaskStatus::InProgress) {
+            15
+        } else {
+            0
+        };
+        let age = task.created_at.map_or(0, |created_at| {
+            (today - created_at.date_naive()).num_days().clamp(0, 60) / 3
+        });
+        let blocked = if self.is_blocked(task) { 50 } else { 0 };
+        urgency + priority + started + age - blocked
+    }
+
+    /// Whether a task still has open subtasks.
+    fn is_blocked(&self, task: &Task) -> bool {
+        self.subtasks(task.id)
+            .any(|child| !matches!(child.status, TaskStatus::Completed))
+    }
+
+    /// Picks an open, unblocked task using `roll` as the random number. With `weighted`,
+    /// high priority tasks are four times as likely as low priority ones.
+    fn roulette(&self, weighted: bool, today: NaiveDate, roll: u64) -> Option<&Task> {
+        let candidates: Vec<(u64, &Task)> = self
+            .tasks
+            .iter()
+            .filter(|task| matches!(task.status, TaskStatus::NotStarted | TaskStatus::InProgress))
+            .filter(|task| !task.is_scheduled_after(today) && !self.is_blocked(task))
+            .map(|task| {
+                let weight = match task.priority {
+                    _ if !weighted => 1,
+                    Some(Priority::High) => 4,
+                    Some(Priority::Medium) => 3,
+                    None => 2,
+                    Some(Priority::Low) => 1,
+                };
+                (weight, task)
+            })
+            .collect();
+        let total: u64 = candidates.iter().map(|(weight, _)| weight).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut roll = roll % total;
+        for (weight, task) in candidates {
+            if roll < weight {
+                return Some(task);
+            }
+            roll -= weight;
+        }
+        None
+    }
+
+    /// Tasks whose description fuzzily matches `query`, best match first.
+    fn find(&self, query: &str) -> Vec<&Task> {
+        let mut found: Vec<(i64, &Task)> = self
+            .tasks
+            .iter()
+            .filter_map(|task| fuzzy::score(query, &task.description).map(|score| (score, task)))
+            .collect();
+        found.sort_by(|(a, a_task), (b, b_task)| b.cmp(a).then(a_task.id.cmp(&b_task.id)));
+        found.into_iter().map(|(_, task)| task).collect()
+    }
+
+    fn update_points(&mut self, task_id: u32, points: Option<u32>) -> Result<(), String> {
+        let task = self.task_mut(task_id)?;
+        let from = task.points.map(|points| points.to_string());
+        task.record("points", from, points.map(|points| points.to_string()));
+        task.points = points;
+        Ok(())
+    }
+
+    fn update_sprint(&mut self, task_id: u32, sprint: Option<String>) -> Result<(), String> {
+        let task = self.task_mut(task_id)?;
+        let from = task.sprint.clone();
+        task.record("sprint", from, sprint.clone());
+        task.sprint = sprint;
+        Ok(())
+    }
+
+    fn sprint_summaries(&self) -> BTreeMap<&str, SprintSummary> {
+        let mut summaries: BTreeMap<&str, SprintSummary> = BTreeMap::new();
+        for task in &self.tasks {
+            let Some(sprint) = task.sprint.as_deref() else {
+                continue;
+            };
+            let summary = summaries.entry(sprint).or_default();
+            let points = task.points.unwrap_or(0);
+            summary.tasks += 1;
+            summary.committed += points;
+            if matches!(task.status, TaskStatus::Completed) {
+                summary.completed += points;
+            }
+        }
+        summaries
+    }
+
+    fn add_milestone(&mut self, name: String) -> u32 {
+        let id = self.milestones.iter().map(|m| m.id + 1).max().unwrap_or(0);
+        self.milestones.push(Milestone { id, name });
+        id
+    }
+
+    /// Removes a milestone and detaches every task that belonged to it.
+    fn remove_milestone(&mut self, milestone_id: u32) -> Result<(), String> {
+        if !self.milestones.iter().any(|m| m.id == milestone_id) {
+            return Err(format!("Milestone with id {} was not found", milestone_id));
+        }
+        self.milestones.retain(|m| m.id != milestone_id);
+        for task in &mut self.tasks {
+            if task.milestone == Some(milestone_id) {
+                task.record("milestone", Some(milestone_id.to_string()), None);
+                task.milestone = None;
+            }
+        }
+        Ok(())
+    }
+
+    fn update_milestone(&mut self, task_id: u32, milestone: Option<u32>) -> Result<(), String> {
+        if let Some(milestone_id) = milestone
+            && !self.milestones.iter().any(|m| m.id == milestone_id)
+        {
+            return Err(format!("Milestone with id {} was not found", milestone_id));
+        }
+        let task = self.task_mut(task_id)?;
+        let from = task.milestone.map(|id| id.to_string());
+        task.record("milestone", from, milestone.map(|id| id.to_string()));
+        task.milestone = milestone;
+        Ok(())
+    }
+
+    /// `(milestone, completed tasks, total tasks)` for every milestone.
+    fn milestone_progress(&self) -> Vec<(&Milestone, usize, usize)> {
+        self.milestones
+            .iter()
+            .map(|milestone| {
+                let tasks: Vec<_> = self
+                    .tasks
+                    .iter()
+                    .filter(|task| task.milestone == Some(milestone.id))
+                    .collect();
+                let completed = tasks
+                    .iter()
+                    .filter(|task| matches!(task.status, TaskStatus::Completed))
+                    .count();
+                (milestone, completed, tasks.len())
+            })
+            .collect()
+    }
+
+    /// Pushes a task's due date (or today, if it has none) forward and counts the snooze.
+    fn snooze(&mut self, task_id: u32, by: TimeDelta, today: NaiveDate) -> Result<(), String> {
+        if by.num_days() < 1 {
+            return Err("Snooze duration must be at least one day".into());
+        }
+        let task = self.task_mut(task_id)?;
+        let due = task
+            .due
+            .unwrap_or(today)
+            .checked_add_signed(by)
+            .ok_or("Invalid snooze duration")?;
+        let from = task.due.map(|date| date.to_string());
+        task.record("snoozed", from, Some(due.to_string()));
+        task.due = Some(due);
+        task.snoozes += 1;
+        Ok(())
+    }
+
+    fn remind(&mut self, task_id: u32, at: NaiveDateTime) -> Result<(), String> {
+        let task = self.task_mut(task_id)?;
+        task.record(
+            "reminder",
+            None,
+            Some(at.format("%Y-%m-%d %H:%M").to_string()),
+        );
+        task.reminders.push(Reminder { at, fired: false });
+        task.reminders.sort_by_key(|reminder| reminder.at);
+        Ok(())
+    }
+
+    /// Pending reminders across all tasks, soonest first.
+    fn upcoming_reminders(&self) -> Vec<(&Task, NaiveDateTime)> {
+        let mut upcoming: Vec<_> = self
+            .tasks
+            .iter()
+            .flat_map(|task| {
+                task.reminders
+                    .iter()
+                    .filter(|reminder| !reminder.fired)
+                    .map(move |reminder| (task, reminder.at))
+            })
+            .collect();
+        upcoming.sort_by_key(|(_, at)| *at);
+        upcoming
+    }
+
+    /// Marks every reminder due at `now` as fired and returns `(task id, description)` for each.
+    fn fire_reminders(&mut self, now: NaiveDateTime) -> Vec<(u32, String)> {
+        let mut fired = vec![];
+        for task in &mut self.tasks {
+            for reminder in &mut task.reminders {
+                if !reminder.fired && reminder.at <= now {
+                    reminder.fired = true;
+                    fired.push((task.id, task.description.clone()));
+                }
+            }
+        }
+        fired
+    }
+
+    /// Attaches a URL or a local file path to a task. Local paths must exist.
+    fn attach(&mut self, task_id: u32, attachment: String) -> Result<(), String> {
+        if !attachment.contains("://") && !Path::new(&attachment).exists() {
+            return Err(format!("File {} does not exist", attachment));
+        }
+        let task = self.task_mut(task_id)?;
+        task.record("attachment", None, Some(attachment.clone()));
+        task.attachments.push(attachment);
+        Ok(())
+    }
+
+    /// Tasks for the default listing: those scheduled in the future are hidden unless `all`.
+    fn listing(&self, filter: &ListFilter, today: NaiveDate) -> Vec<&Task> {
+        self.ordered()
+            .into_iter()
+            .filter(|task| filter.matches(task, today))
+            .collect()
+    }
+
+    fn update_priority(&mut self, task_id: u32, priority: Option<Priority>) -> Result<(), String> {
+        let task = self.task_mut(task_id)?;
+        let from = task.priority.map(|priority| priority.to_string());
+        task.record(
+            "priority",
+            from,
+            priority.map(|priority| priority.to_string()),
+        );
+        task.priority = priority;
+        Ok(())
+    }
+
+    fn update_tags(&mut self, task_id: u32, tags: Vec<String>) -> Result<(), String> {
+        let task = self.task_mut(task_id)?;
+        let from = Some(task.tags.join(",")).filter(|tags| !tags.is_empty());
+        task.record(
+            "tags",
+            from,
+            Some(tags.join(",")).filter(|tags| !tags.is_empty()),
+        );
+        task.tags = tags;
+        Ok(())
+    }
+
+    fn update_project(&mut self, task_id: u32, project: Option<String>) -> Result<(), String> {
+        let task = self.task_mut(task_id)?;
+        let from = task.project.clone();
+        task.record("project", from, project.clone());
+        task.project = project;
+        Ok(())
+    }
+
+    /// Names a task so commands accept the name in place of its id. Names must be unique
+    /// and, so they can't be mistaken for ids, not numbers.
+    fn update_alias(&mut self, task_id: u32, alias: Option<String>) -> Result<(), String> {
+        let alias = alias.map(|alias| alias.to_lowercase());
+        if let Some(alias) = &alias {
+            if alias.parse::<u32>().is_ok() || alias.chars().any(char::is_whitespace) {
+                return Err(format!("{} can't be used as an alias", alias));
+            }
+            if let Some(other) = self.resolve(alias).filter(|other| *other != task_id) {
+                return Err(format!("Task {} already has the alias {}", other, alias));
+            }
+        }
+        let task = self.task_mut(task_id)?;
+        let from = task.alias.clone();
+        task.record("alias", from, alias.clone());
+        task.alias = alias;
+        Ok(())
+    }
+
+    /// The id of the task with the given alias.
+    fn resolve(&self, alias: &str) -> Option<u32> {
+        let alias = alias.to_lowercase();
+        self.tasks
+            .iter()
+            .find(|task| task.alias.as_ref() == Some(&alias))
+            .map(|task| task.id)
+    }
+
+    /// Replaces task aliases with ids in the places a command line takes task ids.
+    fn resolve_aliases(&self, line: &str) -> Result<String, String> {
+        let mut words = shell::split(line)?;
+        let Some(command) = words.first().map(|word| word.to_lowercase()) else {
+            return Ok(line.to_string());
+        };
+        let positions: &[usize] = match command.as_str() {
+            "merge" | "unlink" => &[1, 2],
+            "link" => &[1, 3],
+            "u" | "update" if words.get(2).is_some_and(|field| field == "parent") => &[1, 3],
+            name if help::takes_task_id(name) => &[1],
+            _ => &[],
+        };
+        let mut resolved = false;
+        for &position in positions {
+            if let Some(id) = words.get(position).and_then(|word| self.resolve(word)) {
+                words[position] = id.to_string();
+                resolved = true;
+            }
+        }
+        if !resolved {
+            return Ok(line.to_string());
+        }
+        Ok(words
+            .iter()
+            .map(|word| shell::quote(word))
+            .collect::<Vec<_>>()
+            .join(" "))
+    }
+
+    fn update_color(&mut self, task_id: u32, color: Option<String>) -> Result<(), String> {
+        if let Some(color) = &color
+            && ansi_color(color).is_none()
+        {
+            return Err(format!(
+                "Invalid color {}, expected black, red, green, yellow, blue, magenta, cyan or white",
+                color
+            ));
+        }
+        let task = self.task_mut(task_id)?;
+        let from = task.color.clone();
+        task.record("color", from, color.clone());
+        task.color = color;
+        Ok(())
+    }
+
+    fn update_icon(&mut self, task_id: u32, icon: Option<String>) -> Result<(), String> {
+        let task = self.task_mut(task_id)?;
+        let from = task.icon.clone();
+        task.record("icon", from, icon.clone());
+        task.icon = icon;
+        Ok(())
+    }
+
+    fn update_url(&mut self, task_id: u32, url: Option<String>) -> Result<(), String> {
+        if let Some(url) = &url
+            && !url.contains("://")
+        {
+            return Err(format!(
+                "Invalid URL {}, expected e.g. https://example.com",
+                url
+            ));
+        }
+        let task = self.task_mut(task_id)?;
+        let from = task.url.clone();
+        task.record("url", from, url.clone());
+        task.url = url;
+        Ok(())
+    }
+
+    fn update_location(&mut self, task_id: u32, location: Option<String>) -> Result<(), String> {
+        let task = self.task_mut(task_id)?;
+        let from = task.location.clone();
+        task.record("location", from, location.clone());
+        task.location = location;
+        Ok(())
+    }
+
+    /// Totals by status and tag, and how many open tasks are past their due date.
+    fn counts(&self, today: NaiveDate) -> Counts {
+        let statuses = [
+            TaskStatus::NotStarted,
+            TaskStatus::InProgress,
+            TaskStatus::Waiting,
+            TaskStatus::Completed,
+        ];
+        let mut by_tag = BTreeMap::new();
+        for tag in self.tasks.iter().flat_map(|task| &task.tags) {
+            *by_tag.entry(tag.clone()).or_insert(0) += 1;
+        }
+        Counts {
+            total: self.tasks.len(),
+            overdue: self
+                .tasks
+                .iter()
+                .filter(|task| !matches!(task.status, TaskStatus::Completed))
+                .filter(|task| task.due.is_some_and(|due| due < today))
+                .count(),
+            by_status: statuses
+                .into_iter()
+                .map(|status| {
+                    let count = self
+                        .tasks
+                        .iter()
+                        .filter(|task| task.status == status)
+                        .count();
+                    (status, count)
+                })
+                .collect(),
+            by_tag,
+        }
+    }
+
+    fn context_counts(&self) -> BTreeMap<&str, usize> {
+        let mut counts = BTreeMap::new();
+        for context in self.tasks.iter().filter_map(|task| task.context.as_deref()) {
+            *counts.entry(context).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    fn export_to_string(
+        &self,
+        formatter: &dyn Formatter,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        formatter.format(self)
+    }
+
+    /// A list of just the given tasks, in the given order, for formatting a listing.
+    fn subset(&self, tasks: &[&Task]) -> TaskList {
+        TaskList {
+            tasks: tasks
+                .iter()
+                .map(|task| Task {
+                    position: None,
+                    ..(*task).clone()
+                })
+                .collect(),
+            milestones: self.milestones.clone(),
+            sort: None,
+            archived: vec![],
+        }
+    }
+
+    /// Brings in tasks from another copy of this list. A task with the id of one here is
+    /// the same task, unless both record when they were created and the times differ; the
+    /// copy changed last wins. Other tasks are appended, under a new id if theirs is taken.
+    /// Returns how many tasks were added, updated and skipped as no newer.
+    fn merge_import(&mut self, tasks: Vec<Task>) -> (usize, usize, usize) {
+        let changed = |task: &Task| task.touched.or(task.created_at);
+        let (mut updated, mut skipped) = (0, 0);
+        let mut unknown = vec![];
+        for task in tasks {
+            let Some(index) = self.tasks.iter().position(|known| known.id == task.id) else {
+                unknown.push(task);
+                continue;
+            };
+            let known = &self.tasks[index];
+            if let (Some(ours), Some(theirs)) = (known.created_at, task.created_at)
+                && ours != theirs
+            {
+                unknown.push(task);
+            } else if changed(&task) > changed(known) {
+                self.tasks[index] = Task {
+                    position: known.position,
+                    ..task
+                };
+                updated += 1;
+            } else {
+                skipped += 1;
+            }
+        }
+        let added = unknown.len();
+        let (free, taken): (Vec<Task>, Vec<Task>) = unknown
+            .into_iter()
+            .partition(|task| self.task(task.id).is_err());
+        self.tasks.extend(free);
+        self.adopt(taken);
+        (added, updated, skipped)
+    }
+
+    fn import(&mut self, tasks: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.replace(serde_json::from_str(tasks)?);
+        Ok(())
+    }
+
+    /// The lowest id above every task's.
+    fn next_id(&self) -> u32 {
+        self.tasks.iter().map(|task| task.id + 1).max().unwrap_or(0)
+    }
+
+    fn replace(&mut self, imported: TaskList) {
+        self.tasks = imported.tasks;
+        self.milestones = imported.milestones;
+        self.sort = imported.sort;
+        self.archived = imported.archived;
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Template {
+    name: String,
+    tasks: Vec<Task>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct TemplateStore {
+    templates: Vec<Template>,
+}
+
+impl TemplateStore {
+    fn load(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        if !Path::new(path).exists() {
+            return Ok(Self::default());
+        }
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    fn get(&self, name: &str) -> Option<&Template> {
+        self.templates.iter().find(|template| template.name == name)
+    }
+
+    /// Saves the given tasks under `name`, replacing any template with the same name.
+    fn save_from(&mut self, name: String, tasks: &TaskList, ids: &[u32]) -> Result<(), String> {
+        let mut template_tasks = vec![];
+        for id in ids {
+            match tasks.tasks.iter().find(|task| task.id == *id) {
+                Some(task) => template_tasks.push(task.clone()),
+                None => return Err(format!("Task with id {} was not found", id)),
+            }
+        }
+        self.templates.retain(|template| template.name != name);
+        self.templates.push(Template {
+            name,
+            tasks: template_tasks,
+        });
+        Ok(())
+    }
+
+    fn remove(&mut self, name: &str) -> Result<(), String> {
+        if self.get(name).is_none() {
+            return Err(format!("Template {} was not found", name));
+        }
+        self.templates.retain(|template| template.name != name);
+        Ok(())
+    }
+
+    /// Creates fresh NotStarted copies of a template's tasks, numbered from `first_id`.
+    fn instantiate(&self, name: &str, first_id: u32) -> Result<Vec<Task>, String> {
+        let template = self
+            .get(name)
+            .ok_or(format!("Template {} was not found", name))?;
+        Ok(template
+            .tasks
+            .iter()
+            .zip(first_id..)
+            .map(|(task, id)| task.fresh_copy(id))
+            .collect())
+    }
+}
+
+/// Snapshots of the task list taken before each mutating command, so that it can be
+/// stepped backwards with `undo` and forwards again with `redo`.
+#[derive(Debug, Default)]
+struct UndoStack {
+    undo: Vec<TaskList>,
+    redo: Vec<TaskList>,
+    /// Counts every record, undo and redo, so a changed list can be noticed cheaply.
+    generation: u64,
+}
+
+impl UndoStack {
+    const LIMIT: usize = 100;
+
+    /// Records the state before a mutation; any redoable states are discarded.
+    fn record(&mut self, tasks: &TaskList) {
+        if self.undo.len() == Self::LIMIT {
+            self.undo.remove(0);
+        }
+        self.undo.push(tasks.clone());
+        self.redo.clear();
+        self.generation += 1;
+    }
+
+    fn undo(&mut self, tasks: &mut TaskList) -> Result<(), String> {
+        let previous = self.undo.pop().ok_or("Nothing to undo")?;
+        self.redo.push(std::mem::replace(tasks, previous));
+        self.generation += 1;
+        Ok(())
+    }
+
+    fn redo(&mut self, tasks: &mut TaskList) -> Result<(), String> {
+        let next = self.redo.pop().ok_or("Nothing to redo")?;
+        self.undo.push(std::mem::replace(tasks, next));
+        self.generation += 1;
+        Ok(())
+    }
+}
+
+/// How `import` combines the file's tasks with the current ones.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ImportMode {
+    /// Add every imported task under a new id.
+    Add,
+    /// Match tasks by id, keeping whichever copy was changed last.
+    Merge,
+    Replace,
+}
+
+#[derive(Debug)]
+enum BackupAction {
+    Export { file: String },
+    Import { file: String },
+}
+
+#[derive(Debug)]
+enum SnapshotAction {
+    Create { name: String },
+    Restore { name: String },
+    List,
+}
+
+#[derive(Debug)]
+enum TemplateAction {
+    Save { name: String, ids: Vec<u32> },
+    Remove { name: String },
+    List,
+}
+
+#[derive(Debug)]
+enum MilestoneAction {
+    Add { name: String },
+    Remove { id: u32 },
+}
+
+#[derive(Debug)]
+enum Command {
+    Add {
+        val: String,
+        context: Option<String>,
+        tags: Vec<String>,
+    },
+    AddFromTemplate {
+        name: String,
+    },
+    /// One task per non-empty line of `path`, each also given the shared context, tags and due date.
+    AddFromFile {
+        path: String,
+        context: Option<String>,
+        tags: Vec<String>,
+        due: Option<NaiveDate>,
+    },
+    Template {
+        action: TemplateAction,
+    },
+    Remove {
+        id: u32,
+        force: bool,
+    },
+    Update {
+        id: u32,
+        new_val: String,
+        field: TaskField,
+    },
+    Attach {
+        id: u32,
+        target: String,
+    },
+    /// Exports every task, or with a filter just those `list` would show with it,
+    /// scheduled ones included. No file means the system clipboard.
+    Export {
+        format: Format,
+        out_file: Option<String>,
+        filter: Option<ListFilter>,
+    },
+    ExportTemplate {
+        template: String,
+        out_file: String,
+        filter: Option<ListFilter>,
+    },
+    ExportExec {
+        command: String,
+        out_file: String,
+        filter: Option<ListFilter>,
+    },
+    Import {
+        format: Format,
+        file: String,
+        mode: ImportMode,
+    },
+    Snapshot {
+        action: SnapshotAction,
+    },
+    Backup {
+        action: BackupAction,
+    },
+    /// Lists the latest `count` journal entries, or with `replay` rebuilds the tasks from them.
+    Journal {
+        count: usize,
+        replay: bool,
+    },
+    /// Lists the profiles chosen with `--profile`.
+    Profiles,
+    List {
+        filter: ListFilter,
+    },
+    Contexts,
+    Remind {
+        id: u32,
+        when: String,
+    },
+    Reminders {
+        notify: bool,
+    },
+    Snooze {
+        id: u32,
+        by: TimeDelta,
+    },
+    History {
+        id: u32,
+    },
+    Delegated,
+    SprintSummary {
+        sprint: Option<String>,
+    },
+    Milestone {
+        action: MilestoneAction,
+    },
+    Milestones,
+    Stale {
+        days: Option<u32>,
+    },
+    Skip {
+        id: u32,
+    },
+    Except {
+        id: u32,
+        date: String,
+    },
+    Link {
+        id: u32,
+        kind: RelationKind,
+        other: u32,
+    },
+    Unlink {
+        id: u32,
+        other: u32,
+    },
+    Next {
+        count: usize,
+        energy: Option<Energy>,
+    },
+    Open {
+        id: u32,
+    },
+    Find {
+        query: String,
+    },
+    Sort {
+        order: Option<SortOrder>,
+    },
+    Done {
+        id: u32,
+        archive: bool,
+    },
+    Archived,
+    Edit {
+        id: u32,
+    },
+    Move {
+        id: u32,
+        target: MoveTarget,
+    },
+    Duplicate {
+        id: u32,
+        with_subtasks: bool,
+        keep_tags: bool,
+    },
+    Clear {
+        older_than: Option<TimeDelta>,
+        archive: bool,
+        force: bool,
+    },
+    Show {
+        id: u32,
+    },
+    Start {
+        id: u32,
+        track: bool,
+    },
+    Pause {
+        id: u32,
+    },
+    Merge {
+        source: u32,
+        target: u32,
+    },
+    Split {
+        id: u32,
+        parts: Vec<String>,
+        as_subtasks: bool,
+    },
+    Extend {
+        id: u32,
+        text: String,
+        prepend: bool,
+    },
+    Transfer {
+        id: u32,
+        project: String,
+        keep: bool,
+    },
+    Alias {
+        id: u32,
+        alias: Option<String>,
+    },
+    Pomodoro {
+        id: u32,
+        length: TimeDelta,
+    },
+    Agenda {
+        span: view::Span,
+    },
+    Calendar {
+        month: Option<String>,
+    },
+    Count,
+    Review,
+    Roulette {
+        weighted: bool,
+    },
+    Source {
+        path: String,
+        keep_going: bool,
+    },
+    Undo,
+    Redo,
+    Help {
+        topic: Option<String>,
+    },
+    Quit,
+}
+
+/// Every format by name. Registering a formatter in [`Registry::new`] is all it takes for
+/// `export`, `import`, `--format`, file extensions and the help to know it.
+struct Registry {
+    /// Each format's names in the order registered, the registered name first.
+    names: Vec<Vec<&'static str>>,
+    /// Makes a formatter, afresh each time since some read the clock or terminal size.
+    formatters: HashMap<&'static str, fn() -> Box<dyn Formatter>>,
+    /// Short and alternative names, each with the name it stands for.
+    aliases: HashMap<&'static str, &'static str>,
+    /// File extensions, each with the name of the format it suggests.
+    extensions: HashMap<&'static str, &'static str>,
+}
+
+impl Registry {
+    fn new() -> Self {
+        let mut registry = Registry {
+            names: vec![],
+            formatters: HashMap::new(),
+            aliases: HashMap::new(),
+            extensions: HashMap::new(),
+        };
+        registry.register("json", &["j"], &["json"], || Box::new(JsonFormatter::new()));
+        registry.register("ndjson", &["jsonl"], &["ndjson", "jsonl"], || {
+            Box::new(NdjsonFormatter)
+        });
+        registry.register("yaml", &["y"], &["yaml", "yml"], || {
+            Box::new(YamlFormatter::new())
+        });
+        registry.register("toml", &["t"], &["toml"], || Box::new(TomlFormatter));
+        registry.register("plaintext", &["p"], &["txt"], || {
+            Box::new(PlaintextFormatter::new())
+        });
+        registry.register("csv", &[], &["csv"], || Box::new(CsvFormatter));
+        registry.register("markdown", &["md"], &["md"], || Box::new(MarkdownFormatter));
+        registry.register("html", &[], &["html", "htm"], || {
+            Box::new(HtmlFormatter {
+                today: Local::now().date_naive(),
+            })
+        });
+        registry.register("ics", &["ical"], &["ics"], || {
+            Box::new(IcsFormatter { stamp: Utc::now() })
+        });
+        registry.register("org", &[], &["org"], || Box::new(OrgFormatter));
+        registry.register("xml", &[], &["xml"], || Box::new(xml::XmlFormatter));
+        registry.register("outlook", &["mstodo"], &[], || {
+            Box::new(outlook::OutlookFormatter)
+        });
+        registry.register("jira", &[], &[], || Box::new(jira::JiraFormatter));
+        registry.register("table", &[], &[], || Box::new(TableFormatter::new(false)));
+        registry.register("box", &[], &[], || Box::new(TableFormatter::new(true)));
+        registry
+    }
+
+    fn register(
+        &mut self,
+        name: &'static str,
+        aliases: &[&'static str],
+        extensions: &[&'static str],
+        formatter: fn() -> Box<dyn Formatter>,
+    ) {
+        self.names
+            .push([name].iter().chain(aliases).copied().collect());
+        self.formatters.insert(name, formatter);
+        self.aliases
+            .extend(aliases.iter().map(|alias| (*alias, name)));
+        self.extensions
+            .extend(extensions.iter().map(|ext| (*ext, name)));
+    }
+
+    /// The registered name `name` stands for, if any.
+    fn resolve(&self, name: &str) -> Option<&'static str> {
+        let name = self.aliases.get(name).copied().unwrap_or(name);
+        self.formatters.get_key_value(name).map(|(name, _)| *name)
+    }
+
+    /// The formats for a command's usage, e.g. `[j | json] | csv`, only those `import`
+    /// reads when `imports` is set.
+    fn usage(&self, imports: bool) -> String {
+        self.names
+            .iter()
+            .filter(|names| !imports || self.formatters[names[0]]().imports())
+            .map(|names| match names.as_slice() {
+                [name] => name.to_string(),
+                _ => {
+                    let mut names = names.clone();
+                    names.sort_by_key(|name| name.len());
+                    format!("[{}]", names.join(" | "))
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" | ")
+    }
+
+    /// The registered names for prose, e.g. `json, yaml or csv`.
+    fn list(&self) -> String {
+        match self.names.split_last() {
+            Some((last, rest)) if !rest.is_empty() => {
+                let rest: Vec<&str> = rest.iter().map(|names| names[0]).collect();
+                format!("{} or {}", rest.join(", "), last[0])
+            }
+            _ => self.names.iter().map(|names| names[0]).collect(),
+        }
+    }
+}
+
+/// Built on first use; [`Format`]s are looked up here.
+static REGISTRY: LazyLock<Registry> = LazyLock::new(Registry::new);
+
+/// A format in the [`Registry`], by name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(into = "String")]
+struct Format(&'static str);
+
+impl<'de> Deserialize<'de> for Format {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Format::from_str(&name).map_err(serde::de::Error::custom)
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        REGISTRY
+            .resolve(s)
+            .map(Format)
+            .ok_or_else(|| "Invalid format.".into())
+    }
+}
+
+impl From<Format> for String {
+    fn from(format: Format) -> String {
+        format.0.to_string()
+    }
+}
+
+impl Format {
+    const JSON: Format = Format("json");
+    const NDJSON: Format = Format("ndjson");
+    const YAML: Format = Format("yaml");
+    const TOML: Format = Format("toml");
+    const JIRA: Format = Format("jira");
+
+    fn formatter(&self) -> Box<dyn Formatter> {
+        REGISTRY.formatters[self.0]()
+    }
+
+    /// The format a file's extension suggests, JSON when no format claims it.
+    fn of_path(path: &Path) -> Format {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(|ext| REGISTRY.extensions.get(ext))
+            .map_or(Format::JSON, |name| Format(name))
+    }
+
+    /// The format a task file is kept in: YAML or TOML when its extension says so, and
+    /// JSON otherwise, since the other formats don't hold everything.
+    fn of_store(path: &Path) -> Format {
+        match Format::of_path(path) {
+            format @ (Format::YAML | Format::TOML) => format,
+            _ => Format::JSON,
+        }
+    }
+
+    /// Reads a task list written by the matching formatter.
+    fn parse(&self, text: &str) -> Result<TaskList, Box<dyn std::error::Error>> {
+        self.formatter().parse(text)
+    }
+}
+
+/// Reads the `<id>: <description>\t<status>` lines written by [`PlaintextFormatter`].
+/// Anything after the status is ignored. Simple `- [ ]` and `- [x]` checklist lines are
+/// read too, numbered after the other tasks and following them.
+fn parse_plaintext(text: &str) -> Result<TaskList, String> {
+    let statuses = [
+        TaskStatus::NotStarted,
+        TaskStatus::InProgress,
+        TaskStatus::Waiting,
+        TaskStatus::Completed,
+    ];
+    let mut tasks = TaskList::new();
+    let mut checklist = vec![];
+    for (number, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(item) = checklist_item(line) {
+            checklist.push(item);
+            continue;
+        }
+        let invalid = || {
+            format!(
+                "line {}: expected <id>: <description> or - [ ] <description>",
+                number + 1
+            )
+        };
+        let (id, rest) = line.split_once(": ").ok_or_else(invalid)?;
+        let id = id.trim().parse::<u32>().map_err(|_| invalid())?;
+        let (description, details) = rest.split_once('\t').unwrap_or((rest, ""));
+        let mut task = Task::new(id, description.to_string());
+        if let Some(status) = statuses
+            .iter()
+            .find(|status| details.starts_with(&status.to_string()))
+        {
+            task.status = status.clone();
+        }
+        tasks.tasks.push(task);
+    }
+    // Numbered once every `<id>:` line is read, so they can't take one of those ids.
+    for (checked, description) in checklist {
+        let mut task = Task::new(tasks.next_id(), description.to_string());
+        if checked {
+            task.status = TaskStatus::Completed;
+        }
+        tasks.tasks.push(task);
+    }
+    Ok(tasks)
+}
+
+#[derive(Debug)]
+enum TaskField {
+    Description,
+    Status,
+    Due,
+    Scheduled,
+    WaitingOn,
+    Points,
+    Sprint,
+    Milestone,
+    Progress,
+    Parent,
+    Recur,
+    Energy,
+    Location,
+    Color,
+    Icon,
+    Url,
+    Tags,
+    Project,
+    Priority,
+}
+
+impl FromStr for TaskField {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "description" | "d" => Ok(TaskField::Description),
+            "status" | "s" => Ok(TaskField::Status),
+            "due" => Ok(TaskField::Due),
+            "scheduled" | "sch" => Ok(TaskField::Scheduled),
+            "waiting-on" | "w" => Ok(TaskField::WaitingOn),
+            "points" | "p" => Ok(TaskField::Points),
+            "sprint" => Ok(TaskField::Sprint),
+            "milestone" | "m" => Ok(TaskField::Milestone),
+            "progress" | "pr" => Ok(TaskField::Progress),
+            "parent" => Ok(TaskField::Parent),
+            "recur" | "rc" => Ok(TaskField::Recur),
+            "energy" | "en" => Ok(TaskField::Energy),
+            "location" | "loc" => Ok(TaskField::Location),
+            "color" | "colour" => Ok(TaskField::Color),
+            "icon" => Ok(TaskField::Icon),
+            "url" => Ok(TaskField::Url),
+            "tags" | "t" => Ok(TaskField::Tags),
+            "project" | "pj" => Ok(TaskField::Project),
+            "priority" | "pri" => Ok(TaskField::Priority),
+            _ => Err("Invalid field argument".into()),
+        }
+    }
+}
+
+impl Command {
+    /// Whether the command changes the task list and should be recorded for undo.
+    fn mutates(&self) -> bool {
+        matches!(
+            self,
+            Command::Add { .. }
+                | Command::AddFromTemplate { .. }
+                | Command::AddFromFile { .. }
+                | Command::Remove { .. }
+                | Command::Update { .. }
+                | Command::Attach { .. }
+                | Command::Remind { .. }
+                | Command::Snooze { .. }
+                | Command::Milestone { .. }
+                | Command::Skip { .. }
+                | Command::Except { .. }
+                | Command::Link { .. }
+                | Command::Unlink { .. }
+                | Command::Sort { .. }
+                | Command::Done { .. }
+                | Command::Edit { .. }
+                | Command::Move { .. }
+                | Command::Duplicate { .. }
+                | Command::Clear { .. }
+                | Command::Start { .. }
+                | Command::Pause { .. }
+                | Command::Merge { .. }
+                | Command::Split { .. }
+                | Command::Extend { .. }
+                | Command::Transfer { keep: false, .. }
+                | Command::Alias { .. }
+                | Command::Import { .. }
+                | Command::Journal { replay: true, .. }
+                | Command::Backup {
+                    action: BackupAction::Import { .. }
+                }
+                | Command::Snapshot {
+                    action: SnapshotAction::Restore { .. }
+                }
+                | Command::Pomodoro { .. }
+        )
+    }
+
+    fn from_str(val: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let words = shell::split(val)?;
+        let parts: Vec<&str> = words.iter().map(String::as_str).collect();
+        let Some(name) = parts.first() else {
+            return Err("Invalid argument.".into());
+        };
+        match name.to_lowercase().as_str() {
+            "a" | "add" => {
+                if parts.len() < 2 {
+                    return Err("Invalid arguments for add.".into());
+                }
+                if parts[1] == "--from-template" {
+                    if parts.len() < 3 {
+                        return Err("Invalid arguments for add.".into());
+                    }
+                    return Ok(Command::AddFromTemplate {
+                        name: parts[2].into(),
+                    });
+                }
+                if parts[1] == "--from-file" {
+                    let Some(path) = parts.get(2) else {
+                        return Err("Invalid arguments for add.".into());
+                    };
+                    let (words, context, tags) = split_markers(&parts[3..]);
+                    let due = match words.as_slice() {
+                        [] => None,
+                        ["--due", date] => parse_date(date, Local::now().date_naive())?,
+                        _ => return Err("Invalid arguments for add.".into()),
+                    };
+                    return Ok(Command::AddFromFile {
+                        path: path.to_string(),
+                        context,
+                        tags,
+                        due,
+                    });
+                }
+                let (words, context, tags) = split_markers(&parts[1..]);
+                if words.is_empty() {
+                    return Err("Invalid arguments for add.".into());
+                }
+                Ok(Command::Add {
+                    val: words.join(" "),
+                    context,
+                    tags,
+                })
+            }
+            "r" | "remove" => {
+                let (flags, args): (Vec<&str>, Vec<&str>) = parts[1..]
+                    .iter()
+                    .partition(|part| matches!(**part, "-f" | "--force"));
+                let [id] = args.as_slice() else {
+                    return Err("Invalid arguments for remove.".into());
+                };
+                Ok(Command::Remove {
+                    id: id.parse::<u32>()?,
+                    force: !flags.is_empty(),
+                })
+            }
+            "u" | "update" => {
+                if parts.len() < 4 {
+                    return Err("Invalid arguments for update.".into());
+                }
+                let id = parts[1].parse::<u32>()?;
+                let field = TaskField::from_str(&parts[2].to_lowercase())?;
+                let new_val = parts[3..].join(" ");
+                Ok(Command::Update { id, new_val, field })
+            }
+            "at" | "attach" => {
+                if parts.len() < 3 {
+                    return Err("Invalid arguments for attach.".into());
+                }
+                let id = parts[1].parse::<u32>()?;
+                Ok(Command::Attach {
+                    id,
+                    target: parts[2].into(),
+                })
+            }
+            "tpl" | "template" => {
+                let action = match (parts.get(1).copied(), parts.len()) {
+                    (Some("list"), _) => TemplateAction::List,
+                    (Some("save"), 4..) => TemplateAction::Save {
+                        name: parts[2].into(),
+                        ids: parts[3]
+                            .split(',')
+                            .map(|id| id.parse::<u32>())
+                            .collect::<Result<_, _>>()?,
+                    },
+                    (Some("remove"), 3..) => TemplateAction::Remove {
+                        name: parts[2].into(),
+                    },
+                    _ => return Err("Invalid arguments for template.".into()),
+                };
+                Ok(Command::Template { action })
+            }
+            "l" | "list" => Ok(Command::List {
+                filter: ListFilter::parse(&parts[1..], Local::now().date_naive())?,
+            }),
+            "ctx" | "contexts" => Ok(Command::Contexts),
+            "remind" => {
+                if parts.len() < 3 {
+                    return Err("Invalid arguments for remind.".into());
+                }
+                let id = parts[1].parse::<u32>()?;
+                Ok(Command::Remind {
+                    id,
+                    when: parts[2..].join(" ").to_lowercase(),
+                })
+            }
+            "z" | "snooze" => {
+                if parts.len() < 3 {
+                    return Err("Invalid arguments for snooze.".into());
+                }
+                let id = parts[1].parse::<u32>()?;
+                let by = parse_duration(&parts[2].to_lowercase())?;
+                Ok(Command::Snooze { id, by })
+            }
+            "h" | "history" => {
+                if parts.len() < 2 {
+                    return Err("Invalid arguments for history.".into());
+                }
+                let id = parts[1].parse::<u32>()?;
+                Ok(Command::History { id })
+            }
+            "dg" | "delegated" => Ok(Command::Delegated),
+            "ms" | "milestone" => {
+                let action = match (parts.get(1).copied(), parts.len()) {
+                    (Some("add"), 3..) => MilestoneAction::Add {
+                        name: parts[2..].join(" "),
+                    },
+                    (Some("remove"), 3..) => MilestoneAction::Remove {
+                        id: parts[2].parse::<u32>()?,
+                    },
+                    _ => return Err("Invalid arguments for milestone.".into()),
+                };
+                Ok(Command::Milestone { action })
+            }
+            "milestones" => Ok(Command::Milestones),
+            "skip" => {
+                if parts.len() < 2 {
+                    return Err("Invalid arguments for skip.".into());
+                }
+                let id = parts[1].parse::<u32>()?;
+                Ok(Command::Skip { id })
+            }
+            "except" => {
+                if parts.len() < 3 {
+                    return Err("Invalid arguments for except.".into());
+                }
+                let id = parts[1].parse::<u32>()?;
+                Ok(Command::Except {
+                    id,
+                    date: parts[2..].join(" "),
+                })
+            }
+            "link" => {
+                if parts.len() < 4 {
+                    return Err("Invalid arguments for link.".into());
+                }
+                let id = parts[1].parse::<u32>()?;
+                let kind = RelationKind::from_str(&parts[2].to_lowercase())?;
+                let other = parts[3].parse::<u32>()?;
+                Ok(Command::Link { id, kind, other })
+            }
+            "unlink" => {
+                if parts.len() < 3 {
+                    return Err("Invalid arguments for unlink.".into());
+                }
+                let id = parts[1].parse::<u32>()?;
+                let other = parts[2].parse::<u32>()?;
+                Ok(Command::Unlink { id, other })
+            }
+            "n" | "next" => {
+                let (mut count, mut energy) = (1, None);
+                let mut args = parts[1..].iter();
+                while let Some(arg) = args.next() {
+                    match *arg {
+                        "--energy" => match args.next() {
+                            Some(level) => energy = Some(Energy::from_str(&level.to_lowercase())?),
+                            None => return Err("Invalid arguments for next.".into()),
+                        },
+                        _ => match arg.parse::<usize>() {
+                            Ok(n) if n > 0 => count = n,
+                            _ => return Err("Invalid arguments for next.".into()),
+                        },
+                    }
+                }
+                Ok(Command::Next { count, energy })
+            }
+            "o" | "open" => {
+                if parts.len() < 2 {
+                    return Err("Invalid arguments for open.".into());
+                }
+                let id = parts[1].parse::<u32>()?;
+                Ok(Command::Open { id })
+            }
+            "d" | "done" => match parts.get(1..) {
+                Some([id]) => Ok(Command::Done {
+                    id: id.parse::<u32>()?,
+                    archive: false,
+                }),
+                Some([id, "--archive"]) => Ok(Command::Done {
+                    id: id.parse::<u32>()?,
+                    archive: true,
+                }),
+                _ => Err("Invalid arguments for done.".into()),
+            },
+            "archived" => Ok(Command::Archived),
+            "ed" | "edit" => match parts.get(1..) {
+                Some([id]) => Ok(Command::Edit {
+                    id: id.parse::<u32>()?,
+                }),
+                _ => Err("Invalid arguments for edit.".into()),
+            },
+            "?" | "help" => match parts.get(1..) {
+                Some([]) => Ok(Command::Help { topic: None }),
+                Some([topic]) => Ok(Command::Help {
+                    topic: Some(topic.to_lowercase()),
+                }),
+                _ => Err("Invalid arguments for help.".into()),
+            },
+            "mv" | "move" => {
+                let target = match parts.get(2..) {
+                    Some(["up"]) => MoveTarget::Up,
+                    Some(["down"]) => MoveTarget::Down,
+                    Some(["top"]) => MoveTarget::Top,
+                    Some(["bottom"]) => MoveTarget::Bottom,
+                    Some(["to", position]) => MoveTarget::To(position.parse::<usize>()?),
+                    _ => return Err("Invalid arguments for move.".into()),
+                };
+                Ok(Command::Move {
+                    id: parts[1].parse::<u32>()?,
+                    target,
+                })
+            }
+            "dup" | "duplicate" => {
+                if parts.len() < 2 {
+                    return Err("Invalid arguments for dup.".into());
+                }
+                let (mut with_subtasks, mut keep_tags) = (false, true);
+                for flag in &parts[2..] {
+                    match *flag {
+                        "--subtasks" => with_subtasks = true,
+                        "--no-tags" => keep_tags = false,
+                        _ => return Err("Invalid arguments for dup.".into()),
+                    }
+                }
+                Ok(Command::Duplicate {
+                    id: parts[1].parse::<u32>()?,
+                    with_subtasks,
+                    keep_tags,
+                })
+            }
+            "clear" => {
+                let (mut older_than, mut archive, mut force) = (None, false, false);
+                let mut args = parts[1..].iter();
+                while let Some(arg) = args.next() {
+                    match *arg {
+                        "--archive" => archive = true,
+                        "-f" | "--force" => force = true,
+                        "--older-than" => match args.next() {
+                            Some(age) => older_than = Some(parse_duration(&age.to_lowercase())?),
+                            None => return Err("Invalid arguments for clear.".into()),
+                        },
+                        _ => return Err("Invalid arguments for clear.".into()),
+                    }
+                }
+                Ok(Command::Clear {
+                    older_than,
+                    archive,
+                    force,
+                })
+            }
+            "s" | "show" => match parts.get(1..) {
+                Some([id]) => Ok(Command::Show {
+                    id: id.parse::<u32>()?,
+                }),
+                _ => Err("Invalid arguments for show.".into()),
+            },
+            "c" | "count" => Ok(Command::Count),
+            "review" => Ok(Command::Review),
+            "agenda" => Ok(Command::Agenda {
+                span: view::Span::All,
+            }),
+            "today" => Ok(Command::Agenda {
+                span: view::Span::Today,
+            }),
+            "week" => Ok(Command::Agenda {
+                span: view::Span::Week,
+            }),
+            "cal" | "calendar" => match parts.get(1..) {
+                Some([]) => Ok(Command::Calendar { month: None }),
+                Some([month]) => Ok(Command::Calendar {
+                    month: Some(month.to_lowercase()),
+                }),
+                _ => Err("Invalid arguments for calendar.".into()),
+            },
+            "pomodoro" => match parts.get(1..) {
+                Some([id]) => Ok(Command::Pomodoro {
+                    id: id.parse::<u32>()?,
+                    length: TimeDelta::minutes(25),
+                }),
+                Some([id, length]) => Ok(Command::Pomodoro {
+                    id: id.parse::<u32>()?,
+                    length: parse_duration(&length.to_lowercase())?,
+                }),
+                _ => Err("Invalid arguments for pomodoro.".into()),
+            },
+            "roulette" => match parts.get(1..) {
+                Some([]) => Ok(Command::Roulette { weighted: false }),
+                Some(["--weighted"]) => Ok(Command::Roulette { weighted: true }),
+                _ => Err("Invalid arguments for roulette.".into()),
+            },
+            "merge" => match parts.get(1..) {
+                Some([source, target]) => Ok(Command::Merge {
+                    source: source.parse::<u32>()?,
+                    target: target.parse::<u32>()?,
+                }),
+                _ => Err("Invalid arguments for merge.".into()),
+            },
+            "split" => {
+                if parts.len() < 2 {
+                    return Err("Invalid arguments for split.".into());
+                }
+                Ok(Command::Split {
+                    id: parts[1].parse::<u32>()?,
+                    parts: parts[2..]
+                        .iter()
+                        .filter(|part| **part != "--subtasks")
+                        .map(|part| part.to_string())
+                        .collect(),
+                    as_subtasks: parts[2..].contains(&"--subtasks"),
+                })
+            }
+            "start" => match parts.get(1..) {
+                Some([id]) => Ok(Command::Start {
+                    id: id.parse::<u32>()?,
+                    track: false,
+                }),
+                Some([id, "--track"]) => Ok(Command::Start {
+                    id: id.parse::<u32>()?,
+                    track: true,
+                }),
+                _ => Err("Invalid arguments for start.".into()),
+            },
+            "pause" => match parts.get(1..) {
+                Some([id]) => Ok(Command::Pause {
+                    id: id.parse::<u32>()?,
+                }),
+                _ => Err("Invalid arguments for pause.".into()),
+            },
+            "source" => match parts.get(1..) {
+                Some([path]) => Ok(Command::Source {
+                    path: path.to_string(),
+                    keep_going: false,
+                }),
+                Some([path, "--keep-going"]) => Ok(Command::Source {
+                    path: path.to_string(),
+                    keep_going: true,
+                }),
+                _ => Err("Invalid arguments for source.".into()),
+            },
+            "undo" => Ok(Command::Undo),
+            "redo" => Ok(Command::Redo),
+            "sort" => match parts.get(1..) {
+                Some(["by", key, direction @ ..]) => {
+                    let key = SortKey::from_str(&key.to_lowercase())?;
+                    let descending = match direction {
+                        [] => key == SortKey::Priority,
+                        ["asc"] => false,
+                        ["desc"] => true,
+                        _ => return Err("Invalid arguments for sort.".into()),
+                    };
+                    Ok(Command::Sort {
+                        order: Some(SortOrder { key, descending }),
+                    })
+                }
+                Some(["none"]) => Ok(Command::Sort { order: None }),
+                _ => Err("Invalid arguments for sort.".into()),
+            },
+            command @ ("append" | "prepend") => {
+                if parts.len() < 3 {
+                    return Err(format!("Invalid arguments for {}.", command).into());
+                }
+                Ok(Command::Extend {
+                    id: parts[1].parse::<u32>()?,
+                    text: parts[2..].join(" "),
+                    prepend: command == "prepend",
+                })
+            }
+            command @ ("move-to" | "copy-to") => match parts.get(1..) {
+                Some([id, project]) => Ok(Command::Transfer {
+                    id: id.parse::<u32>()?,
+                    project: project.to_string(),
+                    keep: command == "copy-to",
+                }),
+                _ => Err(format!("Invalid arguments for {}.", command).into()),
+            },
+            "alias" => match parts.get(1..) {
+                Some([id, alias]) => Ok(Command::Alias {
+                    id: id.parse::<u32>()?,
+                    alias: parse_optional(alias)?,
+                }),
+                _ => Err("Invalid arguments for alias.".into()),
+            },
+            "f" | "find" => {
+                if parts.len() < 2 {
+                    return Err("Invalid arguments for find.".into());
+                }
+                Ok(Command::Find {
+                    query: parts[1..].join(" "),
+                })
+            }
+            "stale" => {
+                let days = parts.get(1).map(|days| days.parse::<u32>()).transpose()?;
+                Ok(Command::Stale { days })
+            }
+            "sprint" => {
+                if parts.get(1) != Some(&"summary") {
+                    return Err("Invalid arguments for sprint.".into());
+                }
+                Ok(Command::SprintSummary {
+                    sprint: parts.get(2).map(|sprint| sprint.to_string()),
+                })
+            }
+            "reminders" => match parts.get(1) {
+                None => Ok(Command::Reminders { notify: false }),
+                Some(&"--notify") => Ok(Command::Reminders { notify: true }),
+                Some(_) => Err("Invalid arguments for reminders.".into()),
+            },
+            "q" | "quit" => Ok(Command::Quit),
+            "e" | "export" => {
+                let filter = |args: &[&str]| -> Result<_, Box<dyn std::error::Error>> {
+                    if args.is_empty() {
+                        return Ok(None);
+                    }
+                    let mut filter = ListFilter::parse(args, Local::now().date_naive())?;
+                    filter.all = true;
+                    Ok(Some(filter))
+                };
+                if parts.get(1) == Some(&"template") {
+                    return match parts[2..] {
+                        [template, out_file, ref args @ ..] => Ok(Command::ExportTemplate {
+                            template: template.into(),
+                            out_file: out_file.into(),
+                            filter: filter(args)?,
+                        }),
+                        _ => Err("Invalid arguments for export template.".into()),
+                    };
+                }
+                if parts.get(1) == Some(&"exec") {
+                    return match parts[2..] {
+                        [command, out_file, ref args @ ..] => Ok(Command::ExportExec {
+                            command: command.into(),
+                            out_file: out_file.into(),
+                            filter: filter(args)?,
+                        }),
+                        _ => Err("Invalid arguments for export exec.".into()),
+                    };
+                }
+                if parts.len() < 3 {
+                    return Err("Invalid arguments for export.".into());
+                }
+                let format = Format::from_str(&parts[1].to_lowercase())?;
+                Ok(Command::Export {
+                    format,
+                    out_file: Some(parts[2])
+                        .filter(|&file| file != "--clipboard")
+                        .map(String::from),
+                    filter: filter(&parts[3..])?,
+                })
+            }
+            "snap" | "snapshot" => {
+                let action = match parts.get(1..) {
+                    Some(["create", name]) => SnapshotAction::Create {
+                        name: name.to_string(),
+                    },
+                    Some(["restore", name]) => SnapshotAction::Restore {
+                        name: name.to_string(),
+                    },
+                    Some(["list"]) => SnapshotAction::List,
+                    _ => return Err("Invalid arguments for snapshot.".into()),
+                };
+                Ok(Command::Snapshot { action })
+            }
+            "backup" => {
+                let action = match parts.get(1..) {
+                    Some(["export", file]) => BackupAction::Export {
+                        file: file.to_string(),
+                    },
+                    Some(["import", file]) => BackupAction::Import {
+                        file: file.to_string(),
+                    },
+                    _ => return Err("Invalid arguments for backup.".into()),
+                };
+                Ok(Command::Backup { action })
+            }
+            "profile" | "profiles" => match parts.get(1..) {
+                Some([] | ["list"]) => Ok(Command::Profiles),
+                _ => Err("Invalid arguments for profile.".into()),
+            },
+            "journal" => match parts.get(1..) {
+                Some([]) => Ok(Command::Journal {
+                    count: 10,
+                    replay: false,
+                }),
+                Some(["replay"]) => Ok(Command::Journal {
+                    count: 0,
+                    replay: true,
+                }),
+                Some([count]) => Ok(Command::Journal {
+                    count: count.parse()?,
+                    replay: false,
+                }),
+                _ => Err("Invalid arguments for journal.".into()),
+            },
+            "import" => {
+                let (format, file, mode) = match parts.get(1..) {
+                    Some([format, file]) => (format, file, ImportMode::Add),
+                    Some([format, file, "--merge"]) => (format, file, ImportMode::Merge),
+                    Some([format, file, "--replace"]) => (format, file, ImportMode::Replace),
+                    _ => return Err("Invalid arguments for import.".into()),
+                };
+                Ok(Command::Import {
+                    format: Format::from_str(&format.to_lowercase())?,
+                    file: file.to_string(),
+                    mode,
+                })
+            }
+            _ => Err("Invalid argument.".into()),
+        }
+    }
+}
+
+/// Everything commands act on during one run of todore.
+struct Session {
+    task_list: TaskList,
+    config: Config,
+    templates: TemplateStore,
+    /// Where templates are saved; empty keeps them in memory only.
+    templates_file: String,
+    counter: u32,
+    notify: bool,
+    /// Colour listings and render Markdown; see [`color_enabled`].
+    color: bool,
+    /// Print listings with this formatter instead of as text, set by `--format`.
+    format: Option<Format>,
+    history: UndoStack,
+    /// The previous line typed at the prompt, for `!!`.
+    last_input: Option<String>,
+    /// The interactive prompt; `None` when running a script.
+    editor: Option<Editor<TodoreHelper, DefaultHistory>>,
+    /// Lines printed by commands, kept instead of printed while `--json` collects them.
+    captured: RefCell<Option<Vec<String>>>,
+    /// Report changes and files instead of writing anything, set by `--dry-run`.
+    dry_run: bool,
+    /// Where the task list is saved; `None` keeps it in memory only.
+    storage: Option<Box<dyn Storage>>,
+    /// The undo generation and time of the last save, see [`Session::autosave`].
+    saved: (u64, Option<Instant>),
+    /// The profile chosen with `--profile`, if any.
+    profile: Option<String>,
+    /// Where `move-to` and `copy-to` find other projects' task files: the directory of
+    /// the task file in use.
+    projects_dir: PathBuf,
+}
+
+type TaskChange = (Option<serde_json::Value>, Option<serde_json::Value>);
+
+/// Every task that differs between `before` and `after`, as its JSON before and after;
+/// `None` means the task didn't exist.
+fn task_changes(before: &[Task], after: &[Task]) -> BTreeMap<u32, TaskChange> {
+    let by_id = |tasks: &[Task]| -> BTreeMap<u32, serde_json::Value> {
+        tasks
+            .iter()
+            .filter_map(|task| Some((task.id, serde_json::to_value(task).ok()?)))
+            .collect()
+    };
+    let (before, after) = (by_id(before), by_id(after));
+    before
+        .keys()
+        .chain(after.keys())
+        .filter(|id| before.get(id) != after.get(id))
+        .map(|id| (*id, (before.get(id).cloned(), after.get(id).cloned())))
+        .collect()
+}
+
+/// One line of a dry run report: `+ 4 Buy milk`, `- 2 Old task` or
+/// `~ 3 status: Not Started -> Completed`. History and timestamps are left out.
+fn describe_change(
+    id: u32,
+    before: Option<&serde_json::Value>,
+    after: Option<&serde_json::Value>,
+) -> String {
+    let text = |value: Option<&serde_json::Value>| match value {
+        None | Some(serde_json::Value::Null) => "none".to_string(),
+        Some(serde_json::Value::String(s)) => s.clone(),
+        Some(value) => value.to_string(),
+    };
+    match (before, after) {
+        (None, after) => format!(
+            "+ {} {}",
+            id,
+            text(after.and_then(|t| t.get("description")))
+        ),
+        (before, None) => format!(
+            "- {} {}",
+            id,
+            text(before.and_then(|t| t.get("description")))
+        ),
+        (Some(before), Some(after)) => {
+            let empty = serde_json::Map::new();
+            let fields = |task: &serde_json::Value| task.as_object().unwrap_or(&empty).clone();
+            let (before, after) = (fields(before), fields(after));
+            let changed: Vec<String> = before
+                .keys()
+                .chain(after.keys().filter(|key| !before.contains_key(*key)))
+                .filter(|key| !matches!(key.as_str(), "history" | "touched"))
+                .filter(|key| before.get(*key) != after.get(*key))
+                .map(|key| {
+                    format!(
+                        "{}: {} -> {}",
+                        key,
+                        text(before.get(key)),
+                        text(after.get(key))
+                    )
+                })
+                .collect();
+            format!("~ {} {}", id, changed.join(", "))
+                .trim_end()
+                .to_string()
+        }
+    }
+}
+
+/// Splits the words of an `add` into the description, the `@context` (the last one
+/// wins) and the lowercased `+tags`.
+fn split_markers<'a>(parts: &[&'a str]) -> (Vec<&'a str>, Option<String>, Vec<String>) {
+    let (markers, words): (Vec<&str>, Vec<&str>) = parts.iter().partition(|part| {
+        part.len() > 1
+            && !part.contains(char::is_whitespace)
+            && (part.starts_with('@') || part.starts_with('+'))
+    });
+    let context = markers
+        .iter()
+        .rfind(|marker| marker.starts_with('@'))
+        .map(|context| context[1..].to_string());
+    let tags = markers
+        .iter()
+        .filter(|marker| marker.starts_with('+'))
+        .map(|tag| tag[1..].to_lowercase())
+        .collect();
+    (words, context, tags)
+}
+
+/// Prints a line of command output through [`Session::say`].
+macro_rules! say {
+    ($session:expr, $($arg:tt)*) => {
+        $session.say(format!($($arg)*))
+    };
+}
+
+impl Session {
+    fn new(
+        task_list: TaskList,
+        config: Config,
+        templates: TemplateStore,
+        templates_file: &str,
+    ) -> Self {
+        Session {
+            counter: task_list.next_id(),
+            task_list,
+            config,
+            templates,
+            templates_file: templates_file.to_string(),
+            notify: false,
+            color: false,
+            format: None,
+            history: UndoStack::default(),
+            last_input: None,
+            editor: None,
+            captured: RefCell::new(None),
+            dry_run: false,
+            storage: None,
+            saved: (0, None),
+            profile: None,
+            projects_dir: PathBuf::new(),
+        }
+    }
+
+    /// Takes the settings given on the command line, which override the config.
+    fn apply_cli(&mut self, cli: &Cli) {
+        self.profile = cli.profile.clone();
+        self.color = color_enabled(cli.no_color || self.config.no_color);
+        self.format = cli.format.or(self.config.format);
+        self.dry_run = cli.dry_run;
+    }
+
+    /// Writes the templates out, unless the session keeps them in memory only.
+    fn save_templates(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.templates_file.is_empty() {
+            return Ok(());
+        }
+        self.write_file(
+            &self.templates_file,
+            &serde_json::to_string_pretty(&self.templates)?,
+        )
+    }
+
+    /// Reads a line at the prompt. Unsaved changes are saved in the background meanwhile,
+    /// every `autosave_interval_secs`, and any failure is reported once the line is in.
+    fn read_line(&mut self, history_file: &str) -> Result<Option<String>, ReadlineError> {
+        let interval = Duration::from_secs(self.config.autosave_interval_secs);
+        let (generation, _) = self.saved;
+        let storage = self.storage.take_if(|_| {
+            self.config.autosave
+                && !self.dry_run
+                && !interval.is_zero()
+                && generation != self.history.generation
+        });
+        let Some(storage) = storage else {
+            return self.prompt(history_file);
+        };
+        let background = BackgroundSave::start(storage, self.task_list.clone(), interval);
+        let line = self.prompt(history_file);
+        let (storage, saved) = background.finish();
+        self.storage = Some(storage);
+        match saved {
+            Some(Ok(())) => self.saved = (self.history.generation, Some(Instant::now())),
+            Some(Err(err)) => eprintln!("Autosave failed: {}", err),
+            None => {}
+        }
+        line
+    }
+
+    /// Reads the next line from the prompt, or `None` once the user leaves or there is no prompt.
+    fn prompt(&mut self, history_file: &str) -> Result<Option<String>, ReadlineError> {
+        let Some(editor) = self.editor.as_mut() else {
+            return Ok(None);
+        };
+        if let Some(helper) = editor.helper_mut() {
+            helper.refresh(&self.task_list);
+        }
+        match editor.readline("> ") {
+            Ok(line) => {
+                if !line.trim().is_empty() {
+                    editor.add_history_entry(line.as_str())?;
+                    editor.save_history(history_file)?;
+                }
+                Ok(Some(line))
+            }
+            Err(ReadlineError::Interrupted | ReadlineError::Eof) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Asks before a destructive command; scripts and `confirm = false` skip the question.
+    fn confirm(&mut self, question: &str) -> Result<bool, ReadlineError> {
+        match self.editor.as_mut() {
+            Some(editor) if self.config.confirm => confirm(editor, question),
+            _ => Ok(true),
+        }
+    }
+
+    /// Reads task descriptions from the prompt until an empty line; scripts have no prompt
+    /// to read from.
+    fn read_parts(&mut self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let Some(editor) = self.editor.as_mut() else {
+            return Err("split needs the new descriptions when not run interactively".into());
+        };
+        let mut parts = vec![];
+        loop {
+            match editor.readline(&format!("Part {} (empty to finish): ", parts.len() + 1)) {
+                Ok(line) if line.trim().is_empty() => return Ok(parts),
+                Ok(line) => parts.push(line.trim().to_string()),
+                Err(ReadlineError::Interrupted | ReadlineError::Eof) => return Ok(vec![]),
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+
+    fn run_script(
+        &mut self,
+        path: &str,
+        keep_going: bool,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.run_lines(path, &fs::read_to_string(path)?, keep_going)
+    }
+
+    /// Runs a script one line at a time, skipping blank lines and `#` comments. The first
+    /// failing line stops the script unless `keep_going` is set, in which case it is reported.
+    /// `name` identifies the script in error messages.
+    fn run_lines(
+        &mut self,
+        name: &str,
+        script: &str,
+        keep_going: bool,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        for (number, line) in script.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match self.run_line(line) {
+                Ok(ControlFlow::Continue(())) => {}
+                Ok(ControlFlow::Break(())) => break,
+                Err(err) if keep_going => eprintln!("{}:{}: {}", name, number + 1, err),
+                Err(err) => return Err(format!("{}:{}: {}", name, number + 1, err).into()),
+            }
+        }
+        Ok(())
+    }
+
+    fn say(&self, line: String) {
+        match self.captured.borrow_mut().as_mut() {
+            Some(lines) => lines.push(line),
+            None => println!("{}", line),
+        }
+    }
+
+    /// Runs one command for `--json`, describing the outcome as
+    /// `{"ok": true, "affected": [3], "task": {...}, "output": [...]}`, where `affected`
+    /// lists the tasks added, changed or removed and `task` is the affected task when
+    /// there is exactly one. Failures give `{"ok": false, "error": "..."}`.
+    fn run_json(&mut self, line: &str) -> serde_json::Value {
+        let before = self.task_list.tasks.clone();
+        *self.captured.borrow_mut() = Some(vec![]);
+        let result = self.run_input(line);
+        let output = self.captured.borrow_mut().take().unwrap_or_default();
+        if let Err(err) = result {
+            return serde_json::json!({ "ok": false, "error": err.to_string(), "output": output });
+        }
+        let changes = task_changes(&before, &self.task_list.tasks);
+        let affected: BTreeSet<u32> = changes.keys().copied().collect();
+        let task = match changes.values().collect::<Vec<_>>().as_slice() {
+            [(_, after)] => after.clone(),
+            _ => None,
+        };
+        serde_json::json!({ "ok": true, "affected": affected, "task": task, "output": output })
+    }
+
+    /// The formatter for `format`, set up by the config.
+    fn formatter(&self, format: Format) -> Box<dyn Formatter> {
+        let mut formatter = format.formatter();
+        formatter.configure(&self.config);
+        formatter
+    }
+
+    /// Prints a listing through the `--format` formatter. Returns false, printing
+    /// nothing, when no format was chosen.
+    fn print_formatted(&self, tasks: &[&Task]) -> Result<bool, Box<dyn std::error::Error>> {
+        let Some(format) = self.format else {
+            return Ok(false);
+        };
+        let tasks = self.task_list.subset(tasks);
+        // Each NDJSON line is complete, so it can go straight out.
+        if format == Format::NDJSON && self.captured.borrow().is_none() {
+            let mut out = io::BufWriter::new(io::stdout().lock());
+            self.formatter(format).write(&tasks, &mut out)?;
+            out.flush()?;
+            return Ok(true);
+        }
+        let text = self.formatter(format).format(&tasks)?;
+        say!(self, "{}", text.trim_end());
+        Ok(true)
+    }
+
+    /// Runs a line typed at the prompt: `!!` is replaced by the previous line and `;`
+    /// separates several commands, which run in order until one fails.
+    fn run_input(&mut self, input: &str) -> Result<ControlFlow<()>, Box<dyn std::error::Error>> {
+        let line = shell::expand_history(input, self.last_input.as_deref())?;
+        if line != input {
+            say!(self, "{}", line);
+        }
+        self.last_input = Some(line.clone());
+        for command in shell::split_commands(&line) {
+            if self.run_line(&command)?.is_break() {
+                return Ok(ControlFlow::Break(()));
+            }
+        }
+        Ok(ControlFlow::Continue(()))
+    }
+
+    /// Walks through every open task, asking what to do with each. Every change is
+    /// recorded separately so `undo` reverts the last one.
+    fn review(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.editor.is_none() {
+            return Err("review needs the interactive prompt".into());
+        }
+        let ids: Vec<u32> = self
+            .task_list
+            .ordered()
+            .into_iter()
+            .filter(|task| task.status != TaskStatus::Completed)
+            .map(|task| task.id)
+            .collect();
+        for (n, &id) in ids.iter().enumerate() {
+            let Ok(task) = self.task_list.task(id) else {
+                continue;
+            };
+            say!(
+                self,
+                "({}/{}) {}",
+                n + 1,
+                ids.len(),
+                display_task(&self.task_list, task, self.color)
+            );
+            loop {
+                let Some(editor) = self.editor.as_mut() else {
+                    return Ok(());
+                };
+                let answer = match editor
+                    .readline("[d]one, [s]nooze [duration], [r]emove, [e]dit, [n]ext, [q]uit: ")
+                {
+                    Ok(answer) => answer,
+                    Err(ReadlineError::Interrupted | ReadlineError::Eof) => return Ok(()),
+                    Err(err) => return Err(err.into()),
+                };
+                let command = match ReviewAction::from_str(&answer.trim().to_lowercase()) {
+                    Ok(ReviewAction::Done) => Command::Done { id, archive: false },
+                    Ok(ReviewAction::Snooze(by)) => Command::Snooze { id, by },
+                    Ok(ReviewAction::Remove) => Command::Remove { id, force: true },
+                    Ok(ReviewAction::Edit) => Command::Edit { id },
+                    Ok(ReviewAction::Skip) => break,
+                    Ok(ReviewAction::Quit) => return Ok(()),
+                    Err(err) => {
+                        say!(self, "{}", err);
+                        continue;
+                    }
+                };
+                self.history.record(&self.task_list);
+                match self.execute(command) {
+                    Ok(_) => break,
+                    Err(err) => say!(self, "{}", err),
+                }
+            }
+        }
+        say!(self, "Review finished.");
+        Ok(())
+    }
+
+    /// Lets the user pick a task when a command that needs a task id is typed on its own
+    /// at an interactive prompt. Returns `None` when nothing was picked.
+    fn pick_task(&self, line: &str) -> Result<Option<String>, Box<dyn std::error::Error>> {
+        let items: Vec<(u32, String)> = self
+            .task_list
+            .ordered()
+            .into_iter()
+            .map(|task| (task.id, task.description.clone()))
+            .collect();
+        Ok(picker::pick("Task: ", &items)?.map(|id| format!("{} {}", line, id)))
+    }
+
+    /// Writes a file the session produces, or only reports it in a dry run.
+    fn export(
+        &self,
+        formatter: &dyn Formatter,
+        out_file: Option<&str>,
+        filter: Option<&ListFilter>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let subset;
+        let tasks = match filter {
+            Some(filter) => {
+                let listing = self.task_list.listing(filter, Local::now().date_naive());
+                subset = self.task_list.subset(&listing);
+                &subset
+            }
+            None => &self.task_list,
+        };
+        if let Some(out_file) = out_file.filter(|_| !self.dry_run) {
+            storage::write_atomic_with(Path::new(out_file), |file| {
+                let mut out = io::BufWriter::new(file);
+                formatter
+                    .write(tasks, &mut out)
+                    .map_err(|err| io::Error::other(err.to_string()))?;
+                out.flush()
+            })?;
+        } else {
+            let content = tasks.export_to_string(formatter)?;
+            match out_file {
+                Some(out_file) => self.write_file(out_file, &content)?,
+                None if self.dry_run => {
+                    say!(self, "Would copy {} bytes to the clipboard", content.len())
+                }
+                None => {
+                    arboard::Clipboard::new()
+                        .and_then(|mut clipboard| clipboard.set_text(content))
+                        .map_err(|err| format!("Can't copy to the clipboard: {}", err))?;
+                    say!(
+                        self,
+                        "Copied {} task(s) to the clipboard",
+                        tasks.tasks.len()
+                    );
+                }
+            }
+        }
+        self.hook(
+            "on-export",
+            &serde_json::json!({ "file": out_file, "tasks": tasks.tasks }),
+        );
+        Ok(())
+    }
+
+    fn write_file(
+        &self,
+        path: impl AsRef<Path>,
+        contents: impl AsRef<[u8]>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let path = path.as_ref();
+        let contents = contents.as_ref();
+        if self.dry_run {
+            say!(
+                self,
+                "Would write {} ({} bytes)",
+                path.display(),
+                contents.len()
+            );
+        } else {
+            storage::write_atomic(path, contents)?;
+        }
+        Ok(())
+    }
+
+    fn run_line(&mut self, line: &str) -> Result<ControlFlow<()>, Box<dyn std::error::Error>> {
+        let mut line = shell::expand_alias(line, &self.config.aliases)?;
+        if self.editor.is_some() && io::stdin().is_terminal() && help::takes_task_id(line.trim()) {
+            match self.pick_task(line.trim())? {
+                Some(picked) => line = picked,
+                None => return Ok(ControlFlow::Continue(())),
+            }
+        }
+        let command = Command::from_str(&self.task_list.resolve_aliases(&line)?)?;
+        let generation = self.history.generation;
+        let before = self.storage.is_some().then(|| self.task_list.tasks.clone());
+        let result = self.run_command(command);
+        // Journaled even when the command failed, as it may have changed some tasks first
+        // and those changes are saved below either way.
+        if let (Some(storage), Some(before)) = (&self.storage, before)
+            && generation != self.history.generation
+            && !self.dry_run
+        {
+            let changes: BTreeMap<u32, Option<Task>> = task_changes(&before, &self.task_list.tasks)
+                .into_keys()
+                .map(|id| (id, self.task_list.task(id).ok().cloned()))
+                .collect();
+            if !changes.is_empty() {
+                storage.append_event(&Event {
+                    at: Local::now(),
+                    command: line,
+                    changes,
+                })?;
+            }
+        }
+        self.autosave(false)?;
+        result
+    }
+
+    fn run_command(
+        &mut self,
+        command: Command,
+    ) -> Result<ControlFlow<()>, Box<dyn std::error::Error>> {
+        let before = command.mutates().then(|| self.task_list.clone());
+        let result = self.watch(command);
+        // Failed commands are recorded too when they changed some tasks before failing,
+        // while declined or failed ones that changed nothing leave undo as it was.
+        if let Some(before) = before
+            && serde_json::to_value(&before).ok() != serde_json::to_value(&self.task_list).ok()
+        {
+            self.history.record(&before);
+        }
+        result
+    }
+
+    /// Executes `command`, reporting its changes in a dry run and running the hooks they
+    /// trigger.
+    fn watch(&mut self, command: Command) -> Result<ControlFlow<()>, Box<dyn std::error::Error>> {
+        let watch = self.dry_run || !self.config.hooks.is_empty();
+        if !watch || matches!(command, Command::Source { .. }) {
+            return self.execute(command);
+        }
+        let before = self.task_list.tasks.clone();
+        let flow = self.execute(command)?;
+        for (id, (before, after)) in task_changes(&before, &self.task_list.tasks) {
+            if self.dry_run {
+                say!(
+                    self,
+                    "{}",
+                    describe_change(id, before.as_ref(), after.as_ref())
+                );
+            }
+            let Some(after) = after else {
+                continue;
+            };
+            let status = |task: &serde_json::Value| task["status"] == "Completed";
+            match before {
+                None => self.hook("on-add", &after),
+                Some(before) if status(&after) && !status(&before) => self.hook("on-done", &after),
+                Some(_) => {}
+            }
+        }
+        Ok(flow)
+    }
+
+    /// Hands the task list to the storage backend, if there is one.
+    fn save(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(storage) = &self.storage {
+            storage.save(&self.task_list)?;
+        }
+        self.saved = (self.history.generation, Some(Instant::now()));
+        Ok(())
+    }
+
+    /// Saves the task list if it changed since the last save, unless autosave is off or
+    /// the last save was less than `autosave_delay_ms` ago. `finished` saves regardless
+    /// of the delay, for when the session ends.
+    fn autosave(&mut self, finished: bool) -> Result<(), Box<dyn std::error::Error>> {
+        let (generation, at) = self.saved;
+        if !self.config.autosave || self.dry_run || generation == self.history.generation {
+            return Ok(());
+        }
+        let delay = Duration::from_millis(self.config.autosave_delay_ms);
+        if !finished && at.is_some_and(|at| at.elapsed() < delay) {
+            return Ok(());
+        }
+        self.save()
+    }
+
+    /// Runs the hook configured for `name`, if any, with `input` as JSON on stdin. A
+    /// failing hook is reported but doesn't undo the command.
+    fn hook(&self, name: &str, input: &serde_json::Value) {
+        let Some(command) = self.config.hooks.get(name) else {
+            return;
+        };
+        if self.dry_run {
+            say!(self, "Would run the {} hook", name);
+        } else if let Err(err) = run_hook(name, command, &input.to_string()) {
+            eprintln!("The {} hook failed: {}", name, err);
+        }
+    }
+
+    fn execute(&mut self, command: Command) -> Result<ControlFlow<()>, Box<dyn std::error::Error>> {
+        match command {
+            Command::Add { val, context, tags } => {
+                let mut task = Task::new(self.counter, val);
+                task.context = context;
+                task.tags = tags;
+                task.project = self.config.project.clone();
+                self.task_list.add(task);
+                self.counter += 1;
+            }
+            Command::AddFromTemplate { name } => {
+                for task in self.templates.instantiate(&name, self.counter)? {
+                    self.task_list.add(task);
+                    self.counter += 1;
+                }
+            }
+            Command::AddFromFile {
+                path,
+                context,
+                tags,
+                due,
+            } => {
+                let text = fs::read_to_string(&path)?;
+                let first = self.counter;
+                for line in text.lines() {
+                    let parts: Vec<&str> = line.split_whitespace().collect();
+                    let (words, own_context, own_tags) = split_markers(&parts);
+                    if words.is_empty() {
+                        continue;
+                    }
+                    let mut task = Task::new(self.counter, words.join(" "));
+                    task.context = own_context.or_else(|| context.clone());
+                    task.tags = tags.clone();
+                    for tag in own_tags {
+                        if !task.tags.contains(&tag) {
+                            task.tags.push(tag);
+                        }
+                    }
+                    task.due = due;
+                    task.project = self.config.project.clone();
+                    self.task_list.add(task);
+                    self.counter += 1;
+                }
+                say!(self, "Added {} task(s) from {}", self.counter - first, path);
+            }
+            Command::Template { action } => match action {
+                TemplateAction::Save { name, ids } => {
+                    self.templates.save_from(name, &self.task_list, &ids)?;
+                    self.save_templates()?;
+                }
+                TemplateAction::Remove { name } => {
+                    self.templates.remove(&name)?;
+                    self.save_templates()?;
+                }
+                TemplateAction::List => {
+                    for template in &self.templates.templates {
+                        say!(self, "{} ({} tasks)", template.name, template.tasks.len());
+                    }
+                }
+            },
+            Command::Remove { id, force } => {
+                let question = format!("Remove task {}? [y/N] ", self.task_list.task(id)?);
+                if force || self.confirm(&question)? {
+                    self.task_list.remove(id);
+                }
+            }
+            Command::Update { id, new_val, field } => match field {
+                TaskField::Description => self.task_list.update_description(id, new_val)?,
+                TaskField::Status => {
+                    let status = TaskStatus::from_str(&new_val.to_lowercase())?;
+                    if status == TaskStatus::InProgress {
+                        self.task_list.check_wip(id, self.config.wip_limit)?;
+                    }
+                    self.task_list.update_status(id, status)?
+                }
+                TaskField::Due => self
+                    .task_list
+                    .update_due(id, parse_date(&new_val, Local::now().date_naive())?)?,
+                TaskField::Scheduled => self
+                    .task_list
+                    .update_scheduled(id, parse_date(&new_val, Local::now().date_naive())?)?,
+                TaskField::WaitingOn => {
+                    let waiting_on = Some(new_val).filter(|name| name != "none");
+                    self.task_list.update_waiting_on(id, waiting_on)?
+                }
+                TaskField::Points => self
+                    .task_list
+                    .update_points(id, parse_optional(&new_val)?)?,
+                TaskField::Sprint => self
+                    .task_list
+                    .update_sprint(id, Some(new_val).filter(|sprint| sprint != "none"))?,
+                TaskField::Milestone => self
+                    .task_list
+                    .update_milestone(id, parse_optional(&new_val)?)?,
+                TaskField::Progress => self
+                    .task_list
+                    .update_progress(id, parse_optional(new_val.trim_end_matches('%'))?)?,
+                TaskField::Parent => self
+                    .task_list
+                    .update_parent(id, parse_optional(&new_val)?)?,
+                TaskField::Location => self
+                    .task_list
+                    .update_location(id, Some(new_val).filter(|location| location != "none"))?,
+                TaskField::Color => self.task_list.update_color(
+                    id,
+                    Some(new_val.to_lowercase()).filter(|color| color != "none"),
+                )?,
+                TaskField::Tags => self.task_list.update_tags(
+                    id,
+                    new_val
+                        .split(',')
+                        .map(|tag| tag.trim().trim_start_matches('+').to_lowercase())
+                        .filter(|tag| !tag.is_empty() && tag != "none")
+                        .collect(),
+                )?,
+                TaskField::Project => self
+                    .task_list
+                    .update_project(id, Some(new_val).filter(|project| project != "none"))?,
+                TaskField::Url => self
+                    .task_list
+                    .update_url(id, Some(new_val).filter(|url| url != "none"))?,
+                TaskField::Icon => self
+                    .task_list
+                    .update_icon(id, Some(new_val).filter(|icon| icon != "none"))?,
+                TaskField::Energy => self
+                    .task_list
+                    .update_energy(id, parse_optional(&new_val.to_lowercase())?)?,
+                TaskField::Priority => self
+                    .task_list
+                    .update_priority(id, parse_optional(&new_val.to_lowercase())?)?,
+                TaskField::Recur => self
+                    .task_list
+                    .update_recurrence(id, parse_optional(&new_val.to_lowercase())?)?,
+            },
+            Command::Attach { id, target } => self.task_list.attach(id, target)?,
+            Command::List { filter } => {
+                let today = Local::now().date_naive();
+                let now = Local::now();
+                let tasks = self.task_list.listing(&filter, today);
+                if self.print_formatted(&tasks)? {
+                    return Ok(ControlFlow::Continue(()));
+                }
+                for task in tasks {
+                    let marker = if task.is_stale(now, self.config.stale_days) {
+                        " [stale]"
+                    } else {
+                        ""
+                    };
+                    say!(
+                        self,
+                        "{}{}",
+                        display_task(&self.task_list, task, self.color),
+                        marker
+                    );
+                }
+            }
+            Command::Contexts => {
+                for (context, count) in self.task_list.context_counts() {
+                    say!(self, "@{}: {}", context, count);
+                }
+            }
+            Command::Remind { id, when } => {
+                let at = parse_datetime(&when, Local::now().naive_local())?;
+                self.task_list.remind(id, at)?;
+            }
+            Command::Reminders { notify: enable } => {
+                if enable {
+                    self.notify = true;
+                    say!(self, "Desktop notifications enabled for this session.");
+                }
+                for (task, at) in self.task_list.upcoming_reminders() {
+                    say!(
+                        self,
+                        "{} {}: {}",
+                        at.format("%Y-%m-%d %H:%M"),
+                        task.id,
+                        task.description
+                    );
+                }
+            }
+            Command::Snooze { id, by } => {
+                self.task_list.snooze(id, by, Local::now().date_naive())?;
+            }
+            Command::History { id } => {
+                for event in &self.task_list.task(id)?.history {
+                    say!(self, "{}", event);
+                }
+            }
+            Command::Delegated => {
+                for (waiting_on, tasks) in self.task_list.delegated() {
+                    say!(self, "{}:", waiting_on);
+                    for task in tasks {
+                        say!(
+                            self,
+                            "  {}",
+                            display_task(&self.task_list, task, self.color)
+                        );
+                    }
+                }
+            }
+            Command::SprintSummary { sprint } => {
+                for (name, summary) in self.task_list.sprint_summaries() {
+                    if sprint.as_deref().is_none_or(|sprint| sprint == name) {
+                        say!(self, "{}: {}", name, summary);
+                    }
+                }
+            }
+            Command::Milestone { action } => match action {
+                MilestoneAction::Add { name } => {
+                    let id = self.task_list.add_milestone(name);
+                    say!(self, "Created milestone {}", id);
+                }
+                MilestoneAction::Remove { id } => self.task_list.remove_milestone(id)?,
+            },
+            Command::Milestones => {
+                for (milestone, completed, total) in self.task_list.milestone_progress() {
+                    let percent = (completed * 100).checked_div(total).unwrap_or(0);
+                    say!(
+                        self,
+                        "{}: {}\t{}% ({}/{} tasks)",
+                        milestone.id,
+                        milestone.name,
+                        percent,
+                        completed,
+                        total
+                    );
+                }
+            }
+            Command::Stale { days } => {
+                let now = Local::now();
+                let days = days.unwrap_or(self.config.stale_days);
+                let stale: Vec<&Task> = self
+                    .task_list
+                    .tasks
+                    .iter()
+                    .filter(|task| task.is_stale(now, days))
+                    .collect();
+                if self.print_formatted(&stale)? {
+                    return Ok(ControlFlow::Continue(()));
+                }
+                for task in stale {
+                    say!(self, "{}", display_task(&self.task_list, task, self.color));
+                }
+            }
+            Command::Skip { id } => self.task_list.skip(id)?,
+            Command::Except { id, date } => match parse_date(&date, Local::now().date_naive())? {
+                Some(date) => self.task_list.add_exception(id, date)?,
+                None => return Err("An exception needs a date".into()),
+            },
+            Command::Link { id, kind, other } => self.task_list.link(id, kind, other)?,
+            Command::Unlink { id, other } => self.task_list.unlink(id, other)?,
+            Command::Next { count, energy } => {
+                let mut candidates = self
+                    .task_list
+                    .next_candidates(energy, Local::now().date_naive());
+                candidates.truncate(count);
+                if self.print_formatted(&candidates)? {
+                    return Ok(ControlFlow::Continue(()));
+                }
+                if candidates.is_empty() {
+                    say!(self, "Nothing to work on right now.");
+                }
+                for task in candidates {
+                    say!(self, "{}", display_task(&self.task_list, task, self.color));
+                }
+            }
+            Command::Open { id } => match &self.task_list.task(id)?.url {
+                Some(url) => open_in_browser(url)?,
+                None => return Err(format!("Task with id {} has no url", id).into()),
+            },
+            Command::Find { query } => {
+                let found = self.task_list.find(&query);
+                if self.print_formatted(&found)? {
+                    return Ok(ControlFlow::Continue(()));
+                }
+                if found.is_empty() {
+                    say!(self, "No tasks match {}.", query);
+                }
+                for task in found {
+                    say!(self, "{}", display_task(&self.task_list, task, self.color));
+                }
+            }
+            Command::Sort { order } => self.task_list.set_sort(order),
+            Command::Done { id, archive } => {
+                self.task_list.update_status(id, TaskStatus::Completed)?;
+                if archive {
+                    if self.task_list.task(id)?.recurrence.is_some() {
+                        say!(self, "Task {} repeats, so it was kept.", id);
+                    } else {
+                        self.task_list.archive(id)?;
+                    }
+                }
+            }
+            Command::Edit { id } => {
+                let original = serde_yaml::to_string(&TaskEdit::from(self.task_list.task(id)?))?;
+                let edited = edit_in_editor(&original)?;
+                if edited != original {
+                    self.task_list
+                        .apply_edit(id, serde_yaml::from_str(&edited)?)?;
+                }
+            }
+            Command::Archived => {
+                let archived: Vec<&Task> = self.task_list.archived.iter().collect();
+                if self.print_formatted(&archived)? {
+                    return Ok(ControlFlow::Continue(()));
+                }
+                for task in archived {
+                    say!(self, "{}", task);
+                }
+            }
+            Command::Help { topic: None } => say!(self, "{}", help::menu()),
+            Command::Help { topic: Some(topic) } => match help::lookup(&topic) {
+                Some(spec) => say!(self, "{}", spec.detail()),
+                None => say!(self, "Unknown command {}, try help for a list.", topic),
+            },
+            Command::Move { id, target } => self.task_list.move_task(id, target)?,
+            Command::Duplicate {
+                id,
+                with_subtasks,
+                keep_tags,
+            } => {
+                for task in self
+                    .task_list
+                    .duplicate(id, self.counter, with_subtasks, keep_tags)?
+                {
+                    say!(self, "Added {}", task);
+                    self.task_list.add(task);
+                    self.counter += 1;
+                }
+            }
+            Command::Clear {
+                older_than,
+                archive,
+                force,
+            } => {
+                let question = "Permanently remove completed tasks? [y/N] ";
+                if archive || force || self.confirm(question)? {
+                    let cleared =
+                        self.task_list
+                            .clear_completed(Local::now(), older_than, archive)?;
+                    let verb = if archive { "Archived" } else { "Cleared" };
+                    say!(self, "{} {} completed task(s).", verb, cleared);
+                }
+            }
+            Command::Show { id } => {
+                let task = self.task_list.task(id)?;
+                say!(self, "{}", task_card(&self.task_list, task, self.color));
+            }
+            Command::Start { id, track } => {
+                self.task_list.check_wip(id, self.config.wip_limit)?;
+                self.task_list.start(id, track, Local::now())?;
+            }
+            Command::Pause { id } => self.task_list.pause(id, Local::now())?,
+            Command::Extend { id, text, prepend } => {
+                self.task_list.extend_description(id, &text, prepend)?
+            }
+            Command::Transfer { id, project, keep } => {
+                let moved = self.task_list.subtree(id)?;
+                let file = project_file(&project, &self.projects_dir);
+                // Held while the other list is read and saved, so a todore that has it
+                // open can't save over the tasks moved in.
+                let _lock = if self.dry_run {
+                    None
+                } else {
+                    Some(FileLock::acquire(&file, false).map_err(|err| err.to_string())?)
+                };
+                let storage = file_storage(&file, &self.config)?;
+                let mut target = storage.load()?;
+                let adopted = target.adopt(moved);
+                if self.dry_run {
+                    say!(self, "Would write {}", file.display());
+                } else {
+                    storage.save(&target)?;
+                }
+                for (old, new) in adopted {
+                    say!(self, "{} -> {} in {}", old, new, file.display());
+                    if !keep {
+                        self.task_list.remove(old);
+                    }
+                }
+            }
+            Command::Import { format, file, mode } => {
+                let text = fs::read_to_string(&file)?;
+                let imported = match format {
+                    Format::JIRA => {
+                        let mapping = jira::Mapping::load(self.config.jira_mapping.as_deref())?;
+                        jira::parse(&text, &mapping)?
+                    }
+                    _ => format.parse(&text)?,
+                };
+                let count = imported.tasks.len();
+                match mode {
+                    ImportMode::Add => {
+                        self.task_list.adopt(imported.tasks);
+                        say!(self, "Imported {} task(s) from {}", count, file);
+                    }
+                    ImportMode::Merge => {
+                        let (added, updated, skipped) = self.task_list.merge_import(imported.tasks);
+                        say!(
+                            self,
+                            "Merged {} task(s) from {}: {} added, {} updated, {} skipped",
+                            count,
+                            file,
+                            added,
+                            updated,
+                            skipped
+                        );
+                    }
+                    ImportMode::Replace => {
+                        if !self.confirm("Replace every task with the imported ones? [y/N] ")? {
+                            return Ok(ControlFlow::Continue(()));
+                        }
+                        self.task_list.replace(imported);
+                        say!(self, "Imported {} task(s) from {}", count, file);
+                    }
+                }
+                for id in self.task_list.break_cycles() {
+                    say!(
+                        self,
+                        "Task {} was its own ancestor, so its parent was cleared",
+                        id
+                    );
+                }
+                self.counter = self.counter.max(self.task_list.next_id());
+            }
+            Command::Snapshot { action } => {
+                let storage = self
+                    .storage
+                    .as_ref()
+                    .ok_or("There are no snapshots without a task file")?;
+                match action {
+                    SnapshotAction::Create { name } => {
+                        let snapshot = Snapshot {
+                            created: Local::now(),
+                            tasks: self.task_list.clone(),
+                            templates: self.templates.clone(),
+                        };
+                        if self.dry_run {
+                            say!(self, "Would create snapshot {}", name);
+                        } else {
+                            storage.save_snapshot(&name, &snapshot)?;
+                            say!(self, "Created snapshot {}", name);
+                        }
+                    }
+                    SnapshotAction::List => {
+                        for name in storage.snapshots()? {
+                            let snapshot = storage.load_snapshot(&name)?;
+                            say!(
+                                self,
+                                "{}  {}  ({} task(s), {} archived)",
+                                name,
+                                snapshot.created.format("%Y-%m-%d %H:%M"),
+                                snapshot.tasks.tasks.len(),
+                                snapshot.tasks.archived.len()
+                            );
+                        }
+                    }
+                    SnapshotAction::Restore { name } => {
+                        let snapshot = storage.load_snapshot(&name)?;
+                        let question = format!(
+                            "Replace every task and template with snapshot {}? [y/N] ",
+                            name
+                        );
+                        if !self.confirm(&question)? {
+                            return Ok(ControlFlow::Continue(()));
+                        }
+                        self.task_list = snapshot.tasks;
+                        self.templates = snapshot.templates;
+                        self.counter = self.counter.max(self.task_list.next_id());
+                        if !self.templates.templates.is_empty()
+                            || Path::new(&self.templates_file).exists()
+                        {
+                            self.save_templates()?;
+                        }
+                        say!(self, "Restored snapshot {}", name);
+                    }
+                }
+            }
+            Command::Backup { action } => {
+                let dir = bootstrap::settings_dir(self.profile.as_deref());
+                match action {
+                    BackupAction::Export { file } => {
+                        let mut files = BTreeMap::from([
+                            (
+                                backup::TASKS,
+                                serde_json::to_string_pretty(&self.task_list)?,
+                            ),
+                            (
+                                backup::TEMPLATES,
+                                serde_json::to_string_pretty(&self.templates)?,
+                            ),
+                        ]);
+                        for name in [backup::CONFIG, backup::HISTORY] {
+                            if let Ok(contents) = fs::read_to_string(dir.join(name)) {
+                                files.insert(name, contents);
+                            }
+                        }
+                        self.write_file(&file, backup::pack(&files)?)?;
+                        say!(self, "Backed up {} file(s) to {}", files.len(), file);
+                    }
+                    BackupAction::Import { file } => {
+                        let files = backup::unpack(&fs::read(&file)?)?;
+                        if !self.confirm(
+                            "Replace every task, template and setting with the backup's? [y/N] ",
+                        )? {
+                            return Ok(ControlFlow::Continue(()));
+                        }
+                        self.task_list.import(&files[backup::TASKS])?;
+                        self.counter = self.counter.max(self.task_list.next_id());
+                        if let Some(templates) = files.get(backup::TEMPLATES) {
+                            self.templates = serde_json::from_str(templates)?;
+                            self.save_templates()?;
+                        }
+                        // An in-memory session has nowhere of its own to put these.
+                        if self.storage.is_some() {
+                            for name in [backup::CONFIG, backup::HISTORY] {
+                                if let Some(contents) = files.get(name) {
+                                    self.write_file(dir.join(name), contents)?;
+                                }
+                            }
+                        }
+                        say!(
+                            self,
+                            "Restored {} from {}",
+                            files.keys().cloned().collect::<Vec<_>>().join(", "),
+                            file
+                        );
+                        if files.contains_key(backup::CONFIG) {
+                            say!(self, "Restart todore to use the restored config.toml");
+                        }
+                    }
+                }
+            }
+            Command::Profiles => {
+                let marker = |current: bool| if current { "* " } else { "  " };
+                say!(self, "{}default", marker(self.profile.is_none()));
+                for name in bootstrap::profiles()? {
+                    let current = self.profile.as_ref() == Some(&name);
+                    say!(self, "{}{}", marker(current), name);
+                }
+            }
+            Command::Journal { count, replay } => {
+                let events = self
+                    .storage
+                    .as_ref()
+                    .ok_or("There is no journal without a task file")?
+                    .events()?;
+                if !replay {
+                    for event in &events[events.len().saturating_sub(count)..] {
+                        say!(
+                            self,
+                            "{}  {}  ({} task(s))",
+                            event.at.format("%Y-%m-%d %H:%M"),
+                            event.command,
+                            event.changes.len()
+                        );
+                    }
+                    return Ok(ControlFlow::Continue(()));
+                }
+                let mut rebuilt = TaskList::new();
+                for event in &events {
+                    event.apply(&mut rebuilt);
+                }
+                let question = format!(
+                    "Replace every task with the {} rebuilt from {} journal entries? [y/N] ",
+                    rebuilt.tasks.len(),
+                    events.len()
+                );
+                if !self.confirm(&question)? {
+                    return Ok(ControlFlow::Continue(()));
+                }
+                self.task_list.tasks = rebuilt.tasks;
+                self.counter = self.counter.max(self.task_list.next_id());
+            }
+            Command::Alias { id, alias } => self.task_list.update_alias(id, alias)?,
+            Command::Merge { source, target } => self.task_list.merge(source, target)?,
+            Command::Split {
+                id,
+                mut parts,
+                as_subtasks,
+            } => {
+                self.task_list.task(id)?;
+                if parts.is_empty() {
+                    parts = self.read_parts()?;
+                }
+                for id in self.task_list.split(id, parts, self.counter, as_subtasks)? {
+                    say!(self, "Added {}", self.task_list.task(id)?);
+                    self.counter += 1;
+                }
+            }
+            Command::Review => self.review()?,
+            Command::Calendar { month } => {
+                let today = Local::now().date_naive();
+                let month = match month {
+                    Some(month) => view::parse_month(&month, today)?,
+                    None => today,
+                };
+                say!(
+                    self,
+                    "{}",
+                    view::calendar(&self.task_list.tasks, month, today)
+                );
+            }
+            Command::Agenda { span } => {
+                let today = Local::now().date_naive();
+                let agenda = view::agenda(self.task_list.ordered(), span, today);
+                let tasks: Vec<&Task> =
+                    agenda.iter().flat_map(|(_, tasks)| tasks.clone()).collect();
+                if self.print_formatted(&tasks)? {
+                    return Ok(ControlFlow::Continue(()));
+                }
+                if agenda.is_empty() {
+                    say!(self, "Nothing due.");
+                }
+                for (bucket, tasks) in agenda {
+                    say!(self, "{} ({})", bucket, tasks.len());
+                    for task in tasks {
+                        say!(
+                            self,
+                            "  {}",
+                            display_task(&self.task_list, task, self.color)
+                        );
+                    }
+                }
+            }
+            Command::Pomodoro { id, length } => {
+                let description = self.task_list.task(id)?.description.clone();
+                say!(
+                    self,
+                    "Pomodoro of {} for {}",
+                    format_duration(length),
+                    description
+                );
+                let ran = pomodoro::countdown(length.to_std()?)?;
+                let spent = TimeDelta::from_std(ran)?;
+                self.task_list.log_time(id, spent)?;
+                if ran >= length.to_std()? {
+                    say!(self, "\x07Pomodoro finished: {}", description);
+                    send_notification("Pomodoro finished", &description);
+                } else {
+                    say!(self, "Pomodoro stopped after {}", format_duration(spent));
+                }
+            }
+            Command::Roulette { weighted } => {
+                let roll = RandomState::new().build_hasher().finish();
+                let today = Local::now().date_naive();
+                let Some(task) = self.task_list.roulette(weighted, today, roll) else {
+                    say!(self, "Nothing to pick from.");
+                    return Ok(ControlFlow::Continue(()));
+                };
+                say!(self, "{}", display_task(&self.task_list, task, self.color));
+                let id = task.id;
+                if task.status == TaskStatus::NotStarted
+                    && let Some(editor) = self.editor.as_mut()
+                    && confirm(editor, "Start it now? [y/N] ")?
+                {
+                    self.history.record(&self.task_list);
+                    return self.execute(Command::Start { id, track: false });
+                }
+            }
+            Command::Count => say!(self, "{}", self.task_list.counts(Local::now().date_naive())),
+            Command::Source { path, keep_going } => self.run_script(&path, keep_going)?,
+            Command::Undo => self.history.undo(&mut self.task_list)?,
+            Command::Redo => self.history.redo(&mut self.task_list)?,
+            Command::Quit => return Ok(ControlFlow::Break(())),
+            Command::Export {
+                format,
+                out_file,
+                filter,
+            } => {
+                let formatter = self.formatter(format);
+                self.export(formatter.as_ref(), out_file.as_deref(), filter.as_ref())?;
+            }
+            Command::ExportTemplate {
+                template,
+                out_file,
+                filter,
+            } => {
+                let formatter = TemplateFormatter::load(&template)?;
+                self.export(&formatter, Some(&out_file), filter.as_ref())?;
+            }
+            Command::ExportExec {
+                command,
+                out_file,
+                filter,
+            } => {
+                // The program could do anything, so like hooks it isn't run in a dry run.
+                if self.dry_run {
+                    say!(self, "Would run {} and write {}", command, out_file);
+                } else {
+                    self.export(&ExecFormatter { command }, Some(&out_file), filter.as_ref())?;
+                }
+            }
+        }
+        Ok(ControlFlow::Continue(()))
+    }
+}
+
+/// A terminal TODO list.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+struct Cli {
+    /// The task file to use instead of the one in the platform data directory, or a URL:
+    /// `https://...` or `s3://bucket/key`
+    #[arg(short, long, value_name = "PATH", env = "TODORE_FILE")]
+    file: Option<PathBuf>,
+    /// Keep tasks, config, templates and history in a separate profile directory, e.g. `work`
+    #[arg(long, value_name = "NAME", env = "TODORE_PROFILE")]
+    profile: Option<String>,
+    /// Run the commands in FILE instead of starting the interactive prompt
+    #[arg(long, value_name = "FILE")]
+    script: Option<String>,
+    /// Keep running a script or piped commands after one fails, reporting the error
+    #[arg(long)]
+    keep_going: bool,
+    /// Print without colours (also set by the NO_COLOR environment variable)
+    #[arg(long)]
+    no_color: bool,
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        help = format!("Print listings as {} instead of text", REGISTRY.list()),
+        value_parser = |s: &str| Format::from_str(&s.to_lowercase())
+    )]
+    format: Option<Format>,
+    /// Wait for another todore using the task file to exit instead of failing
+    #[arg(long, conflicts_with = "no_lock")]
+    wait: bool,
+    /// Don't lock the task file; changes made by another todore meanwhile may be lost
+    #[arg(long)]
+    no_lock: bool,
+    /// Start with no tasks and keep them in memory only, leaving the task file and
+    /// templates untouched, e.g. to triage a pasted list
+    #[arg(long)]
+    ephemeral: bool,
+    /// Run commands as usual but write no files, printing what would change instead
+    #[arg(long)]
+    dry_run: bool,
+    /// Print the result of the command as a JSON object instead of text
+    #[arg(long, requires = "command")]
+    json: bool,
+    /// Print the man page and exit, e.g. `todore --man > ~/.local/share/man/man1/todore.1`
+    #[arg(long, exclusive = true)]
+    man: bool,
+    /// Run this one command and exit, e.g. `todore list --tag work`
+    #[arg(
+        trailing_var_arg = true,
+        allow_hyphen_values = true,
+        conflicts_with = "script"
+    )]
+    command: Vec<String>,
+}
+
+impl Cli {
+    /// The clap command with the REPL commands and environment appended to `--help`.
+    fn command_with_help() -> clap::Command {
+        Cli::command().after_long_help(help::long_help())
+    }
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli =
+        Cli::from_arg_matches(&Cli::command_with_help().get_matches()).unwrap_or_else(|e| e.exit());
+    if cli.man {
+        print!("{}", help::man_page(Cli::command_with_help())?);
+        return Ok(());
+    }
+
+    // A profile keeps its settings next to its tasks instead of in the working directory.
+    if let Some(name) = &cli.profile {
+        bootstrap::check_profile(name)?;
+    }
+    let dir = bootstrap::settings_dir(cli.profile.as_deref());
+    if cli.profile.is_some() && !cli.dry_run {
+        fs::create_dir_all(&dir)?;
+    }
+    let config_file = dir.join("config.toml").display().to_string();
+    let config_file = config_file.as_str();
+    let config = Config::load(config_file)?;
+    if cli.ephemeral {
+        let mut session = Session::new(TaskList::new(), config, TemplateStore::default(), "");
+        session.apply_cli(&cli);
+        return run(&mut session, cli);
+    }
+
+    let default_file = bootstrap::default_file(cli.profile.as_deref());
+    let user_config = bootstrap::user_config(cli.profile.as_deref());
+    let chosen = cli
+        .file
+        .clone()
+        .or_else(|| config.file.clone())
+        .or_else(|| bootstrap::recorded_file(&user_config));
+    if chosen.is_none() && cli.profile.is_none() && !cli.dry_run {
+        let legacy = Path::new("tasks.json");
+        bootstrap::migrate(legacy, &default_file, || {
+            eprintln!(
+                "Moving the task list in {} to {}, where todore keeps it now",
+                legacy.display(),
+                default_file.display()
+            )
+        })?;
+    }
+    let first_run = chosen.is_none()
+        && !default_file.exists()
+        && !cli.dry_run
+        && cli.script.is_none()
+        && cli.command.is_empty()
+        && io::stdin().is_terminal();
+    let (tasks_file, imported) = if first_run {
+        let (file, tasks) = bootstrap::first_run(&default_file, |question| {
+            print!("{}", question);
+            io::stdout().flush()?;
+            let mut answer = String::new();
+            io::stdin().read_line(&mut answer)?;
+            Ok(answer)
+        })?;
+        if file != default_file {
+            if let Some(dir) = user_config
+                .parent()
+                .filter(|dir| !dir.as_os_str().is_empty())
+            {
+                fs::create_dir_all(dir)?;
+            }
+            let user_config = user_config.display().to_string();
+            config::record(&user_config, "file", &file.display().to_string())?;
+        }
+        (file, tasks)
+    } else {
+        (chosen.unwrap_or(default_file), None)
+    };
+    // Held until todore exits so another instance can't save over our changes.
+    // A remote list is guarded by ETags instead.
+    let remote = remote::is_remote(&tasks_file);
+    let _lock = if cli.no_lock || cli.dry_run || remote {
+        None
+    } else {
+        Some(FileLock::acquire(&tasks_file, cli.wait).map_err(|err| err.to_string())?)
+    };
+    let storage = open_storage(&tasks_file, &config)?;
+    let task_list = match imported {
+        Some(tasks) => {
+            storage.save(&tasks)?;
+            tasks
+        }
+        None => {
+            let (tasks, notes) = bootstrap::load(storage.as_ref(), !cli.dry_run)?;
+            for note in notes {
+                eprintln!("{}", note);
+            }
+            tasks
+        }
+    };
+
+    let templates_file = dir.join("templates.json").display().to_string();
+    let templates = TemplateStore::load(&templates_file)?;
+
+    let mut session = Session::new(task_list, config, templates, &templates_file);
+    session.apply_cli(&cli);
+    session.storage = Some(storage);
+    // A remote list has no directory, so its projects are kept where a local one would be.
+    let tasks_dir = if remote {
+        bootstrap::default_file(session.profile.as_deref())
+    } else {
+        tasks_file
+    };
+    session.projects_dir = tasks_dir
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+    let result = run(&mut session, cli);
+    session.autosave(true)?;
+    result
+}
+
+/// The storage for the task file, remote, a database or a plain file.
+fn open_storage(
+    tasks_file: &Path,
+    config: &Config,
+) -> Result<Box<dyn Storage>, Box<dyn std::error::Error>> {
+    if remote::is_remote(tasks_file) {
+        let url = tasks_file.to_string_lossy();
+        // The list is uploaded as it is, so it would reach the server in the clear.
+        if config.encrypt {
+            return Err(format!(
+                "Can't encrypt {}: remote task files are uploaded in the clear, so use a local \
+                 file or turn encrypt off",
+                url
+            )
+            .into());
+        }
+        return Ok(Box::new(RemoteStorage::open(
+            &url,
+            config.s3_endpoint.as_deref(),
+        )?));
+    }
+    if KvStorage::handles(tasks_file) {
+        // Records in the database aren't sealed, so they'd be kept in the clear.
+        if config.encrypt {
+            return Err(format!(
+                "Can't encrypt {}: .redb task files are kept in the clear, so use a .json file \
+                 or turn encrypt off",
+                tasks_file.display()
+            )
+            .into());
+        }
+        return Ok(Box::new(KvStorage::new(tasks_file)));
+    }
+    Ok(Box::new(file_storage(tasks_file, config)?))
+}
+
+/// The storage for a task file kept on disk, encrypted when it is or `encrypt` is set.
+fn file_storage(
+    tasks_file: &Path,
+    config: &Config,
+) -> Result<FileStorage, Box<dyn std::error::Error>> {
+    let mut storage = FileStorage::new(tasks_file);
+    // An encrypted file always needs its secret; `encrypt` seals a plain one on next save.
+    let sealed = fs::read(tasks_file).is_ok_and(|data| crypto::is_sealed(&data));
+    if sealed || config.encrypt {
+        let secret = crypto::secret(config.key_file.as_deref(), !sealed)?;
+        storage = storage.with_cipher(Cipher::new(secret));
+    }
+    Ok(storage)
+}
+
+/// Runs the script, one-shot command, piped input or interactive prompt chosen by `cli`.
+fn run(session: &mut Session, cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(script) = cli.script {
+        return session.run_script(&script, cli.keep_going);
+    }
+    if !cli.command.is_empty() {
+        let line: Vec<String> = cli.command.iter().map(|word| shell::quote(word)).collect();
+        if cli.json {
+            let result = session.run_json(&line.join(" "));
+            println!("{}", result);
+            if result["ok"] != true {
+                session.autosave(true)?;
+                process::exit(1);
+            }
+            return Ok(());
+        }
+        return session.run_input(&line.join(" ")).map(|_| ());
+    }
+    // Commands piped in on stdin run silently, like a script.
+    if !io::stdin().is_terminal() {
+        let input = io::read_to_string(io::stdin())?;
+        return session.run_lines("stdin", &input, cli.keep_going);
+    }
+
+    println!("Welcome to the Todore TODO list!");
+    let history_file = bootstrap::settings_dir(session.profile.as_deref())
+        .join(backup::HISTORY)
+        .display()
+        .to_string();
+    let history_file = history_file.as_str();
+    let mut editor: Editor<TodoreHelper, DefaultHistory> = Editor::new()?;
+    editor.set_helper(Some(TodoreHelper::default()));
+    if Path::new(history_file).exists() {
+        editor.load_history(history_file)?;
+    }
+    session.editor = Some(editor);
+    loop {
+        for (id, description) in session.task_list.fire_reminders(Local::now().naive_local()) {
+            println!("Reminder: {}: {}", id, description);
+            if session.notify {
+                send_notification("todore reminder", &description);
+            }
+        }
+        if session.config.auto_list && !session.task_list.tasks.is_empty() {
+            println!("Here are your current tasks:");
+            for task in session
+                .task_list
+                .listing(&ListFilter::default(), Local::now().date_naive())
+            {
+                println!("{}", display_task(&session.task_list, task, session.color));
+            }
+        }
+        println!("Below are the options (help <command> for details):");
+        println!("{}", help::menu());
+
+        let Some(input) = session.read_line(history_file)? else {
+            break;
+        };
+        if input.trim().is_empty() {
+            continue;
+        }
+
+        println!("You chose: {}", input.trim());
+        if session.run_input(input.trim())?.is_break() {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update_status() {
+        let mut list = TaskList::new();
+
+        list.add(Task::new(1, "Test".into()));
+        assert!(matches!(list.tasks.len(), 1));
+
+        list.update_status(1, TaskStatus::InProgress).unwrap();
+        assert!(matches!(list.tasks[0].status, TaskStatus::InProgress));
+    }
+
+    #[test]
+    fn test_update_description() {
+        let mut list = TaskList::new();
+
+        list.add(Task::new(2, "Test2".into()));
+        let new_description = "Test123";
+
+        list.update_description(2, new_description.into()).unwrap();
+        assert_eq!(list.tasks[0].description, new_description);
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut list = TaskList::new();
+
+        list.add(Task::new(1, "Test1".into()));
+        assert_eq!(list.tasks.len(), 1);
+
+        list.remove(1);
+        assert_eq!(list.tasks.len(), 0);
+    }
+
+    #[test]
+    fn test_task_new() {
+        let task = Task::new(42, "Test task".to_string());
+        assert_eq!(task.id, 42);
+        assert_eq!(task.description, "Test task");
+        assert!(matches!(task.status, TaskStatus::NotStarted));
+    }
+
+    #[test]
+    fn test_task_serialization() {
+        let task = Task::new(1, "Test task".to_string());
+        let json = serde_json::to_string(&task).unwrap();
+        let deserialized: Task = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized.id, task.id);
+        assert_eq!(deserialized.description, task.description);
+        assert!(matches!(deserialized.status, TaskStatus::NotStarted));
+    }
+
+    // TaskStatus enum tests
+    #[test]
+    fn test_taskstatus_fromstr_valid() {
+        assert!(matches!(
+            TaskStatus::from_str("not started"),
+            Ok(TaskStatus::NotStarted)
+        ));
+        assert!(matches!(
+            TaskStatus::from_str("ns"),
+            Ok(TaskStatus::NotStarted)
+        ));
+        assert!(matches!(
+            TaskStatus::from_str("in progress"),
+            Ok(TaskStatus::InProgress)
+        ));
+        assert!(matches!(
+            TaskStatus::from_str("ip"),
+            Ok(TaskStatus::InProgress)
+        ));
+        assert!(matches!(
+            TaskStatus::from_str("waiting"),
+            Ok(TaskStatus::Waiting)
+        ));
+        assert!(matches!(TaskStatus::from_str("w"), Ok(TaskStatus::Waiting)));
+        assert!(matches!(
+            TaskStatus::from_str("completed"),
+            Ok(TaskStatus::Completed)
+        ));
+        assert!(matches!(
+            TaskStatus::from_str("c"),
+            Ok(TaskStatus::Completed)
+        ));
+    }
+
+    #[test]
+    fn test_taskstatus_fromstr_invalid() {
+        assert!(TaskStatus::from_str("invalid").is_err());
+        assert!(TaskStatus::from_str("").is_err());
+        assert!(TaskStatus::from_str("notstarted").is_err());
+        assert!(TaskStatus::from_str("done").is_err());
+    }
+
+    #[test]
+    fn test_taskstatus_display() {
+        assert_eq!(format!("{}", TaskStatus::NotStarted), "Not Started");
+        assert_eq!(format!("{}", TaskStatus::InProgress), "In Progress");
+        assert_eq!(format!("{}", TaskStatus::Waiting), "Waiting");
+        assert_eq!(format!("{}", TaskStatus::Completed), "Completed");
+    }
+
+    #[test]
+    fn test_taskstatus_serialization() {
+        let statuses = vec![
+            TaskStatus::NotStarted,
+            TaskStatus::InProgress,
+            TaskStatus::Waiting,
+            TaskStatus::Completed,
+        ];
+        for status in statuses {
+            let json = serde_json::to_string(&status).unwrap();
+            let deserialized: TaskStatus = serde_json::from_str(&json).unwrap();
+            assert!(matches!(
+                (status, deserialized),
+                (TaskStatus::NotStarted, TaskStatus::NotStarted)
+                    | (TaskStatus::InProgress, TaskStatus::InProgress)
+                    | (TaskStatus::Waiting, TaskStatus::Waiting)
+                    | (TaskStatus::Completed, TaskStatus::Completed)
+            ));
+        }
+    }
+
+    // TaskList struct tests
+    #[test]
+    fn test_tasklist_new() {
+        let list = TaskList::new();
+        assert_eq!(list.tasks.len(), 0);
+    }
+
+    #[test]
+    fn test_tasklist_add() {
+        let mut list = TaskList::new();
+        let task = Task::new(1, "Test task".to_string());
+        list.add(task);
+        assert_eq!(list.tasks.len(), 1);
+        assert_eq!(list.tasks[0].id, 1);
+        assert_eq!(list.tasks[0].description, "Test task");
+    }
+
+    #[test]
+    fn test_tasklist_update_status_nonexistent() {
+        let mut list = TaskList::new();
+        let result = list.update_status(999, TaskStatus::Completed);
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err(), "Task with id 999 was not found");
+    }
+
+    #[test]
+    fn test_tasklist_update_description_nonexistent() {
+        let mut list = TaskList::new();
+        let result = list.update_description(999, "New description".to_string());
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err(), "Task with id 999 was not found");
+    }
+
+    #[test]
+    fn test_tasklist_attach_url() {
+        let mut list = TaskList::new();
+        list.add(Task::new(1, "Task".to_string()));
+        list.attach(1, "https://example.com/spec".to_string())
+            .unwrap();
+        assert_eq!(list.tasks[0].attachments, vec!["https://example.com/spec"]);
+    }
+
+    #[test]
+    fn test_tasklist_attach_local_file() {
+        let mut list = TaskList::new();
+        list.add(Task::new(1, "Task".to_string()));
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml");
+        list.attach(1, path.to_string()).unwrap();
+        assert_eq!(list.tasks[0].attachments, vec![path]);
+    }
+
+    #[test]
+    fn test_tasklist_attach_missing_file() {
+        let mut list = TaskList::new();
+        list.add(Task::new(1, "Task".to_string()));
+        let result = list.attach(1, "./does-not-exist.pdf".to_string());
+        assert_eq!(
+            result.unwrap_err(),
+            "File ./does-not-exist.pdf does not exist"
+        );
+        assert!(list.tasks[0].attachments.is_empty());
+    }
+
+    #[test]
+    fn test_tasklist_contexts() {
+        let mut list = TaskList::new();
+        let mut call = Task::new(1, "Call".to_string());
+        call.context = Some("phone".to_string());
+        let mut sweep = Task::new(2, "Sweep".to_string());
+        sweep.context = Some("home".to_string());
+        let mut text = Task::new(3, "Text".to_string());
+        text.context = Some("phone".to_string());
+        list.add(call);
+        list.add(sweep);
+        list.add(text);
+        list.add(Task::new(4, "Think".to_string()));
+
+        let today = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        let ids: Vec<u32> = list
+            .listing(
+                &ListFilter {
+                    context: Some("phone".to_string()),
+                    ..Default::default()
+                },
+                today,
+            )
+            .iter()
+            .map(|task| task.id)
+            .collect();
+        assert_eq!(ids, vec![1, 3]);
+
+        let counts = list.context_counts();
+        assert_eq!(counts.len(), 2);
+        assert_eq!(counts["phone"], 2);
+        assert_eq!(counts["home"], 1);
+    }
+
+    #[test]
+    fn test_tasklist_listing_hides_scheduled() {
+        let mut list = TaskList::new();
+        list.add(Task::new(1, "Now".to_string()));
+        list.add(Task::new(2, "Later".to_string()));
+        list.add(Task::new(3, "Today".to_string()));
+        let today = NaiveDate::from_ymd_opt(2025, 3, 10).unwrap();
+        list.update_scheduled(2, NaiveDate::from_ymd_opt(2025, 3, 11))
+            .unwrap();
+        list.update_scheduled(3, Some(today)).unwrap();
+        list.update_due(3, NaiveDate::from_ymd_opt(2025, 3, 12))
+            .unwrap();
+
+        let ids: Vec<u32> = list
+            .listing(&ListFilter::default(), today)
+            .iter()
+            .map(|task| task.id)
+            .collect();
+        assert_eq!(ids, vec![1, 3]);
+        let all = ListFilter {
+            all: true,
+            ..Default::default()
+        };
+        assert_eq!(list.listing(&all, today).len(), 3);
+        assert_eq!(list.tasks[2].due, NaiveDate::from_ymd_opt(2025, 3, 12));
+    }
+
+    #[test]
+    fn test_tasklist_reminders() {
+        let mut list = TaskList::new();
+        list.add(Task::new(1, "Call".to_string()));
+        list.add(Task::new(2, "Pay".to_string()));
+        let day = NaiveDate::from_ymd_opt(2025, 3, 10).unwrap();
+        let nine = day.and_hms_opt(9, 0, 0).unwrap();
+        let noon = day.and_hms_opt(12, 0, 0).unwrap();
+        list.remind(1, noon).unwrap();
+        list.remind(2, nine).unwrap();
+        assert!(list.remind(3, nine).is_err());
+
+        let upcoming: Vec<u32> = list
+            .upcoming_reminders()
+            .iter()
+            .map(|(task, _)| task.id)
+            .collect();
+        assert_eq!(upcoming, vec![2, 1]);
+
+        let fired = list.fire_reminders(day.and_hms_opt(10, 0, 0).unwrap());
+        assert_eq!(fired, vec![(2, "Pay".to_string())]);
+        assert!(
+            list.fire_reminders(day.and_hms_opt(10, 0, 0).unwrap())
+                .is_empty()
+        );
+        assert_eq!(list.upcoming_reminders().len(), 1);
+    }
+
+    #[test]
+    fn test_tasklist_snooze() {
+        let mut list = TaskList::new();
+        list.add(Task::new(1, "Due".to_string()));
+        list.add(Task::new(2, "Undated".to_string()));
+        let today = NaiveDate::from_ymd_opt(2025, 3, 10).unwrap();
+        list.update_due(1, NaiveDate::from_ymd_opt(2025, 3, 12))
+            .unwrap();
+
+        list.snooze(1, TimeDelta::days(3), today).unwrap();
+        list.snooze(1, TimeDelta::weeks(1), today).unwrap();
+        list.snooze(2, TimeDelta::days(1), today).unwrap();
+
+        assert_eq!(list.tasks[0].due, NaiveDate::from_ymd_opt(2025, 3, 22));
+        assert_eq!(list.tasks[0].snoozes, 2);
+        assert_eq!(list.tasks[1].due, NaiveDate::from_ymd_opt(2025, 3, 11));
+        assert!(list.snooze(1, TimeDelta::hours(5), today).is_err());
+        assert!(list.snooze(1, TimeDelta::days(999_999_999), today).is_err());
+        assert_eq!(list.tasks[0].snoozes, 2);
+        assert!(list.tasks[0].to_string().contains("(snoozed 2x)"));
+    }
+
+    #[test]
+    fn test_tasklist_history() {
+        let mut list = TaskList::new();
+        list.add(Task::new(1, "Draft".to_string()));
+        list.update_status(1, TaskStatus::InProgress).unwrap();
+        list.update_description(1, "Final".to_string()).unwrap();
+        list.update_due(1, NaiveDate::from_ymd_opt(2025, 5, 1))
+            .unwrap();
+
+        let changes: Vec<_> = list
+            .task(1)
+            .unwrap()
+            .history
+            .iter()
+            .map(|event| {
+                (
+                    event.change.as_str(),
+                    event.from.as_deref(),
+                    event.to.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                ("created", None, Some("Draft")),
+                ("status", Some("Not Started"), Some("In Progress")),
+                ("description", Some("Draft"), Some("Final")),
+                ("due", None, Some("2025-05-01")),
+            ]
+        );
+        assert!(list.task(2).is_err());
+    }
+
+    #[test]
+    fn test_tasklist_waiting_on() {
+        let mut list = TaskList::new();
+        list.add(Task::new(1, "Review".to_string()));
+        list.add(Task::new(2, "Invoice".to_string()));
+        list.add(Task::new(3, "Quote".to_string()));
+        list.update_waiting_on(1, Some("alice".to_string()))
+            .unwrap();
+        list.update_waiting_on(2, Some("bob".to_string())).unwrap();
+        list.update_waiting_on(3, Some("alice".to_string()))
+            .unwrap();
+        assert!(matches!(list.tasks[0].status, TaskStatus::Waiting));
+
+        list.update_status(2, TaskStatus::Completed).unwrap();
+        let delegated = list.delegated();
+        assert_eq!(delegated.len(), 1);
+        let ids: Vec<u32> = delegated["alice"].iter().map(|task| task.id).collect();
+        assert_eq!(ids, vec![1, 3]);
+
+        list.update_waiting_on(1, None).unwrap();
+        assert!(matches!(list.tasks[0].status, TaskStatus::NotStarted));
+        assert!(list.tasks[0].waiting_on.is_none());
+    }
+
+    #[test]
+    fn test_tasklist_sprint_summaries() {
+        let mut list = TaskList::new();
+        for id in 1..=4 {
+            list.add(Task::new(id, format!("Story {}", id)));
+        }
+        list.update_points(1, Some(3)).unwrap();
+        list.update_points(2, Some(5)).unwrap();
+        list.update_points(3, Some(8)).unwrap();
+        list.update_sprint(1, Some("s1".to_string())).unwrap();
+        list.update_sprint(2, Some("s1".to_string())).unwrap();
+        list.update_sprint(3, Some("s2".to_string())).unwrap();
+        list.update_sprint(4, Some("s2".to_string())).unwrap();
+        list.update_status(2, TaskStatus::Completed).unwrap();
+
+        let summaries = list.sprint_summaries();
+        assert_eq!(
+            summaries["s1"],
+            SprintSummary {
+                tasks: 2,
+                committed: 8,
+                completed: 5
+            }
+        );
+        assert_eq!(
+            summaries["s2"].to_string(),
+            "0/8 points completed across 2 tasks"
+        );
+    }
+
+    #[test]
+    fn test_tasklist_milestones() {
+        let mut list = TaskList::new();
+        for id in 1..=3 {
+            list.add(Task::new(id, format!("Task {}", id)));
+        }
+        let beta = list.add_milestone("Beta".to_string());
+        let launch = list.add_milestone("Launch".to_string());
+        assert_eq!((beta, launch), (0, 1));
+
+        list.update_milestone(1, Some(beta)).unwrap();
+        list.update_milestone(2, Some(beta)).unwrap();
+        list.update_milestone(3, Some(launch)).unwrap();
+        list.update_status(1, TaskStatus::Completed).unwrap();
+        assert!(list.update_milestone(1, Some(9)).is_err());
+
+        let progress: Vec<_> = list
+            .milestone_progress()
+            .iter()
+            .map(|(milestone, completed, total)| (milestone.name.as_str(), *completed, *total))
+            .collect();
+        assert_eq!(progress, vec![("Beta", 1, 2), ("Launch", 0, 1)]);
+
+        list.remove_milestone(launch).unwrap();
+        assert!(list.tasks[2].milestone.is_none());
+        assert!(list.remove_milestone(launch).is_err());
+    }
+
+    #[test]
+    fn test_task_is_stale() {
+        let now = Local::now();
+        let mut task = Task::new(1, "Old".to_string());
+        assert!(task.is_stale(now, 14));
+
+        task.record("status", None, None);
+        assert!(!task.is_stale(now, 14));
+        assert!(task.is_stale(now + TimeDelta::days(15), 14));
+        assert!(!task.is_stale(now + TimeDelta::days(15), 30));
+
+        task.status = TaskStatus::Completed;
+        assert!(!task.is_stale(now + TimeDelta::days(15), 14));
+    }
+
+    #[test]
+    fn test_config_defaults() {
+        let config: Config = toml::from_str("").unwrap();
+        assert_eq!(config.stale_days, 14);
+        let config: Config = toml::from_str("stale_days = 30").unwrap();
+        assert_eq!(config.stale_days, 30);
+    }
+
+    #[test]
+    fn test_tasklist_progress() {
+        let mut list = TaskList::new();
+        list.add(Task::new(1, "Solo".to_string()));
+        assert_eq!(list.progress(&list.tasks[0]), None);
+        list.update_progress(1, Some(60)).unwrap();
+        assert_eq!(list.progress(&list.tasks[0]), Some(60));
+        assert!(list.update_progress(1, Some(101)).is_err());
+        assert_eq!(
+            list.line(&list.tasks[0]),
+            format!("{} [######----] 60%", list.tasks[0])
+        );
+    }
+
+    #[test]
+    fn test_tasklist_progress_rolls_up_subtasks() {
+        let mut list = TaskList::new();
+        for id in 1..=4 {
+            list.add(Task::new(id, format!("Task {}", id)));
+        }
+        list.update_parent(2, Some(1)).unwrap();
+        list.update_parent(3, Some(1)).unwrap();
+        list.update_parent(4, Some(3)).unwrap();
+        list.update_status(2, TaskStatus::Completed).unwrap();
+        list.update_progress(4, Some(50)).unwrap();
+        list.update_progress(1, Some(10)).unwrap();
+
+        assert_eq!(list.progress(&list.tasks[2]), Some(50));
+        assert_eq!(list.progress(&list.tasks[0]), Some(75));
+        assert!(list.update_parent(1, Some(4)).is_err());
+        assert!(list.update_parent(1, Some(1)).is_err());
+        assert!(list.update_parent(1, Some(9)).is_err());
+
+        list.tasks[0].parent = Some(4);
+        assert_eq!(list.break_cycles(), vec![1]);
+        assert_eq!(list.task(1).unwrap().parent, None);
+        assert_eq!(list.progress(&list.tasks[0]), Some(75));
+        assert!(list.break_cycles().is_empty());
+    }
+
+    #[test]
+    fn test_parse_optional() {
+        assert_eq!(parse_optional::<u32>("5").unwrap(), Some(5));
+        assert_eq!(parse_optional::<u32>("none").unwrap(), None);
+        assert!(parse_optional::<u32>("five").is_err());
+    }
+
+    #[test]
+    fn test_tasklist_recurrence() {
+        let date = |d| NaiveDate::from_ymd_opt(2025, 12, d).unwrap();
+        let mut list = TaskList::new();
+        list.add(Task::new(1, "Standup notes".to_string()));
+        assert!(list.update_recurrence(1, Some(Frequency::Weekly)).is_err());
+        list.update_due(1, Some(date(1))).unwrap();
+        list.update_recurrence(1, Some(Frequency::Weekly)).unwrap();
+
+        list.update_status(1, TaskStatus::Completed).unwrap();
+        assert!(matches!(list.tasks[0].status, TaskStatus::NotStarted));
+        assert_eq!(list.tasks[0].due, Some(date(8)));
+
+        list.skip(1).unwrap();
+        assert_eq!(list.tasks[0].due, Some(date(15)));
+
+        list.add_exception(1, date(22)).unwrap();
+        list.add_exception(1, date(29)).unwrap();
+        list.update_status(1, TaskStatus::Completed).unwrap();
+        assert_eq!(
+            list.tasks[0].due,
+            Some(NaiveDate::from_ymd_opt(2026, 1, 5).unwrap())
+        );
+
+        // A recurring task without a due date can't be completed, and stays as it was.
+        list.update_due(1, None).unwrap();
+        let history = list.tasks[0].history.len();
+        let err = list.update_status(1, TaskStatus::Completed).unwrap_err();
+        assert!(err.contains("no due date"));
+        assert_eq!(list.tasks[0].status, TaskStatus::NotStarted);
+        assert_eq!(list.tasks[0].history.len(), history);
+
+        list.update_recurrence(1, None).unwrap();
+        assert!(list.skip(1).is_err());
+        assert!(list.add_exception(1, date(1)).is_err());
+    }
+
+    #[test]
+    fn test_tasklist_relations() {
+        let mut list = TaskList::new();
+        for id in 1..=3 {
+            list.add(Task::new(id, format!("Task {}", id)));
+        }
+        list.link(3, RelationKind::Duplicates, 1).unwrap();
+        list.link(3, RelationKind::Duplicates, 1).unwrap();
+        list.link(3, RelationKind::RelatesTo, 2).unwrap();
+        assert_eq!(list.tasks[2].relations.len(), 2);
+        assert!(list.link(3, RelationKind::RelatesTo, 3).is_err());
+        assert!(list.link(3, RelationKind::RelatesTo, 9).is_err());
+
+        let json = serde_json::to_value(&list.tasks[2]).unwrap();
+        assert_eq!(json["relations"][0]["kind"], "Duplicates");
+        assert_eq!(json["relations"][0]["task"], 1);
+
+        list.unlink(3, 2).unwrap();
+        assert!(list.unlink(3, 2).is_err());
+        list.remove(1);
+        assert!(list.tasks[1].relations.is_empty());
+    }
+
+    #[test]
+    fn test_tasklist_next_candidates_by_energy() {
+        let today = NaiveDate::from_ymd_opt(2025, 3, 10).unwrap();
+        let mut list = TaskList::new();
+        for id in 1..=4 {
+            list.add(Task::new(id, format!("Task {}", id)));
+        }
+        list.update_energy(1, Some(Energy::High)).unwrap();
+        list.update_energy(2, Some(Energy::Low)).unwrap();
+        list.update_energy(3, Some(Energy::Low)).unwrap();
+        list.update_status(2, TaskStatus::Completed).unwrap();
+
+        let ids = |energy| -> Vec<u32> {
+            list.next_candidates(energy, today)
+                .iter()
+                .map(|task| task.id)
+                .collect()
+        };
+        assert_eq!(ids(Some(Energy::Low)), vec![3]);
+        assert_eq!(ids(Some(Energy::Medium)), Vec::<u32>::new());
+        assert_eq!(ids(None), vec![1, 3, 4]);
+    }
+
+    #[test]
+    fn test_tasklist_next_candidates_ranked() {
+        let today = NaiveDate::from_ymd_opt(2025, 3, 10).unwrap();
+        let mut list = TaskList::new();
+        for id in 1..=6 {
+            list.add(Task::new(id, format!("Task {}", id)));
+        }
+        list.update_due(1, NaiveDate::from_ymd_opt(2025, 3, 30))
+            .unwrap();
+        list.update_due(2, NaiveDate::from_ymd_opt(2025, 3, 8))
+            .unwrap();
+        list.update_due(3, NaiveDate::from_ymd_opt(2025, 3, 12))
+            .unwrap();
+        list.update_priority(4, Some(Priority::High)).unwrap();
+        list.update_status(5, TaskStatus::InProgress).unwrap();
+        list.update_due(6, NaiveDate::from_ymd_opt(2025, 3, 8))
+            .unwrap();
+        list.update_parent(1, Some(6)).unwrap();
+
+        let ids: Vec<u32> = list
+            .next_candidates(None, today)
+            .iter()
+            .map(|task| task.id)
+            .collect();
+        assert_eq!(ids, vec![2, 6, 3, 4, 5, 1]);
+        assert!(
+            list.next_score(list.task(6).unwrap(), today)
+                < list.next_score(list.task(2).unwrap(), today)
+        );
+    }
+
+    #[test]
+    fn test_tasklist_listing_by_location() {
+        let today = NaiveDate::from_ymd_opt(2025, 3, 10).unwrap();
+        let mut list = TaskList::new();
+        for id in 1..=3 {
+            list.add(Task::new(id, format!("Task {}", id)));
+        }
+        list.update_location(1, Some("Office".to_string())).unwrap();
+        list.update_location(2, Some("hardware store".to_string()))
+            .unwrap();
+        list.update_location(3, Some("office".to_string())).unwrap();
+        list.update_location(3, None).unwrap();
+
+        let filter = ListFilter {
+            location: Some("office".to_string()),
+            ..Default::default()
+        };
+        let ids: Vec<u32> = list
+            .listing(&filter, today)
+            .iter()
+            .map(|task| task.id)
+            .collect();
+        assert_eq!(ids, vec![1]);
+    }
+
+    #[test]
+    fn test_tasklist_color_and_icon() {
+        let mut list = TaskList::new();
+        list.add(Task::new(1, "Groceries".to_string()));
+        list.update_color(1, Some("green".to_string())).unwrap();
+        list.update_icon(1, Some("🛒".to_string())).unwrap();
+        assert!(
+            list.update_color(1, Some("chartreuse".to_string()))
+                .is_err()
+        );
+        assert_eq!(list.tasks[0].color.as_deref(), Some("green"));
+
+        let yaml = YamlFormatter::new().format(&list).unwrap();
+        let parsed: serde_yaml::Value = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(parsed["tasks"][0]["color"].as_str(), Some("green"));
+        assert_eq!(parsed["tasks"][0]["icon"].as_str(), Some("🛒"));
+
+        list.update_color(1, None).unwrap();
+        assert!(list.tasks[0].color.is_none());
+    }
+
+    #[test]
+    fn test_task_completion_metadata() {
+        let created = Local::now();
+        let mut task = Task::new(1, "Ship".to_string());
+        task.created_at = Some(created);
+        task.mark_completed(created + TimeDelta::hours(51), Some("sam".to_string()));
+        assert_eq!(task.cycle_time, Some(51 * 3600));
+        assert!(task.to_string().contains("(done in 2d 3h by sam)"));
+
+        task.clear_completion();
+        assert!(task.completed_at.is_none());
+        assert!(task.cycle_time.is_none());
+    }
+
+    #[test]
+    fn test_tasklist_completion_tracks_status() {
+        let mut list = TaskList::new();
+        list.add(Task::new(1, "Ship".to_string()));
+        assert!(list.tasks[0].created_at.is_some());
+
+        list.update_status(1, TaskStatus::Completed).unwrap();
+        let completed_at = list.tasks[0].completed_at;
+        assert!(completed_at.is_some());
+        assert!(list.tasks[0].cycle_time.is_some_and(|secs| secs >= 0));
+
+        list.update_status(1, TaskStatus::Completed).unwrap();
+        assert_eq!(list.tasks[0].completed_at, completed_at);
+
+        list.update_status(1, TaskStatus::InProgress).unwrap();
+        assert!(list.tasks[0].completed_at.is_none());
+    }
+
+    #[test]
+    fn test_tasklist_update_url() {
+        let mut list = TaskList::new();
+        list.add(Task::new(1, "Review PR".to_string()));
+        list.update_url(1, Some("https://example.com/pr/1".to_string()))
+            .unwrap();
+        assert_eq!(
+            list.tasks[0].url.as_deref(),
+            Some("https://example.com/pr/1")
+        );
+        assert!(list.update_url(1, Some("example.com".to_string())).is_err());
+        list.update_url(1, None).unwrap();
+        assert!(list.tasks[0].url.is_none());
+    }
+
+    #[test]
+    fn test_tasklist_listing_filters() {
+        let today = NaiveDate::from_ymd_opt(2025, 3, 10).unwrap();
+        let mut list = TaskList::new();
+        for id in 1..=4 {
+            list.add(Task::new(id, format!("Task {}", id)));
+        }
+        list.update_tags(1, vec!["work".to_string()]).unwrap();
+        list.update_tags(2, vec!["work".to_string(), "home".to_string()])
+            .unwrap();
+        list.update_project(2, Some("web".to_string())).unwrap();
+        list.update_project(3, Some("web".to_string())).unwrap();
+        list.update_status(1, TaskStatus::InProgress).unwrap();
+        list.update_due(2, NaiveDate::from_ymd_opt(2025, 3, 9))
+            .unwrap();
+        list.update_due(3, NaiveDate::from_ymd_opt(2025, 3, 20))
+            .unwrap();
+
+        let ids = |filter: ListFilter| -> Vec<u32> {
+            list.listing(&filter, today)
+                .iter()
+                .map(|task| task.id)
+                .collect()
+        };
+        let filter = |args: &[&str]| ListFilter::parse(args, today).unwrap();
+        assert_eq!(ids(filter(&["--tag", "work"])), vec![1, 2]);
+        assert_eq!(ids(filter(&["--project", "web"])), vec![2, 3]);
+        assert_eq!(ids(filter(&["--status", "ip"])), vec![1]);
+        assert_eq!(ids(filter(&["--due", "overdue"])), vec![2]);
+        assert_eq!(ids(filter(&["--due", "2025-03-31"])), vec![2, 3]);
+        assert_eq!(ids(filter(&["--tag", "work", "--project", "web"])), vec![2]);
+        assert!(
+            list.tasks[1]
+                .to_string()
+                .contains(" +work +home (project web)")
+        );
+    }
+
+    #[test]
+    fn test_tasklist_find_ranks_by_score() {
+        let mut list = TaskList::new();
+        list.add(Task::new(1, "Make invoices like last week".to_string()));
+        list.add(Task::new(2, "Buy milk".to_string()));
+        list.add(Task::new(3, "Call mom".to_string()));
+        let ids = |query| -> Vec<u32> { list.find(query).iter().map(|task| task.id).collect() };
+        assert_eq!(ids("bymlk"), vec![2]);
+        assert_eq!(ids("milk"), vec![2, 1]);
+        assert!(ids("xyz").is_empty());
+    }
+
+    #[test]
+    fn test_tasklist_sort_orders() {
+        let mut list = TaskList::new();
+        for id in 1..=4 {
+            list.add(Task::new(id, format!("Task {}", id)));
+        }
+        list.update_due(1, NaiveDate::from_ymd_opt(2025, 3, 20))
+            .unwrap();
+        list.update_due(3, NaiveDate::from_ymd_opt(2025, 3, 10))
+            .unwrap();
+        list.update_priority(2, Some(Priority::Low)).unwrap();
+        list.update_priority(4, Some(Priority::High)).unwrap();
+        list.update_status(3, TaskStatus::Completed).unwrap();
+        list.update_status(4, TaskStatus::InProgress).unwrap();
+
+        let ids =
+            |list: &TaskList| -> Vec<u32> { list.ordered().iter().map(|task| task.id).collect() };
+        let sort = |key, descending| Some(SortOrder { key, descending });
+        assert_eq!(ids(&list), vec![1, 2, 3, 4]);
+        list.set_sort(sort(SortKey::Due, false));
+        assert_eq!(ids(&list), vec![3, 1, 2, 4]);
+        list.set_sort(sort(SortKey::Due, true));
+        assert_eq!(ids(&list), vec![1, 3, 2, 4]);
+        list.set_sort(sort(SortKey::Priority, true));
+        assert_eq!(ids(&list), vec![4, 2, 1, 3]);
+        list.set_sort(sort(SortKey::Status, false));
+        assert_eq!(ids(&list), vec![1, 2, 4, 3]);
+        list.set_sort(sort(SortKey::Id, true));
+        assert_eq!(ids(&list), vec![4, 3, 2, 1]);
+    }
+
+    #[test]
+    fn test_formatters_respect_sort() {
+        let mut list = TaskList::new();
+        list.add(Task::new(1, "First".to_string()));
+        list.add(Task::new(2, "Second".to_string()));
+        list.set_sort(Some(SortOrder {
+            key: SortKey::Id,
+            descending: true,
+        }));
+        let plaintext = PlaintextFormatter::new().format(&list).unwrap();
+        assert!(plaintext.starts_with("2: Second"));
+        let json = JsonFormatter::new().format(&list).unwrap();
+        assert!(json.find("Second").unwrap() < json.find("First").unwrap());
+        assert_eq!(list.tasks[0].id, 1);
+    }
+
+    #[test]
+    fn test_command_sort() {
+        match Command::from_str("sort by priority").unwrap() {
+            Command::Sort { order: Some(order) } => {
+                assert_eq!(order.key, SortKey::Priority);
+                assert!(order.descending);
+            }
+            _ => panic!("Expected Sort command"),
+        }
+        match Command::from_str("sort by due asc").unwrap() {
+            Command::Sort { order: Some(order) } => assert!(!order.descending),
+            _ => panic!("Expected Sort command"),
+        }
+        assert!(matches!(
+            Command::from_str("sort none").unwrap(),
+            Command::Sort { order: None }
+        ));
+        assert!(Command::from_str("sort by size").is_err());
+        assert!(Command::from_str("sort by due sideways").is_err());
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let mut list = TaskList::new();
+        let mut history = UndoStack::default();
+        history.record(&list);
+        list.add(Task::new(1, "Task 1".to_string()));
+        history.record(&list);
+        list.update_description(1, "Renamed".to_string()).unwrap();
+
+        history.undo(&mut list).unwrap();
+        assert_eq!(list.tasks[0].description, "Task 1");
+        history.undo(&mut list).unwrap();
+        assert!(list.tasks.is_empty());
+        assert!(history.undo(&mut list).is_err());
+
+        history.redo(&mut list).unwrap();
+        history.redo(&mut list).unwrap();
+        assert_eq!(list.tasks[0].description, "Renamed");
+        assert!(history.redo(&mut list).is_err());
+    }
+
+    #[test]
+    fn test_undo_record_clears_redo() {
+        let mut list = TaskList::new();
+        let mut history = UndoStack::default();
+        history.record(&list);
+        list.add(Task::new(1, "Task 1".to_string()));
+        history.undo(&mut list).unwrap();
+        history.record(&list);
+        list.add(Task::new(2, "Task 2".to_string()));
+        assert!(history.redo(&mut list).is_err());
+        assert!(Command::from_str("remove 1").unwrap().mutates());
+        assert!(!Command::from_str("list").unwrap().mutates());
+    }
+
+    #[test]
+    fn test_session_undo_skips_failed_commands() {
+        let mut session = Session::new(
+            TaskList::new(),
+            Config::default(),
+            TemplateStore::default(),
+            "",
+        );
+        session.run_json("add Apples");
+        session.run_json("update 0 description Bananas");
+        assert_eq!(session.run_json("update 99 description Nope")["ok"], false);
+        session.run_json("undo");
+        assert_eq!(session.task_list.tasks[0].description, "Apples");
+        session.run_json("redo");
+        assert_eq!(session.task_list.tasks[0].description, "Bananas");
+    }
+
+    #[test]
+    fn test_tasklist_archive() {
+        let mut list = TaskList::new();
+        list.add(Task::new(1, "Task 1".to_string()));
+        list.add(Task::new(2, "Task 2".to_string()));
+        assert!(list.archive(1).is_err());
+        list.update_status(1, TaskStatus::Completed).unwrap();
+        list.archive(1).unwrap();
+        assert_eq!(list.tasks.len(), 1);
+        assert_eq!(list.archived[0].id, 1);
+        assert_eq!(list.archived[0].history.last().unwrap().change, "archived");
+        assert!(list.archive(1).is_err());
+    }
+
+    #[test]
+    fn test_command_done() {
+        assert!(matches!(
+            Command::from_str("d 3").unwrap(),
+            Command::Done {
+                id: 3,
+                archive: false
+            }
+        ));
+        assert!(matches!(
+            Command::from_str("done 3 --archive").unwrap(),
+            Command::Done {
+                id: 3,
+                archive: true
+            }
+        ));
+        assert!(Command::from_str("done").is_err());
+        assert!(Command::from_str("done 3 --later").is_err());
+    }
+
+    #[test]
+    fn test_tasklist_apply_edit() {
+        let mut list = TaskList::new();
+        list.add(Task::new(1, "Write report".to_string()));
+        let yaml = serde_yaml::to_string(&TaskEdit::from(list.task(1).unwrap())).unwrap();
+        let edited = yaml
+            .replace(
+                "description: Write report",
+                "description: Write the quarterly report",
+            )
+            .replace(
+                "notes: null",
+                "notes: |\n  Include **charts**.\n  Send to Sam.",
+            )
+            .replace("due: null", "due: 2025-06-03")
+            .replace("tags: []", "tags: [Work]");
+        list.apply_edit(1, serde_yaml::from_str(&edited).unwrap())
+            .unwrap();
+
+        let task = list.task(1).unwrap();
+        assert_eq!(task.description, "Write the quarterly report");
+        assert_eq!(
+            task.notes.as_deref(),
+            Some("Include **charts**.\nSend to Sam.\n")
+        );
+        assert_eq!(task.due, NaiveDate::from_ymd_opt(2025, 6, 3));
+        assert_eq!(task.tags, vec!["work"]);
+        let changes: Vec<&str> = task
+            .history
+            .iter()
+            .map(|event| event.change.as_str())
+            .collect();
+        assert_eq!(
+            changes,
+            vec!["created", "description", "notes", "tags", "due"]
+        );
+    }
+
+    #[test]
+    fn test_tasklist_apply_edit_rejects_empty_description() {
+        let mut list = TaskList::new();
+        list.add(Task::new(1, "Write report".to_string()));
+        let mut edit = TaskEdit::from(list.task(1).unwrap());
+        edit.description = " ".to_string();
+        assert!(list.apply_edit(1, edit).is_err());
+        assert!(Command::from_str("edit").is_err());
+        assert!(matches!(
+            Command::from_str("edit 1").unwrap(),
+            Command::Edit { id: 1 }
+        ));
+    }
+
+    #[test]
+    fn test_help_covers_every_command() {
+        for spec in help::COMMANDS {
+            for name in spec.names {
+                if let Err(err) = Command::from_str(name) {
+                    assert_ne!(
+                        err.to_string(),
+                        "Invalid argument.",
+                        "{} is not a command",
+                        name
+                    );
+                }
+            }
+        }
+        assert!(matches!(
+            Command::from_str("help Update").unwrap(),
+            Command::Help { topic: Some(topic) } if topic == "update"
+        ));
+    }
+
+    #[test]
+    fn test_command_quoting() {
+        match Command::from_str(r#"add "Read  'Dune'" @home '+1 ideas'"#).unwrap() {
+            Command::Add { val, context, tags } => {
+                assert_eq!(val, "Read  'Dune' +1 ideas");
+                assert_eq!(context.as_deref(), Some("home"));
+                assert!(tags.is_empty());
+            }
+            _ => panic!("Expected Add command"),
+        }
+        match Command::from_str(r#"u 1 d "Call \"Bob\"""#).unwrap() {
+            Command::Update { new_val, .. } => assert_eq!(new_val, r#"Call "Bob""#),
+            _ => panic!("Expected Update command"),
+        }
+        assert!(Command::from_str("add \"Buy milk").is_err());
+        assert!(Command::from_str("").is_err());
+    }
+
+    #[test]
+    fn test_tasklist_move_task() {
+        let mut list = TaskList::new();
+        for id in 1..=4 {
+            list.add(Task::new(id, format!("Task {}", id)));
+        }
+        let ids =
+            |list: &TaskList| -> Vec<u32> { list.ordered().iter().map(|task| task.id).collect() };
+        list.move_task(4, MoveTarget::To(1)).unwrap();
+        assert_eq!(ids(&list), vec![4, 1, 2, 3]);
+        list.move_task(1, MoveTarget::Down).unwrap();
+        assert_eq!(ids(&list), vec![4, 2, 1, 3]);
+        list.move_task(4, MoveTarget::Up).unwrap();
+        assert_eq!(ids(&list), vec![4, 2, 1, 3]);
+        list.move_task(2, MoveTarget::Bottom).unwrap();
+        assert_eq!(ids(&list), vec![4, 1, 3, 2]);
+        list.move_task(3, MoveTarget::To(99)).unwrap();
+        assert_eq!(ids(&list), vec![4, 1, 2, 3]);
+        assert_eq!(list.task(3).unwrap().position, Some(4));
+        assert!(list.move_task(9, MoveTarget::Top).is_err());
+
+        list.add(Task::new(5, "Task 5".to_string()));
+        list.move_task(5, MoveTarget::Up).unwrap();
+        assert_eq!(ids(&list), vec![4, 1, 2, 5, 3]);
+    }
+
+    #[test]
+    fn test_command_move() {
+        assert!(matches!(
+            Command::from_str("move 5 up").unwrap(),
+            Command::Move {
+                id: 5,
+                target: MoveTarget::Up
+            }
+        ));
+        assert!(matches!(
+            Command::from_str("mv 5 to 1").unwrap(),
+            Command::Move {
+                id: 5,
+                target: MoveTarget::To(1)
+            }
+        ));
+        assert!(Command::from_str("move 5").is_err());
+        assert!(Command::from_str("move 5 sideways").is_err());
+    }
+
+    #[test]
+    fn test_tasklist_duplicate() {
+        let mut list = TaskList::new();
+        let mut task = Task::new(1, "Plan trip".to_string());
+        task.tags = vec!["travel".to_string()];
+        list.add(task);
+        list.add(Task::new(2, "Book hotel".to_string()));
+        list.add(Task::new(3, "Pack".to_string()));
+        list.add(Task::new(4, "Check in online".to_string()));
+        list.update_parent(2, Some(1)).unwrap();
+        list.update_parent(3, Some(1)).unwrap();
+        list.update_parent(4, Some(2)).unwrap();
+        list.update_status(1, TaskStatus::InProgress).unwrap();
+
+        let single = list.duplicate(1, 10, false, false).unwrap();
+        assert_eq!(single.len(), 1);
+        assert!(matches!(single[0].status, TaskStatus::NotStarted));
+        assert!(single[0].tags.is_empty());
+        assert!(single[0].history.is_empty());
+
+        let tree = list.duplicate(1, 10, true, true).unwrap();
+        let shape: Vec<(u32, &str, Option<u32>)> = tree
+            .iter()
+            .map(|task| (task.id, task.description.as_str(), task.parent))
+            .collect();
+        assert_eq!(
+            shape,
+            vec![
+                (10, "Plan trip", None),
+                (11, "Book hotel", Some(10)),
+                (12, "Check in online", Some(11)),
+                (13, "Pack", Some(10)),
+            ]
+        );
+        assert_eq!(tree[0].tags, vec!["travel"]);
+        assert!(list.duplicate(9, 10, false, true).is_err());
+    }
+
+    #[test]
+    fn test_command_duplicate() {
+        assert!(matches!(
+            Command::from_str("dup 3 --subtasks --no-tags").unwrap(),
+            Command::Duplicate {
+                id: 3,
+                with_subtasks: true,
+                keep_tags: false
+            }
+        ));
+        assert!(Command::from_str("dup").is_err());
+        assert!(Command::from_str("dup 3 --all").is_err());
+    }
+
+    #[test]
+    fn test_tasklist_clear_completed() {
+        let now = Local::now();
+        let mut list = TaskList::new();
+        for id in 1..=4 {
+            list.add(Task::new(id, format!("Task {}", id)));
+        }
+        list.link(4, RelationKind::RelatesTo, 1).unwrap();
+        for id in 1..=3 {
+            list.update_status(id, TaskStatus::Completed).unwrap();
+        }
+        list.tasks[0].completed_at = Some(now - TimeDelta::days(40));
+        list.tasks[2].completed_at = None;
+
+        assert_eq!(
+            list.clear_completed(now, Some(TimeDelta::days(30)), false)
+                .unwrap(),
+            1
+        );
+        assert!(list.task(1).is_err());
+        assert!(list.task(4).unwrap().relations.is_empty());
+
+        assert_eq!(list.clear_completed(now, None, true).unwrap(), 2);
+        assert_eq!(list.tasks.len(), 1);
+        assert_eq!(list.archived.len(), 2);
+    }
+
+    #[test]
+    fn test_command_clear() {
+        match Command::from_str("clear --older-than 30d --archive").unwrap() {
+            Command::Clear {
+                older_than,
+                archive,
+                force,
+            } => {
+                assert_eq!(older_than, Some(TimeDelta::days(30)));
+                assert!(archive);
+                assert!(!force);
+            }
+            _ => panic!("Expected Clear command"),
+        }
+        assert!(Command::from_str("clear --older-than").is_err());
+        assert!(Command::from_str("clear all").is_err());
+    }
+
+    #[test]
+    fn test_task_card() {
+        let mut list = TaskList::new();
+        list.add(Task::new(1, "Write **report**".to_string()));
+        list.add(Task::new(2, "Collect numbers".to_string()));
+        list.update_parent(2, Some(1)).unwrap();
+        list.update_tags(1, vec!["work".to_string()]).unwrap();
+        list.update_due(1, NaiveDate::from_ymd_opt(2025, 6, 3))
+            .unwrap();
+        list.update_notes(1, Some("First line\nSecond line".to_string()))
+            .unwrap();
+
+        let card = task_card(&list, list.task(1).unwrap(), false);
+        let lines: Vec<&str> = card.lines().collect();
+        assert_eq!(lines[0], "       Task: 1");
+        assert_eq!(lines[1], "Description: Write **report**");
+        assert!(lines.contains(&"     Status: Not Started"));
+        assert!(lines.contains(&"       Tags: +work"));
+        assert!(lines.contains(&"        Due: 2025-06-03"));
+        assert!(lines.contains(&"   Subtasks: 2"));
+        assert!(lines.contains(&"  Second line"));
+        assert!(!card.contains("Project"));
+        assert!(card.contains("History:\n"));
+        assert!(task_card(&list, list.task(1).unwrap(), true).contains("\x1b[1mreport"));
+        assert!(matches!(
+            Command::from_str("show 1").unwrap(),
+            Command::Show { id: 1 }
+        ));
+    }
+
+    #[test]
+    fn test_config_aliases() {
+        let config: Config =
+            toml::from_str("[aliases]\nt = \"update $1 status in-progress\"\n").unwrap();
+        let line = shell::expand_alias("t 4", &config.aliases).unwrap();
+        assert!(matches!(
+            Command::from_str(&line).unwrap(),
+            Command::Update {
+                id: 4,
+                field: TaskField::Status,
+                ..
+            }
+        ));
+        assert!(config.confirm);
+    }
+
+    fn script(name: &str, lines: &str) -> String {
+        let path = env::temp_dir().join(format!("todore-{}-{}.txt", name, process::id()));
+        fs::write(&path, lines).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn test_session_run_script() {
+        let path = script(
+            "setup",
+            "# weekly setup\nadd \"Water plants\" +home\n\nadd Review PRs\nremove 0\nupdate 1 status ip\n",
+        );
+        let mut session = Session::new(
+            TaskList::new(),
+            Config::default(),
+            TemplateStore::default(),
+            "",
+        );
+        session.run_script(&path, false).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(session.task_list.tasks.len(), 1);
+        assert_eq!(session.task_list.tasks[0].description, "Review PRs");
+        assert!(matches!(
+            session.task_list.tasks[0].status,
+            TaskStatus::InProgress
+        ));
+    }
+
+    #[test]
+    fn test_man_page_and_long_help() {
+        let page = help::man_page(Cli::command_with_help()).unwrap();
+        for section in [
+            "NAME",
+            "SYNOPSIS",
+            "OPTIONS",
+            "COMMANDS",
+            "ENVIRONMENT",
+            "FILES",
+        ] {
+            assert!(page.contains(&format!(".SH {}", section)), "{}", section);
+        }
+        assert!(page.contains("\\fBmerge\\fR"));
+        assert!(page.contains("TODORE_FORMAT"));
+        assert!(Cli::try_parse_from(["todore", "--man", "list"]).is_err());
+
+        let mut cmd = Cli::command_with_help();
+        let help = cmd.render_long_help().to_string();
+        assert!(help.contains("[u | update] <TODO-item-id> <field> <new-value>"));
+        assert!(help.contains("TODORE_PROJECT"));
+    }
+
+    #[test]
+    fn test_cli_one_shot_command() {
+        let cli = Cli::try_parse_from(["todore", "--no-color", "list", "--tag", "work"]).unwrap();
+        assert!(cli.no_color);
+        assert_eq!(cli.command, vec!["list", "--tag", "work"]);
+        let cli =
+            Cli::try_parse_from(["todore", "-f", "work.json", "add", "Buy milk", "-f"]).unwrap();
+        assert_eq!(cli.file, Some(PathBuf::from("work.json")));
+        assert_eq!(cli.command, vec!["add", "Buy milk", "-f"]);
+        assert!(Cli::try_parse_from(["todore", "--script", "s.txt", "list"]).is_err());
+        assert!(
+            Cli::try_parse_from(["todore", "--ephemeral"])
+                .unwrap()
+                .ephemeral
+        );
+
+        let mut session = Session::new(
+            TaskList::new(),
+            Config::default(),
+            TemplateStore::default(),
+            "",
+        );
+        session
+            .run_lines(
+                "stdin",
+                "add buy milk
+add eggs
+",
+                false,
+            )
+            .unwrap();
+        assert_eq!(session.task_list.tasks.len(), 2);
+    }
+
+    #[test]
+    fn test_session_run_json() {
+        let mut list = TaskList::new();
+        list.add(Task::new(0, "Buy milk".to_string()));
+        list.add(Task::new(1, "Call mom".to_string()));
+        let mut session = Session::new(list, Config::default(), TemplateStore::default(), "");
+
+        let result = session.run_json("update 1 status ip");
+        assert_eq!(result["ok"], true);
+        assert_eq!(result["affected"], serde_json::json!([1]));
+        assert_eq!(result["task"]["status"], "InProgress");
+
+        let result = session.run_json("add Eggs; remove 0 -f");
+        assert_eq!(result["affected"], serde_json::json!([0, 2]));
+        assert_eq!(result["task"], serde_json::Value::Null);
+
+        let result = session.run_json("find mom");
+        assert_eq!(result["affected"], serde_json::json!([]));
+        assert_eq!(result["output"][0], "1: Call mom\tIn Progress");
+
+        let result = session.run_json("update 9 status ip");
+        assert_eq!(result["ok"], false);
+        assert_eq!(result["error"], "Task with id 9 was not found");
+        assert!(Cli::try_parse_from(["todore", "--json"]).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_session_hooks() {
+        let log = env::temp_dir().join(format!("todore-hooks-{}.log", process::id()));
+        let mut config: Config = toml::from_str(&format!(
+            "[hooks]\non-add = 'cat >> {0}; echo >> {0}'\non-done = 'echo $TODORE_HOOK >> {0}'\n",
+            log.display()
+        ))
+        .unwrap();
+        config
+            .hooks
+            .insert("on-export".to_string(), "exit 1".to_string());
+        let mut session = Session::new(TaskList::new(), config, TemplateStore::default(), "");
+        assert!(
+            session
+                .run_input("add Buy milk; done 0")
+                .unwrap()
+                .is_continue()
+        );
+
+        let lines = fs::read_to_string(&log).unwrap();
+        fs::remove_file(&log).unwrap();
+        let lines: Vec<&str> = lines.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let added: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(added["description"], "Buy milk");
+        assert_eq!(lines[1], "on-done");
+
+        session.dry_run = true;
+        let result = session.run_json("add Eggs; export json out.json");
+        assert_eq!(result["output"][1], "Would run the on-add hook");
+        assert_eq!(result["output"][3], "Would run the on-export hook");
+    }
+
+    #[test]
+    fn test_export_exec() {
+        assert!(matches!(
+            Command::from_str("export exec 'jq -r .tasks[].description' list.txt --tag work").unwrap(),
+            Command::ExportExec { command, out_file, filter: Some(_) }
+                if command == "jq -r .tasks[].description" && out_file == "list.txt"
+        ));
+        assert!(Command::from_str("export exec jq").is_err());
+
+        let mut session = Session::new(
+            TaskList::new(),
+            Config::default(),
+            TemplateStore::default(),
+            "",
+        );
+        session.dry_run = true;
+        let marker = env::temp_dir().join(format!("todore-exec-{}", process::id()));
+        let result = session.run_json(&format!("export exec 'touch {}' out.txt", marker.display()));
+        assert_eq!(
+            result["output"][0],
+            format!("Would run touch {} and write out.txt", marker.display())
+        );
+        assert!(!marker.exists());
+
+        let mut list = TaskList::new();
+        list.add(Task::new(0, "Buy milk".to_string()));
+        let upper = ExecFormatter {
+            command: "tr a-z A-Z".to_string(),
+        };
+        assert!(
+            upper
+                .format(&list)
+                .unwrap()
+                .contains("\"DESCRIPTION\": \"BUY MILK\"")
+        );
+        let failing = ExecFormatter {
+            command: "exit 3".to_string(),
+        };
+        assert!(failing.format(&list).is_err());
+    }
+
+    #[test]
+    fn test_session_autosave() {
+        let file = env::temp_dir().join(format!("todore-autosave-{}.json", process::id()));
+        let config: Config = toml::from_str("autosave_delay_ms = 60000").unwrap();
+        let mut session = Session::new(TaskList::new(), config, TemplateStore::default(), "");
+        session.storage = Some(Box::new(FileStorage::new(&file)));
+        let saved = || {
+            let mut list = TaskList::new();
+            list.import(&fs::read_to_string(&file).unwrap()).unwrap();
+            list.tasks.len()
+        };
+
+        assert!(session.run_line("list").unwrap().is_continue());
+        assert!(!file.exists());
+        assert!(session.run_line("add Buy milk").unwrap().is_continue());
+        assert_eq!(saved(), 1);
+        // Within the delay the change waits for the next save.
+        assert!(session.run_line("add Eggs").unwrap().is_continue());
+        assert_eq!(saved(), 1);
+        session.autosave(true).unwrap();
+        assert_eq!(saved(), 2);
+        assert!(session.run_line("undo").unwrap().is_continue());
+        session.autosave(true).unwrap();
+        assert_eq!(saved(), 1);
+        fs::remove_file(&file).unwrap();
+    }
+
+    #[test]
+    fn test_session_dry_run() {
+        let mut list = TaskList::new();
+        list.add(Task::new(0, "Buy milk".to_string()));
+        list.add(Task::new(1, "Call mom".to_string()));
+        let mut session = Session::new(list, Config::default(), TemplateStore::default(), "");
+        session.dry_run = true;
+
+        let out = env::temp_dir().join(format!("todore-dry-run-{}.json", process::id()));
+        let result = session.run_json(&format!(
+            "add Eggs; update 1 priority high; remove 0 -f; export json {}; export md --clipboard",
+            out.display()
+        ));
+        assert_eq!(result["output"][0], "+ 2 Eggs");
+        assert_eq!(result["output"][1], "~ 1 priority: none -> High");
+        assert_eq!(result["output"][2], "- 0 Buy milk");
+        let written = result["output"][3].as_str().unwrap();
+        assert!(written.starts_with(&format!("Would write {} (", out.display())));
+        assert!(!out.exists());
+        let copied = result["output"][4].as_str().unwrap();
+        assert!(copied.starts_with("Would copy "));
+        assert_eq!(session.task_list.tasks.len(), 2);
     }
 
     #[test]
-    fn test_task_new() {
-        let task = Task::new(42, "Test task".to_string());
-        assert_eq!(task.id, 42);
-        assert_eq!(task.description, "Test task");
-        assert!(matches!(task.status, TaskStatus::NotStarted));
+    fn test_session_filtered_export() {
+        let mut list = TaskList::new();
+        for (id, description) in ["Buy milk", "Ship release", "Write report"]
+            .iter()
+            .enumerate()
+        {
+            list.add(Task::new(id as u32, description.to_string()));
+        }
+        list.update_tags(1, vec!["work".to_string()]).unwrap();
+        list.update_tags(2, vec!["work".to_string()]).unwrap();
+        list.update_status(1, TaskStatus::Completed).unwrap();
+        list.update_due(1, NaiveDate::from_ymd_opt(2024, 12, 1))
+            .unwrap();
+        list.update_due(2, NaiveDate::from_ymd_opt(2025, 2, 1))
+            .unwrap();
+        let mut session = Session::new(list, Config::default(), TemplateStore::default(), "");
+
+        let out = env::temp_dir().join(format!("todore-filtered-{}.json", process::id()));
+        let result = session.run_json(&format!(
+            "export json {} --tag work --due-before 2025-01-01",
+            out.display()
+        ));
+        assert_eq!(result["ok"], true);
+        let exported: TaskList = serde_json::from_str(&fs::read_to_string(&out).unwrap()).unwrap();
+        fs::remove_file(&out).unwrap();
+        assert_eq!(exported.tasks.len(), 1);
+        assert_eq!(exported.tasks[0].description, "Ship release");
+        assert!(Command::from_str("export json out.json --status completed --frobnicate").is_err());
     }
 
     #[test]
-    fn test_task_serialization() {
-        let task = Task::new(1, "Test task".to_string());
-        let json = serde_json::to_string(&task).unwrap();
-        let deserialized: Task = serde_json::from_str(&json).unwrap();
-        assert_eq!(deserialized.id, task.id);
-        assert_eq!(deserialized.description, task.description);
-        assert!(matches!(deserialized.status, TaskStatus::NotStarted));
+    fn test_session_run_script_errors() {
+        let path = script(
+            "errors",
+            "add First\nupdate 9 status ip\nadd Second\nquit\nadd Third\n",
+        );
+        let mut session = Session::new(
+            TaskList::new(),
+            Config::default(),
+            TemplateStore::default(),
+            "",
+        );
+        let err = session.run_script(&path, false).unwrap_err();
+        assert!(
+            err.to_string()
+                .ends_with(":2: Task with id 9 was not found")
+        );
+        assert_eq!(session.task_list.tasks.len(), 1);
+
+        let mut session = Session::new(
+            TaskList::new(),
+            Config::default(),
+            TemplateStore::default(),
+            "",
+        );
+        session.run_script(&path, true).unwrap();
+        fs::remove_file(&path).unwrap();
+        let descriptions: Vec<&str> = session
+            .task_list
+            .tasks
+            .iter()
+            .map(|task| task.description.as_str())
+            .collect();
+        assert_eq!(descriptions, vec!["First", "Second"]);
     }
 
-    // TaskStatus enum tests
     #[test]
-    fn test_taskstatus_fromstr_valid() {
-        assert!(matches!(
-            TaskStatus::from_str("not started"),
-            Ok(TaskStatus::NotStarted)
-        ));
+    fn test_tasklist_counts() {
+        let today = NaiveDate::from_ymd_opt(2025, 3, 10).unwrap();
+        let mut list = TaskList::new();
+        for id in 1..=4 {
+            list.add(Task::new(id, format!("Task {}", id)));
+        }
+        list.update_tags(1, vec!["work".to_string()]).unwrap();
+        list.update_tags(2, vec!["work".to_string(), "home".to_string()])
+            .unwrap();
+        list.update_due(1, NaiveDate::from_ymd_opt(2025, 3, 9))
+            .unwrap();
+        list.update_due(2, NaiveDate::from_ymd_opt(2025, 3, 1))
+            .unwrap();
+        list.update_due(3, Some(today)).unwrap();
+        list.update_status(2, TaskStatus::Completed).unwrap();
+        list.update_status(3, TaskStatus::InProgress).unwrap();
+
+        let counts = list.counts(today);
+        assert_eq!(counts.total, 4);
+        assert_eq!(counts.overdue, 1);
+        assert_eq!(
+            counts.to_string(),
+            "4 tasks, 1 overdue\nNot Started: 2  In Progress: 1  Waiting: 0  Completed: 1\n+home: 1  +work: 2"
+        );
         assert!(matches!(
-            TaskStatus::from_str("ns"),
-            Ok(TaskStatus::NotStarted)
+            Command::from_str("count").unwrap(),
+            Command::Count
         ));
+    }
+
+    #[test]
+    fn test_tasklist_start_pause_tracks_time() {
+        let at = |minute| {
+            NaiveDate::from_ymd_opt(2025, 3, 10)
+                .unwrap()
+                .and_hms_opt(9, minute, 0)
+                .unwrap()
+                .and_local_timezone(Local)
+                .unwrap()
+        };
+        let mut list = TaskList::new();
+        list.add(Task::new(1, "Write report".to_string()));
+        assert!(list.pause(1, at(0)).is_err());
+        list.start(1, true, at(0)).unwrap();
+        assert_eq!(list.task(1).unwrap().status, TaskStatus::InProgress);
+        list.pause(1, at(25)).unwrap();
+        let task = list.task(1).unwrap();
+        assert_eq!(task.status, TaskStatus::NotStarted);
+        assert_eq!(task.time_spent, 25 * 60);
+        assert!(task.started_at.is_none());
+
+        list.start(1, false, at(30)).unwrap();
+        assert!(list.task(1).unwrap().started_at.is_none());
+        list.pause(1, at(40)).unwrap();
+        assert_eq!(list.task(1).unwrap().time_spent, 25 * 60);
+    }
+
+    #[test]
+    fn test_tasklist_check_wip() {
+        let mut list = TaskList::new();
+        for id in 1..=3 {
+            list.add(Task::new(id, format!("Task {}", id)));
+        }
+        list.start(1, false, Local::now()).unwrap();
+        list.start(2, false, Local::now()).unwrap();
+        assert!(list.check_wip(3, None).is_ok());
+        assert!(list.check_wip(3, Some(3)).is_ok());
+        assert_eq!(
+            list.check_wip(3, Some(2)).unwrap_err(),
+            "WIP limit of 2 tasks in progress reached"
+        );
+        assert!(list.check_wip(1, Some(2)).is_ok());
         assert!(matches!(
-            TaskStatus::from_str("in progress"),
-            Ok(TaskStatus::InProgress)
+            Command::from_str("start 3 --track").unwrap(),
+            Command::Start { id: 3, track: true }
         ));
+        assert!(Command::from_str("pause").is_err());
+    }
+
+    #[test]
+    fn test_tasklist_merge() {
+        let mut list = TaskList::new();
+        for id in 1..=4 {
+            list.add(Task::new(id, format!("Task {}", id)));
+        }
+        let earlier = Local::now() - TimeDelta::days(3);
+        list.tasks[0].created_at = Some(earlier);
+        list.update_tags(1, vec!["home".to_string(), "urgent".to_string()])
+            .unwrap();
+        list.update_tags(2, vec!["urgent".to_string()]).unwrap();
+        list.update_notes(1, Some("from one".to_string())).unwrap();
+        list.update_parent(3, Some(1)).unwrap();
+        list.link(4, RelationKind::RelatesTo, 1).unwrap();
+
+        list.merge(1, 2).unwrap();
+        assert!(list.task(1).is_err());
+        let task = list.task(2).unwrap();
+        assert_eq!(task.description, "Task 2; Task 1");
+        assert_eq!(task.notes.as_deref(), Some("from one"));
+        assert_eq!(task.tags, vec!["urgent", "home"]);
+        assert_eq!(task.created_at, Some(earlier));
+        assert_eq!(task.history.last().unwrap().change, "merged");
+        assert_eq!(list.task(3).unwrap().parent, Some(2));
+        assert!(list.task(4).unwrap().relations.is_empty());
+
+        assert!(list.merge(2, 2).is_err());
+        assert!(list.merge(9, 2).is_err());
+        assert!(list.merge(2, 3).is_err());
+        assert_eq!(list.task(3).unwrap().parent, Some(2));
         assert!(matches!(
-            TaskStatus::from_str("ip"),
-            Ok(TaskStatus::InProgress)
+            Command::from_str("merge 1 2").unwrap(),
+            Command::Merge {
+                source: 1,
+                target: 2
+            }
         ));
+    }
+
+    #[test]
+    fn test_tasklist_split() {
+        let mut list = TaskList::new();
+        let mut task = Task::new(1, "Plan trip".to_string());
+        task.tags = vec!["travel".to_string()];
+        list.add(task);
+        list.add(Task::new(2, "Pack".to_string()));
+        list.update_parent(2, Some(1)).unwrap();
+
+        let parts = vec!["Book flights".to_string(), "Book hotel".to_string()];
+        assert_eq!(list.split(1, parts.clone(), 3, true).unwrap(), vec![3, 4]);
+        assert_eq!(
+            list.task(1).unwrap().history.last().unwrap().change,
+            "split"
+        );
+        assert_eq!(list.task(4).unwrap().parent, Some(1));
+        assert_eq!(list.task(4).unwrap().tags, vec!["travel"]);
+
+        assert_eq!(list.split(1, parts, 5, false).unwrap(), vec![5, 6]);
+        assert!(list.task(1).is_err());
+        assert_eq!(list.task(2).unwrap().parent, Some(5));
+        assert_eq!(list.task(3).unwrap().parent, Some(5));
+        assert_eq!(list.task(6).unwrap().description, "Book hotel");
+        assert!(list.split(5, vec![], 7, false).is_err());
+    }
+
+    #[test]
+    fn test_command_split() {
+        let Command::Split {
+            id,
+            parts,
+            as_subtasks,
+        } = Command::from_str("split 3 'Write draft' Review --subtasks").unwrap()
+        else {
+            panic!("expected split");
+        };
+        assert_eq!((id, as_subtasks), (3, true));
+        assert_eq!(parts, vec!["Write draft", "Review"]);
+        assert!(Command::from_str("split").is_err());
+    }
+
+    #[test]
+    fn test_tasklist_extend_description() {
+        let mut list = TaskList::new();
+        list.add(Task::new(1, "Buy milk".to_string()));
+        list.extend_description(1, "and eggs", false).unwrap();
+        list.extend_description(1, "Tomorrow:", true).unwrap();
+        let task = list.task(1).unwrap();
+        assert_eq!(task.description, "Tomorrow: Buy milk and eggs");
+        assert_eq!(task.history.last().unwrap().change, "description");
+        assert!(list.extend_description(2, "x", false).is_err());
         assert!(matches!(
-            TaskStatus::from_str("completed"),
-            Ok(TaskStatus::Completed)
+            Command::from_str("PREPEND 1 'Call  first'").unwrap(),
+            Command::Extend { prepend: true, text, .. } if text == "Call  first"
         ));
+        assert!(Command::from_str("append 1").is_err());
+    }
+
+    #[test]
+    fn test_tasklist_adopt_remaps_ids() {
+        let mut source = TaskList::new();
+        for id in 0..3 {
+            source.add(Task::new(id, format!("Task {}", id)));
+        }
+        source.update_parent(2, Some(1)).unwrap();
+        source.link(2, RelationKind::RelatesTo, 1).unwrap();
+        source.link(1, RelationKind::RelatesTo, 0).unwrap();
+        let moved = source.subtree(1).unwrap();
+        assert_eq!(
+            moved.iter().map(|task| task.id).collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+
+        let mut target = TaskList::new();
+        target.add(Task::new(0, "Existing".to_string()));
+        target.add(Task::new(1, "Existing too".to_string()));
+        assert_eq!(target.adopt(moved), vec![(1, 2), (2, 3)]);
+        let child = target.task(3).unwrap();
+        assert_eq!(child.parent, Some(2));
+        assert_eq!(child.relations[0].task, 2);
+        assert!(target.task(2).unwrap().relations.is_empty());
+        assert_eq!(child.history.last().unwrap().from.as_deref(), Some("2"));
+    }
+
+    #[test]
+    fn test_session_transfer_to_project_file() {
+        let dir = env::temp_dir().join(format!("todore-transfer-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut list = TaskList::new();
+        list.add(Task::new(0, "Buy milk".to_string()));
+        list.add(Task::new(1, "Ship release".to_string()));
+        let mut session = Session::new(list, Config::default(), TemplateStore::default(), "");
+        session.projects_dir = dir.clone();
+
+        let result = session.run_json("move-to 1 work");
+        assert_eq!(result["ok"], true, "{}", result);
+        let work = FileStorage::new(dir.join("work.json")).load().unwrap();
+        assert_eq!(work.tasks[0].description, "Ship release");
+        assert_eq!(session.task_list.tasks.len(), 1);
+
+        // A todore that has the other list open keeps it from being written.
+        let _lock = FileLock::acquire(&dir.join("work.json"), false).unwrap();
+        assert_eq!(session.run_json("move-to 0 work")["ok"], false);
+        assert_eq!(session.task_list.tasks.len(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_command_transfer() {
         assert!(matches!(
-            TaskStatus::from_str("c"),
-            Ok(TaskStatus::Completed)
+            Command::from_str("copy-to 3 work").unwrap(),
+            Command::Transfer { id: 3, keep: true, project } if project == "work"
         ));
+        assert!(Command::from_str("move-to 3 work").unwrap().mutates());
+        assert!(!Command::from_str("copy-to 3 work").unwrap().mutates());
+        let dir = Path::new("/data/todore");
+        assert_eq!(project_file("work", dir), dir.join("work.json"));
+        assert_eq!(project_file("../home.json", dir), dir.join("../home.json"));
     }
 
     #[test]
-    fn test_taskstatus_fromstr_invalid() {
-        assert!(TaskStatus::from_str("invalid").is_err());
-        assert!(TaskStatus::from_str("").is_err());
-        assert!(TaskStatus::from_str("notstarted").is_err());
-        assert!(TaskStatus::from_str("done").is_err());
+    fn test_task_style() {
+        let today = NaiveDate::from_ymd_opt(2025, 3, 10).unwrap();
+        let mut task = Task::new(1, "Pay rent".to_string());
+        assert_eq!(task_style(&task, today), None);
+        task.status = TaskStatus::InProgress;
+        assert_eq!(task_style(&task, today), Some(33));
+        task.due = NaiveDate::from_ymd_opt(2025, 3, 9);
+        assert_eq!(task_style(&task, today), Some(31));
+        task.status = TaskStatus::Completed;
+        assert_eq!(task_style(&task, today), Some(2));
+        task.color = Some("blue".to_string());
+        assert_eq!(task_style(&task, today), Some(34));
+
+        let list = TaskList::new();
+        assert_eq!(display_task(&list, &task, false), list.line(&task));
+        assert!(display_task(&list, &task, true).starts_with("\x1b[34m"));
     }
 
     #[test]
-    fn test_taskstatus_display() {
-        assert_eq!(format!("{}", TaskStatus::NotStarted), "Not Started");
-        assert_eq!(format!("{}", TaskStatus::InProgress), "In Progress");
-        assert_eq!(format!("{}", TaskStatus::Completed), "Completed");
+    fn test_tasklist_aliases() {
+        let mut list = TaskList::new();
+        list.add(Task::new(12, "Do taxes".to_string()));
+        list.add(Task::new(13, "File receipts".to_string()));
+        list.update_alias(12, Some("Taxes".to_string())).unwrap();
+        assert_eq!(list.resolve("taxes"), Some(12));
+        assert!(list.update_alias(13, Some("taxes".to_string())).is_err());
+        assert!(list.update_alias(13, Some("42".to_string())).is_err());
+        assert!(list.update_alias(12, Some("taxes".to_string())).is_ok());
+        assert_eq!(list.task(12).unwrap().fresh_copy(14).alias, None);
+
+        assert_eq!(list.resolve_aliases("done taxes").unwrap(), "done 12");
+        assert_eq!(
+            list.resolve_aliases("merge 13 TAXES").unwrap(),
+            "merge 13 12"
+        );
+        assert_eq!(
+            list.resolve_aliases("u 13 parent taxes").unwrap(),
+            "u 13 parent 12"
+        );
+        assert_eq!(
+            list.resolve_aliases("add 'pay taxes' taxes").unwrap(),
+            "add 'pay taxes' taxes"
+        );
+        list.update_alias(12, None).unwrap();
+        assert_eq!(list.resolve("taxes"), None);
     }
 
     #[test]
-    fn test_taskstatus_serialization() {
-        let statuses = vec![
-            TaskStatus::NotStarted,
-            TaskStatus::InProgress,
-            TaskStatus::Completed,
+    fn test_session_run_input_chains_and_repeats() {
+        let mut session = Session::new(
+            TaskList::new(),
+            Config::default(),
+            TemplateStore::default(),
+            "templates.json",
+        );
+        assert!(session.run_input("!!").is_err());
+        assert!(
+            session
+                .run_input("add 'x; y'; add z")
+                .unwrap()
+                .is_continue()
+        );
+        assert!(session.run_input("!!").unwrap().is_continue());
+        let descriptions: Vec<&str> = session
+            .task_list
+            .tasks
+            .iter()
+            .map(|task| task.description.as_str())
+            .collect();
+        assert_eq!(descriptions, vec!["x; y", "z", "x; y", "z"]);
+        assert!(session.run_input("add w; quit; add v").unwrap().is_break());
+        assert_eq!(session.task_list.tasks.len(), 5);
+    }
+
+    #[test]
+    fn test_tasklist_export_import() {
+        let mut list = TaskList::new();
+        list.add(Task::new(1, "Task 1".to_string()));
+        list.add(Task::new(2, "Task 2".to_string()));
+
+        // Export to JSON
+        let json_formatter = JsonFormatter::new();
+        let json_str = list.export_to_string(&json_formatter).unwrap();
+
+        // Import into new list
+        let mut new_list = TaskList::new();
+        new_list.import(&json_str).unwrap();
+
+        // Verify import worked
+        assert_eq!(new_list.tasks.len(), 2);
+        assert_eq!(new_list.tasks[0].id, 1);
+        assert_eq!(new_list.tasks[0].description, "Task 1");
+        assert_eq!(new_list.tasks[1].id, 2);
+        assert_eq!(new_list.tasks[1].description, "Task 2");
+    }
+
+    #[test]
+    fn test_toml_keeps_every_field() {
+        let mut list = TaskList::new();
+        assert!(
+            Format::TOML
+                .parse(&TomlFormatter.format(&list).unwrap())
+                .is_ok()
+        );
+        list.add(Task::new(0, "Plan trip".to_string()));
+        list.add(Task::new(1, "Book flights".to_string()));
+        list.update_parent(1, Some(0)).unwrap();
+        list.update_priority(1, Some(Priority::High)).unwrap();
+        list.tasks[1].due = NaiveDate::from_ymd_opt(2025, 3, 10);
+        list.update_status(0, TaskStatus::Completed).unwrap();
+        list.archive(0).unwrap();
+
+        let text = TomlFormatter.format(&list).unwrap();
+        assert!(text.contains("[[tasks]]"));
+        assert_eq!(Format::of_path(Path::new("tasks.toml")), Format::TOML);
+        let parsed = Format::TOML.parse(&text).unwrap();
+        assert_eq!(
+            serde_json::to_value(&parsed).unwrap(),
+            serde_json::to_value(list.sorted()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_markdown_checklist() {
+        let mut list = TaskList::new();
+        list.add(Task::new(0, "Buy milk".to_string()));
+        list.add(Task::new(1, "Call mom".to_string()));
+        list.update_status(1, TaskStatus::Completed).unwrap();
+        let text = MarkdownFormatter.format(&list).unwrap();
+        assert_eq!(
+            text,
+            "## Not Started\n\n- [ ] Buy milk\n\n## Completed\n\n- [x] Call mom"
+        );
+
+        list.tasks[0].project = Some("home".to_string());
+        list.add(Task::new(2, "Write report".to_string()));
+        list.update_status(2, TaskStatus::InProgress).unwrap();
+        let text = MarkdownFormatter.format(&list).unwrap();
+        assert!(text.starts_with("## No project\n\n### In Progress\n\n- [ ] Write report"));
+        let parsed = Format("markdown").parse(&text).unwrap();
+        let summary: Vec<(&str, Option<&str>, TaskStatus)> = parsed
+            .tasks
+            .iter()
+            .map(|task| {
+                let project = task.project.as_deref();
+                (task.description.as_str(), project, task.status.clone())
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("Write report", None, TaskStatus::InProgress),
+                ("Call mom", None, TaskStatus::Completed),
+                ("Buy milk", Some("home"), TaskStatus::NotStarted),
+            ]
+        );
+
+        let readme = "# Chores\n\nSome notes.\n\n* [X] Sweep\n  - [ ] Mop\n- plain bullet\n";
+        let parsed = parse_markdown(readme);
+        assert_eq!(parsed.tasks.len(), 2);
+        assert_eq!(parsed.tasks[0].status, TaskStatus::Completed);
+        assert_eq!(parsed.tasks[1].project.as_deref(), Some("Chores"));
+        assert_eq!(Format::of_path(Path::new("TODO.md")), Format("markdown"));
+    }
+
+    #[test]
+    fn test_html_formatter() {
+        let mut list = TaskList::new();
+        list.add(Task::new(0, "Fix <b> & \"quotes\"".to_string()));
+        list.add(Task::new(1, "Ship it".to_string()));
+        list.tasks[1].due = NaiveDate::from_ymd_opt(2025, 3, 1);
+        list.update_status(0, TaskStatus::InProgress).unwrap();
+        let html = HtmlFormatter {
+            today: NaiveDate::from_ymd_opt(2025, 3, 2).unwrap(),
+        }
+        .format(&list)
+        .unwrap();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("Fix &lt;b&gt; &amp; &quot;quotes&quot;"));
+        assert!(html.contains("<span class=\"badge in-progress\">In Progress</span>"));
+        assert!(html.contains("<tr class=\"overdue\"><td>1</td>"));
+        assert!(Format("html").parse(&html).is_err());
+    }
+
+    #[test]
+    fn test_ics_formatter() {
+        let mut list = TaskList::new();
+        list.add(Task::new(0, "No due date".to_string()));
+        list.add(Task::new(1, "Water plants; both rooms".to_string()));
+        list.add(Task::new(2, "Pay rent".to_string()));
+        list.tasks[1].due = NaiveDate::from_ymd_opt(2025, 3, 1);
+        list.tasks[1].recurrence = Some(Recurrence {
+            every: Frequency::Weekly,
+            exceptions: vec![NaiveDate::from_ymd_opt(2025, 3, 8).unwrap()],
+        });
+        list.tasks[2].due = NaiveDate::from_ymd_opt(2025, 3, 31);
+        list.tasks[2].notes = Some("x".repeat(100));
+        list.update_status(2, TaskStatus::Completed).unwrap();
+        let stamp = DateTime::from_timestamp(1_740_000_000, 0).unwrap();
+        let ics = IcsFormatter { stamp }.format(&list).unwrap();
+        let lines: Vec<&str> = ics.split("\r\n").collect();
+        assert_eq!(lines[0], "BEGIN:VCALENDAR");
+        assert!(!ics.contains("No due date"));
+        assert_eq!(ics.matches("BEGIN:VEVENT").count(), 1);
+        assert_eq!(ics.matches("BEGIN:VTODO").count(), 2);
+        for line in [
+            "DTSTART;VALUE=DATE:20250301",
+            "DTEND;VALUE=DATE:20250302",
+            "SUMMARY:Water plants\\; both rooms",
+            "RRULE:FREQ=WEEKLY",
+            "EXDATE;VALUE=DATE:20250308",
+            "DUE;VALUE=DATE:20250331",
+            "STATUS:COMPLETED",
+            "DTSTAMP:20250219T212000Z",
+        ] {
+            assert!(lines.contains(&line), "{}", line);
+        }
+        assert!(lines.iter().all(|line| line.len() <= 75));
+        assert!(lines.iter().any(|line| line.starts_with(" xxx")));
+    }
+
+    #[test]
+    fn test_org_round_trip() {
+        let mut list = TaskList::new();
+        list.add(Task::new(0, "Plan trip".to_string()));
+        list.add(Task::new(1, "Book flights".to_string()));
+        list.add(Task::new(2, "Renew passport".to_string()));
+        list.update_parent(1, Some(0)).unwrap();
+        list.update_status(0, TaskStatus::InProgress).unwrap();
+        list.update_priority(1, Some(Priority::High)).unwrap();
+        list.update_tags(1, vec!["travel".to_string(), "to-book".to_string()])
+            .unwrap();
+        list.tasks[1].scheduled = NaiveDate::from_ymd_opt(2025, 3, 1);
+        list.tasks[1].due = NaiveDate::from_ymd_opt(2025, 3, 5);
+        list.tasks[1].notes = Some("Window seat\n\nCheck baggage".to_string());
+        let org = OrgFormatter.format(&list).unwrap();
+        assert_eq!(
+            org,
+            "* STARTED Plan trip\n\
+             ** TODO [#A] Book flights :travel:to_book:\n   \
+             SCHEDULED: <2025-03-01 Sat> DEADLINE: <2025-03-05 Wed>\n   \
+             Window seat\n\n   Check baggage\n\
+             * TODO Renew passport\n"
+        );
+
+        let parsed = Format("org").parse(&org).unwrap();
+        assert_eq!(parsed.tasks.len(), 3);
+        let flights = &parsed.tasks[1];
+        assert_eq!(flights.parent, Some(0));
+        assert_eq!(flights.priority, Some(Priority::High));
+        assert_eq!(flights.tags, vec!["travel", "to_book"]);
+        assert_eq!(flights.due, NaiveDate::from_ymd_opt(2025, 3, 5));
+        assert_eq!(flights.scheduled, NaiveDate::from_ymd_opt(2025, 3, 1));
+        assert_eq!(
+            flights.notes.as_deref(),
+            Some("Window seat\n\nCheck baggage")
+        );
+        assert_eq!(parsed.tasks[0].status, TaskStatus::InProgress);
+        assert_eq!(parsed.tasks[2].parent, None);
+
+        let parsed = parse_org("#+TITLE: Inbox\n* Notes\n** DONE Sweep\n*bold* text\n");
+        assert_eq!(parsed.tasks.len(), 1);
+        assert_eq!(parsed.tasks[0].status, TaskStatus::Completed);
+        assert_eq!(parsed.tasks[0].parent, None);
+    }
+
+    #[test]
+    fn test_table_formatter() {
+        let mut list = TaskList::new();
+        list.add(Task::new(1, "Buy milk".to_string()));
+        list.add(Task::new(12, "Write the quarterly report".to_string()));
+        list.update_tags(12, vec!["work".to_string()]).unwrap();
+        list.tasks[1].due = NaiveDate::from_ymd_opt(2025, 3, 5);
+        let table = TableFormatter {
+            borders: false,
+            width: 60,
+        }
+        .format(&list)
+        .unwrap();
+        assert_eq!(
+            table,
+            "ID  Status       Due         Tags  Description\n\
+             1   Not Started                    Buy milk\n\
+             12  Not Started  2025-03-05  work  Write the quarterly repo…"
+        );
+
+        let boxed = TableFormatter {
+            borders: true,
+            width: 200,
+        }
+        .format(&list)
+        .unwrap();
+        let lines: Vec<&str> = boxed.lines().collect();
+        assert_eq!(lines.len(), 6);
+        assert!(lines[0].starts_with("┌────┬"));
+        assert_eq!(
+            lines[4],
+            "│ 12 │ Not Started │ 2025-03-05 │ work │ Write the quarterly report │"
+        );
+        assert!(
+            lines
+                .iter()
+                .all(|line| line.chars().count() == lines[4].chars().count())
+        );
+        assert_eq!(Format::from_str("box"), Ok(Format("box")));
+    }
+
+    #[test]
+    fn test_format_parse_round_trips() {
+        let mut list = TaskList::new();
+        list.add(Task::new(1, "Buy milk".to_string()));
+        list.add(Task::new(2, "Call mom".to_string()));
+        list.update_status(2, TaskStatus::InProgress).unwrap();
+        list.update_tags(2, vec!["family".to_string()]).unwrap();
+        for format in [
+            Format::JSON,
+            Format::YAML,
+            Format::TOML,
+            Format("plaintext"),
+        ] {
+            let text = format.formatter().format(&list).unwrap();
+            let parsed = format.parse(&text).unwrap();
+            assert_eq!(parsed.tasks.len(), 2);
+            assert_eq!(parsed.tasks[1].id, 2);
+            assert_eq!(parsed.tasks[1].description, "Call mom");
+            assert_eq!(parsed.tasks[1].status, TaskStatus::InProgress);
+        }
+        assert!(parse_plaintext("Buy milk").is_err());
+        let checklist =
+            parse_plaintext("4: Call mom\tIn Progress\n- [ ] Buy milk\n* [x] Pay rent\n").unwrap();
+        assert_eq!(checklist.tasks.len(), 3);
+        assert_eq!(checklist.tasks[1].id, 5);
+        assert_eq!(checklist.tasks[1].description, "Buy milk");
+        assert_eq!(checklist.tasks[2].status, TaskStatus::Completed);
+        let mixed = parse_plaintext("- [ ] a\n0: b\tNot Started\n").unwrap();
+        let ids: Vec<u32> = mixed.tasks.iter().map(|task| task.id).collect();
+        assert_eq!(ids, vec![0, 1]);
+        assert_eq!(mixed.tasks[1].description, "a");
+        assert!(Format("csv").parse("id\n").is_err());
+    }
+
+    #[test]
+    fn test_format_registry() {
+        let registry = Registry::new();
+        for name in registry.formatters.keys() {
+            let format = Format::from_str(name).unwrap();
+            let json = serde_json::to_string(&format).unwrap();
+            assert_eq!(json, format!("\"{}\"", name));
+            assert_eq!(serde_json::from_str::<Format>(&json).unwrap(), format);
+        }
+        for (alias, name) in &registry.aliases {
+            assert_eq!(Format::from_str(alias), Ok(Format(name)));
+        }
+        assert_eq!(Format::of_path(Path::new("tasks.yml")), Format::YAML);
+        assert_eq!(Format::of_path(Path::new("tasks.unknown")), Format::JSON);
+        assert!(
+            registry
+                .usage(false)
+                .starts_with("[j | json] | [jsonl | ndjson]")
+        );
+        assert!(registry.usage(false).ends_with("| table | box"));
+        assert!(!registry.usage(true).contains("csv"));
+        assert!(registry.list().ends_with("table or box"));
+        assert!(serde_json::from_str::<Format>("\"pdf\"").is_err());
+    }
+
+    #[test]
+    fn test_command_import() {
+        assert!(matches!(
+            Command::from_str("import yaml tasks.yaml").unwrap(),
+            Command::Import {
+                format: Format::YAML,
+                mode: ImportMode::Add,
+                ..
+            }
+        ));
+        assert!(matches!(
+            Command::from_str("import j tasks.json --replace").unwrap(),
+            Command::Import {
+                mode: ImportMode::Replace,
+                ..
+            }
+        ));
+        assert!(matches!(
+            Command::from_str("import j tasks.json --merge").unwrap(),
+            Command::Import {
+                mode: ImportMode::Merge,
+                ..
+            }
+        ));
+        assert!(Command::from_str("import json tasks.json --wipe").is_err());
+    }
+
+    #[test]
+    fn test_merge_import() {
+        let now = Local::now();
+        let task = |id, description: &str, created_mins: i64, touched_mins: i64| {
+            let mut task = Task::new(id, description.to_string());
+            task.created_at = Some(now - chrono::Duration::minutes(created_mins));
+            task.touched = Some(now - chrono::Duration::minutes(touched_mins));
+            task
+        };
+        let mut list = TaskList::new();
+        list.tasks = vec![
+            task(0, "Buy milk", 60, 30),
+            task(1, "Call mom", 60, 5),
+            task(2, "Pay rent", 60, 60),
         ];
-        for status in statuses {
-            let json = serde_json::to_string(&status).unwrap();
-            let deserialized: TaskStatus = serde_json::from_str(&json).unwrap();
-            assert!(matches!(
-                (status, deserialized),
-                (TaskStatus::NotStarted, TaskStatus::NotStarted)
-                    | (TaskStatus::InProgress, TaskStatus::InProgress)
-                    | (TaskStatus::Completed, TaskStatus::Completed)
-            ));
+
+        let (added, updated, skipped) = list.merge_import(vec![
+            // Changed later elsewhere, so it wins.
+            task(0, "Buy oat milk", 60, 10),
+            // Older than ours.
+            task(1, "Call dad", 60, 20),
+            // Same id but created at another time: a different task.
+            task(2, "Water plants", 30, 30),
+            task(7, "Book flights", 10, 10),
+        ]);
+        assert_eq!((added, updated, skipped), (2, 1, 1));
+        let descriptions: Vec<(u32, &str)> = list
+            .tasks
+            .iter()
+            .map(|task| (task.id, task.description.as_str()))
+            .collect();
+        assert_eq!(
+            descriptions,
+            vec![
+                (0, "Buy oat milk"),
+                (1, "Call mom"),
+                (2, "Pay rent"),
+                (7, "Book flights"),
+                (8, "Water plants"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_review_action_fromstr() {
+        assert_eq!(ReviewAction::from_str("d"), Ok(ReviewAction::Done));
+        assert_eq!(
+            ReviewAction::from_str("s"),
+            Ok(ReviewAction::Snooze(TimeDelta::days(1)))
+        );
+        assert_eq!(
+            ReviewAction::from_str("snooze 1w"),
+            Ok(ReviewAction::Snooze(TimeDelta::weeks(1)))
+        );
+        assert_eq!(ReviewAction::from_str(""), Ok(ReviewAction::Skip));
+        assert_eq!(ReviewAction::from_str("delete"), Ok(ReviewAction::Remove));
+        assert!(ReviewAction::from_str("x").is_err());
+        assert!(ReviewAction::from_str("s soon").is_err());
+
+        let mut session = Session::new(
+            TaskList::new(),
+            Config::default(),
+            TemplateStore::default(),
+            "templates.json",
+        );
+        assert!(session.run_line("review").is_err());
+    }
+
+    #[test]
+    fn test_tasklist_roulette() {
+        let today = NaiveDate::from_ymd_opt(2025, 3, 10).unwrap();
+        let mut list = TaskList::new();
+        assert!(list.roulette(false, today, 7).is_none());
+        for id in 0..4 {
+            list.add(Task::new(id, format!("Task {}", id)));
         }
+        list.update_status(0, TaskStatus::Completed).unwrap();
+        list.update_parent(2, Some(1)).unwrap();
+        list.update_priority(3, Some(Priority::High)).unwrap();
+        let picks: Vec<u32> = (0..6)
+            .map(|roll| list.roulette(false, today, roll).unwrap().id)
+            .collect();
+        assert_eq!(picks, vec![2, 3, 2, 3, 2, 3]);
+        let weighted: Vec<u32> = (0..6)
+            .map(|roll| list.roulette(true, today, roll).unwrap().id)
+            .collect();
+        assert_eq!(weighted, vec![2, 2, 3, 3, 3, 3]);
     }
 
-    // TaskList struct tests
     #[test]
-    fn test_tasklist_new() {
-        let list = TaskList::new();
-        assert_eq!(list.tasks.len(), 0);
+    fn test_tasklist_log_time() {
+        let mut list = TaskList::new();
+        list.add(Task::new(1, "Write report".to_string()));
+        list.log_time(1, TimeDelta::minutes(25)).unwrap();
+        list.log_time(1, TimeDelta::minutes(10)).unwrap();
+        let task = list.task(1).unwrap();
+        assert_eq!(task.time_spent, 35 * 60);
+        assert_eq!(task.history.last().unwrap().to.as_deref(), Some("10m"));
+        assert!(list.log_time(2, TimeDelta::minutes(1)).is_err());
+        assert!(matches!(
+            Command::from_str("pomodoro 1").unwrap(),
+            Command::Pomodoro { id: 1, length } if length == TimeDelta::minutes(25)
+        ));
+        assert!(matches!(
+            Command::from_str("pomodoro 1 50M").unwrap(),
+            Command::Pomodoro { length, .. } if length == TimeDelta::minutes(50)
+        ));
     }
 
     #[test]
-    fn test_tasklist_add() {
+    fn test_csv_formatter() {
         let mut list = TaskList::new();
-        let task = Task::new(1, "Test task".to_string());
+        list.add(Task::new(1, "Buy milk, eggs".to_string()));
+        let mut task = Task::new(2, "Say \"hi\"".to_string());
+        task.tags = vec!["home".to_string(), "family".to_string()];
+        task.priority = Some(Priority::High);
         list.add(task);
-        assert_eq!(list.tasks.len(), 1);
-        assert_eq!(list.tasks[0].id, 1);
-        assert_eq!(list.tasks[0].description, "Test task");
+        assert_eq!(
+            CsvFormatter.format(&list).unwrap(),
+            "id,description,status,due,priority,project,context,tags\n\
+             1,\"Buy milk, eggs\",Not Started,,,,,\n\
+             2,\"Say \"\"hi\"\"\",Not Started,,high,,,home family\n"
+        );
     }
 
     #[test]
-    fn test_tasklist_update_status_nonexistent() {
+    fn test_session_print_formatted() {
         let mut list = TaskList::new();
-        let result = list.update_status(999, TaskStatus::Completed);
-        assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), "Task with id 999 was not found");
+        for id in 0..3 {
+            list.add(Task::new(id, format!("Task {}", id)));
+        }
+        list.move_task(2, MoveTarget::Top).unwrap();
+        let subset = list.subset(&[list.task(1).unwrap(), list.task(0).unwrap()]);
+        let ids: Vec<u32> = subset.ordered().iter().map(|task| task.id).collect();
+        assert_eq!(ids, vec![1, 0]);
+
+        let mut session = Session::new(list, Config::default(), TemplateStore::default(), "");
+        assert!(!session.print_formatted(&[]).unwrap());
+        session.format = Some(Format::JSON);
+        assert!(session.print_formatted(&[]).unwrap());
+        let cli = Cli::try_parse_from(["todore", "--format", "CSV", "list"]).unwrap();
+        assert_eq!(cli.format, Some(Format("csv")));
     }
 
     #[test]
-    fn test_tasklist_update_description_nonexistent() {
+    fn test_tasklist_import_invalid_json() {
         let mut list = TaskList::new();
-        let result = list.update_description(999, "New description".to_string());
+        let result = list.import("invalid json");
         assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), "Task with id 999 was not found");
     }
 
     #[test]
-    fn test_tasklist_export_import() {
+    fn test_template_save_and_instantiate() {
         let mut list = TaskList::new();
-        list.add(Task::new(1, "Task 1".to_string()));
-        list.add(Task::new(2, "Task 2".to_string()));
+        list.add(Task::new(1, "Tag release".to_string()));
+        list.add(Task::new(2, "Publish crate".to_string()));
+        list.update_status(2, TaskStatus::Completed).unwrap();
 
-        // Export to JSON
-        let json_formatter = JsonFormatter::new();
-        let json_str = list
-            .export_to_string::<JsonFormatter>(&json_formatter)
+        let mut store = TemplateStore::default();
+        store
+            .save_from("release".to_string(), &list, &[1, 2])
             .unwrap();
 
-        // Import into new list
-        let mut new_list = TaskList::new();
-        new_list.import(&json_str).unwrap();
-
-        // Verify import worked
-        assert_eq!(new_list.tasks.len(), 2);
-        assert_eq!(new_list.tasks[0].id, 1);
-        assert_eq!(new_list.tasks[0].description, "Task 1");
-        assert_eq!(new_list.tasks[1].id, 2);
-        assert_eq!(new_list.tasks[1].description, "Task 2");
+        let tasks = store.instantiate("release", 10).unwrap();
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[0].id, 10);
+        assert_eq!(tasks[0].description, "Tag release");
+        assert_eq!(tasks[1].id, 11);
+        assert!(matches!(tasks[1].status, TaskStatus::NotStarted));
     }
 
     #[test]
-    fn test_tasklist_import_invalid_json() {
+    fn test_template_save_replaces_and_remove() {
         let mut list = TaskList::new();
-        let result = list.import("invalid json");
-        assert!(result.is_err());
+        list.add(Task::new(1, "One".to_string()));
+        list.add(Task::new(2, "Two".to_string()));
+
+        let mut store = TemplateStore::default();
+        store.save_from("t".to_string(), &list, &[1]).unwrap();
+        store.save_from("t".to_string(), &list, &[1, 2]).unwrap();
+        assert_eq!(store.templates.len(), 1);
+        assert_eq!(store.templates[0].tasks.len(), 2);
+
+        assert!(store.save_from("u".to_string(), &list, &[9]).is_err());
+        store.remove("t").unwrap();
+        assert!(store.remove("t").is_err());
+        assert!(store.instantiate("t", 0).is_err());
     }
 
     // Formatter implementation tests
@@ -516,6 +8408,49 @@ mod tests {
         assert!(lines[1].contains("2: Task 2"));
     }
 
+    #[test]
+    fn test_pretty_plaintext_formatter() {
+        let mut list = TaskList::new();
+        list.add(Task::new(1, "Buy milk".to_string()));
+        list.add(Task::new(2, "File taxes".to_string()));
+        list.add(Task::new(3, "Call mom".to_string()));
+        list.update_priority(1, Some(Priority::Medium)).unwrap();
+        list.update_tags(1, vec!["errand".to_string()]).unwrap();
+        list.update_due(1, NaiveDate::from_ymd_opt(2025, 3, 1))
+            .unwrap();
+        list.update_status(2, TaskStatus::InProgress).unwrap();
+        list.update_due(2, NaiveDate::from_ymd_opt(2025, 4, 15))
+            .unwrap();
+        list.update_status(3, TaskStatus::Completed).unwrap();
+
+        let mut formatter = PlaintextFormatter::new();
+        formatter.today = NaiveDate::from_ymd_opt(2025, 3, 10).unwrap();
+        let mut config = Config {
+            plaintext_format: Some(
+                "{id} [{status:short}] {description} (due {due}) {{{tags}}}".into(),
+            ),
+            ..Config::default()
+        };
+        formatter.configure(&config);
+        let result = formatter.format(&list).unwrap();
+        let lines: Vec<&str> = result.lines().collect();
+        assert_eq!(lines[0], "1 [ns] Buy milk (due 2025-03-01) {errand}");
+        assert_eq!(lines[2], "3 [c] Call mom (due ) {}");
+        config.plaintext_format = Some("{id} {owner}".into());
+        formatter.configure(&config);
+        assert!(formatter.format(&list).is_err());
+
+        formatter.configure(&Config {
+            pretty_plaintext: true,
+            ..Config::default()
+        });
+        let result = formatter.format(&list).unwrap();
+        let lines: Vec<&str> = result.lines().collect();
+        assert_eq!(lines[0], "☐ 1 Buy milk ⚑⚑ +errand ⏰ 2025-03-01");
+        assert_eq!(lines[1], "◐ 2 File taxes (due 2025-04-15)");
+        assert_eq!(lines[2], "☑ 3 Call mom");
+    }
+
     #[test]
     fn test_json_formatter() {
         let mut list = TaskList::new();
@@ -552,15 +8487,106 @@ mod tests {
     fn test_command_add() {
         let cmd = Command::from_str("add Buy groceries").unwrap();
         match cmd {
-            Command::Add { val } => assert_eq!(val, "Buy"),
+            Command::Add { val, .. } => assert_eq!(val, "Buy groceries"),
             _ => panic!("Expected Add command"),
         }
 
         let cmd_short = Command::from_str("a Buy groceries").unwrap();
         match cmd_short {
-            Command::Add { val } => assert_eq!(val, "Buy"),
+            Command::Add { val, .. } => assert_eq!(val, "Buy groceries"),
+            _ => panic!("Expected Add command"),
+        }
+    }
+
+    #[test]
+    fn test_command_add_context() {
+        let cmd = Command::from_str("add Call @phone +Family +urgent").unwrap();
+        match cmd {
+            Command::Add { val, context, tags } => {
+                assert_eq!(val, "Call");
+                assert_eq!(context.as_deref(), Some("phone"));
+                assert_eq!(tags, vec!["family", "urgent"]);
+            }
             _ => panic!("Expected Add command"),
         }
+
+        assert!(Command::from_str("add @phone").is_err());
+    }
+
+    #[test]
+    fn test_command_list() {
+        match Command::from_str("list").unwrap() {
+            Command::List { filter } => {
+                assert!(filter.context.is_none());
+                assert!(!filter.all);
+            }
+            _ => panic!("Expected List command"),
+        }
+        match Command::from_str("l @home --at office --all").unwrap() {
+            Command::List { filter } => {
+                assert_eq!(filter.context.as_deref(), Some("home"));
+                assert_eq!(filter.location.as_deref(), Some("office"));
+                assert!(filter.all);
+            }
+            _ => panic!("Expected List command"),
+        }
+        assert!(Command::from_str("list home").is_err());
+        assert!(Command::from_str("list --at").is_err());
+        assert!(Command::from_str("list --status someday").is_err());
+    }
+
+    #[test]
+    fn test_list_filter_parse() {
+        let today = NaiveDate::from_ymd_opt(2025, 3, 10).unwrap();
+        let filter = ListFilter::parse(
+            &[
+                "--status",
+                "ip",
+                "--tag",
+                "+Work",
+                "--project",
+                "web",
+                "--due",
+                "2025-03-31",
+            ],
+            today,
+        )
+        .unwrap();
+        assert_eq!(filter.status, Some(TaskStatus::InProgress));
+        assert_eq!(filter.tag.as_deref(), Some("work"));
+        assert_eq!(filter.project.as_deref(), Some("web"));
+        assert_eq!(filter.due_by, NaiveDate::from_ymd_opt(2025, 3, 31));
+
+        let overdue = ListFilter::parse(&["--due", "overdue"], today).unwrap();
+        assert_eq!(overdue.due_by, NaiveDate::from_ymd_opt(2025, 3, 9));
+    }
+
+    #[test]
+    fn test_session_add_from_file() {
+        let path = env::temp_dir().join(format!("todore-ideas-{}.txt", process::id()));
+        fs::write(&path, "Learn Rust +study\n\n  Plant tomatoes @garden\n").unwrap();
+        let mut session = Session::new(
+            TaskList::new(),
+            Config::default(),
+            TemplateStore::default(),
+            "",
+        );
+        let line = format!(
+            "add --from-file {} @home +someday --due 2025-06-01",
+            path.display()
+        );
+        assert!(session.run_line(&line).unwrap().is_continue());
+        fs::remove_file(&path).unwrap();
+
+        let tasks = &session.task_list.tasks;
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[0].description, "Learn Rust");
+        assert_eq!(tasks[0].context.as_deref(), Some("home"));
+        assert_eq!(tasks[0].tags, vec!["someday", "study"]);
+        assert_eq!(tasks[1].description, "Plant tomatoes");
+        assert_eq!(tasks[1].context.as_deref(), Some("garden"));
+        assert_eq!(tasks[1].due, NaiveDate::from_ymd_opt(2025, 6, 1));
+        assert!(Command::from_str("add --from-file ideas.txt --due").is_err());
     }
 
     #[test]
@@ -577,15 +8603,29 @@ mod tests {
     fn test_command_remove() {
         let cmd = Command::from_str("remove 5").unwrap();
         match cmd {
-            Command::Remove { id } => assert_eq!(id, 5),
+            Command::Remove { id, force } => {
+                assert_eq!(id, 5);
+                assert!(!force);
+            }
             _ => panic!("Expected Remove command"),
         }
 
         let cmd_short = Command::from_str("r 10").unwrap();
         match cmd_short {
-            Command::Remove { id } => assert_eq!(id, 10),
+            Command::Remove { id, .. } => assert_eq!(id, 10),
             _ => panic!("Expected Remove command"),
         }
+
+        for input in ["remove 10 --force", "r -f 10"] {
+            assert!(matches!(
+                Command::from_str(input).unwrap(),
+                Command::Remove {
+                    id: 10,
+                    force: true
+                }
+            ));
+        }
+        assert!(Command::from_str("remove 10 11").is_err());
     }
 
     #[test]
@@ -633,7 +8673,7 @@ mod tests {
         match cmd {
             Command::Update { id, new_val, field } => {
                 assert_eq!(id, 1);
-                assert_eq!(new_val, "New");
+                assert_eq!(new_val, "New description");
                 assert!(matches!(field, TaskField::Description));
             }
             _ => panic!("Expected Update command"),
@@ -643,7 +8683,7 @@ mod tests {
         match cmd_short {
             Command::Update { id, new_val, field } => {
                 assert_eq!(id, 2);
-                assert_eq!(new_val, "Short");
+                assert_eq!(new_val, "Short desc");
                 assert!(matches!(field, TaskField::Description));
             }
             _ => panic!("Expected Update command"),
@@ -670,30 +8710,113 @@ mod tests {
     fn test_command_export() {
         let cmd = Command::from_str("export json output.json").unwrap();
         match cmd {
-            Command::Export { format, out_file } => {
-                assert!(matches!(format, Format::Json));
-                assert_eq!(out_file, "output.json");
+            Command::Export {
+                format,
+                out_file,
+                filter,
+            } => {
+                assert!(matches!(format, Format::JSON));
+                assert_eq!(out_file.as_deref(), Some("output.json"));
+                assert!(filter.is_none());
             }
             _ => panic!("Expected Export command"),
         }
 
         let cmd_short = Command::from_str("e y output.yaml").unwrap();
         match cmd_short {
-            Command::Export { format, out_file } => {
-                assert!(matches!(format, Format::Yaml));
-                assert_eq!(out_file, "output.yaml");
+            Command::Export {
+                format, out_file, ..
+            } => {
+                assert!(matches!(format, Format::YAML));
+                assert_eq!(out_file.as_deref(), Some("output.yaml"));
             }
             _ => panic!("Expected Export command"),
         }
 
         let cmd_plaintext = Command::from_str("e p output.txt").unwrap();
         match cmd_plaintext {
-            Command::Export { format, out_file } => {
-                assert!(matches!(format, Format::Plaintext));
-                assert_eq!(out_file, "output.txt");
+            Command::Export {
+                format, out_file, ..
+            } => {
+                assert!(matches!(format, Format("plaintext")));
+                assert_eq!(out_file.as_deref(), Some("output.txt"));
             }
             _ => panic!("Expected Export command"),
         }
+
+        assert!(matches!(
+            Command::from_str("export md --clipboard --tag work").unwrap(),
+            Command::Export { out_file: None, filter: Some(filter), .. }
+                if filter.tag.as_deref() == Some("work")
+        ));
+    }
+
+    #[test]
+    fn test_template_export() {
+        assert!(matches!(
+            Command::from_str("export template report.j2 report.txt").unwrap(),
+            Command::ExportTemplate { template, out_file, filter: None }
+                if template == "report.j2" && out_file == "report.txt"
+        ));
+        assert!(Command::from_str("export template report.j2").is_err());
+
+        let mut list = TaskList::new();
+        list.add(Task::new(0, "Fish & chips".to_string()));
+        list.add(Task::new(1, "Call mom".to_string()));
+        list.update_tags(1, vec!["family".to_string()]).unwrap();
+        list.update_status(1, TaskStatus::InProgress).unwrap();
+        let source = "{{ today }}\n{% for task in tasks %}* {{ task.description }} \
+                      ({{ task.status_text }}){% if task.tags %} {{ task.tags | join(\",\") }}{% endif %}\n\
+                      {% endfor %}";
+        let template = |name: &str| TemplateFormatter {
+            name: name.to_string(),
+            source: source.to_string(),
+            today: NaiveDate::from_ymd_opt(2025, 3, 5).unwrap(),
+        };
+        assert_eq!(
+            template("report.txt").format(&list).unwrap(),
+            "2025-03-05\n* Fish & chips (Not Started)\n* Call mom (In Progress) family\n"
+        );
+        assert!(
+            template("report.html")
+                .format(&list)
+                .unwrap()
+                .contains("Fish &amp; chips")
+        );
+        let broken = TemplateFormatter {
+            source: "{% for task in tasks %}".to_string(),
+            ..template("broken.txt")
+        };
+        assert!(broken.format(&list).is_err());
+    }
+
+    #[test]
+    fn test_ndjson_export_and_import() {
+        let mut list = TaskList::new();
+        list.add(Task::new(0, "Buy milk".to_string()));
+        list.add(Task::new(1, "Line\nbreak".to_string()));
+        let mut out = vec![];
+        NdjsonFormatter.write(&list, &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(text.lines().count(), 2);
+        assert!(text.starts_with("{\"id\":0,\"description\":\"Buy milk\""));
+        assert_eq!(NdjsonFormatter.format(&list).unwrap(), text);
+        let parsed = Format::NDJSON.parse(&format!("{}\n\n", text)).unwrap();
+        assert_eq!(parsed.tasks[1].description, "Line\nbreak");
+        assert_eq!(Format::of_path(Path::new("tasks.jsonl")), Format::NDJSON);
+
+        let file = env::temp_dir().join(format!("todore-ndjson-{}.ndjson", process::id()));
+        let mut session = Session::new(list, Config::default(), TemplateStore::default(), "");
+        session
+            .export(&NdjsonFormatter, file.to_str(), None)
+            .unwrap();
+        assert_eq!(fs::read_to_string(&file).unwrap(), text);
+        fs::remove_file(&file).unwrap();
+        session.dry_run = true;
+        session
+            .export(&NdjsonFormatter, file.to_str(), None)
+            .unwrap();
+        assert!(!file.exists());
     }
 
     #[test]
@@ -712,6 +8835,295 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_command_attach() {
+        let cmd = Command::from_str("attach 2 ./Spec.pdf").unwrap();
+        match cmd {
+            Command::Attach { id, target } => {
+                assert_eq!(id, 2);
+                assert_eq!(target, "./Spec.pdf");
+            }
+            _ => panic!("Expected Attach command"),
+        }
+
+        let result = Command::from_str("at 2");
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Invalid arguments for attach."
+        );
+    }
+
+    #[test]
+    fn test_command_add_from_template() {
+        let cmd = Command::from_str("add --from-template release-checklist").unwrap();
+        match cmd {
+            Command::AddFromTemplate { name } => assert_eq!(name, "release-checklist"),
+            _ => panic!("Expected AddFromTemplate command"),
+        }
+        assert!(Command::from_str("add --from-template").is_err());
+    }
+
+    #[test]
+    fn test_command_template() {
+        let cmd = Command::from_str("template save weekly 1,2,3").unwrap();
+        match cmd {
+            Command::Template {
+                action: TemplateAction::Save { name, ids },
+            } => {
+                assert_eq!(name, "weekly");
+                assert_eq!(ids, vec![1, 2, 3]);
+            }
+            _ => panic!("Expected Template save command"),
+        }
+        assert!(matches!(
+            Command::from_str("tpl list").unwrap(),
+            Command::Template {
+                action: TemplateAction::List
+            }
+        ));
+        assert_eq!(
+            Command::from_str("template save weekly")
+                .unwrap_err()
+                .to_string(),
+            "Invalid arguments for template."
+        );
+    }
+
+    #[test]
+    fn test_open_storage_refuses_to_encrypt_remote() {
+        let config = Config {
+            encrypt: true,
+            ..Config::default()
+        };
+        let url = Path::new("https://example.com/tasks.json");
+        let err = open_storage(url, &config).err().unwrap();
+        assert!(
+            err.to_string()
+                .starts_with("Can't encrypt https://example.com/tasks.json")
+        );
+        assert!(open_storage(url, &Config::default()).is_ok());
+    }
+
+    #[test]
+    fn test_open_storage_refuses_to_encrypt_redb() {
+        let file = env::temp_dir().join(format!("todore-encrypt-{}.redb", process::id()));
+        let config = Config {
+            encrypt: true,
+            ..Config::default()
+        };
+        let err = open_storage(&file, &config).err().unwrap();
+        assert!(err.to_string().starts_with("Can't encrypt"));
+        assert!(!file.exists());
+        assert!(open_storage(&file, &Config::default()).is_ok());
+        let _ = fs::remove_file(&file);
+    }
+
+    #[test]
+    fn test_session_backup_round_trip() {
+        let file = env::temp_dir().join(format!("todore-backup-{}.tar.gz", process::id()));
+        let mut session = Session::new(
+            TaskList::new(),
+            Config::default(),
+            TemplateStore::default(),
+            "",
+        );
+        let line = format!(
+            "add Buy milk; add Eggs; done 1 --archive; template save weekly 0; backup export {}",
+            file.display()
+        );
+        let result = session.run_json(&line);
+        assert_eq!(result["ok"], true, "{}", result);
+
+        let config = Config {
+            confirm: false,
+            ..Config::default()
+        };
+        let mut other = Session::new(TaskList::new(), config, TemplateStore::default(), "");
+        let result = other.run_json(&format!("add Other; backup import {}", file.display()));
+        assert_eq!(result["ok"], true, "{}", result);
+        assert_eq!(other.task_list.tasks.len(), 1);
+        assert_eq!(other.task_list.archived.len(), 1);
+        assert_eq!(other.templates.templates[0].name, "weekly");
+        assert!(other.run_json("backup import missing.tar.gz")["ok"] != true);
+        fs::remove_file(&file).unwrap();
+    }
+
+    #[test]
+    fn test_templates_in_memory_only() {
+        // As in --ephemeral: without a templates file, saved templates aren't written out.
+        let mut session = Session::new(
+            TaskList::new(),
+            Config::default(),
+            TemplateStore::default(),
+            "",
+        );
+        let result = session.run_json("add Buy milk; template save weekly 0");
+        assert_eq!(result["ok"], true);
+        assert_eq!(session.templates.templates[0].name, "weekly");
+    }
+
+    #[test]
+    fn test_command_remind() {
+        match Command::from_str("remind 4 Tomorrow 9am").unwrap() {
+            Command::Remind { id, when } => {
+                assert_eq!(id, 4);
+                assert_eq!(when, "tomorrow 9am");
+            }
+            _ => panic!("Expected Remind command"),
+        }
+        assert!(Command::from_str("remind 4").is_err());
+        assert!(matches!(
+            Command::from_str("reminders --notify").unwrap(),
+            Command::Reminders { notify: true }
+        ));
+    }
+
+    #[test]
+    fn test_command_snooze() {
+        match Command::from_str("snooze 3 3d").unwrap() {
+            Command::Snooze { id, by } => {
+                assert_eq!(id, 3);
+                assert_eq!(by, TimeDelta::days(3));
+            }
+            _ => panic!("Expected Snooze command"),
+        }
+        assert!(Command::from_str("z 3").is_err());
+        assert!(Command::from_str("z 3 soon").is_err());
+    }
+
+    #[test]
+    fn test_command_history() {
+        assert!(matches!(
+            Command::from_str("history 7").unwrap(),
+            Command::History { id: 7 }
+        ));
+        assert!(Command::from_str("h").is_err());
+    }
+
+    #[test]
+    fn test_command_sprint_summary() {
+        match Command::from_str("sprint summary s12").unwrap() {
+            Command::SprintSummary { sprint } => assert_eq!(sprint.as_deref(), Some("s12")),
+            _ => panic!("Expected SprintSummary command"),
+        }
+        assert!(matches!(
+            Command::from_str("sprint summary").unwrap(),
+            Command::SprintSummary { sprint: None }
+        ));
+        assert!(Command::from_str("sprint").is_err());
+    }
+
+    #[test]
+    fn test_command_milestone() {
+        match Command::from_str("milestone add Public beta").unwrap() {
+            Command::Milestone {
+                action: MilestoneAction::Add { name },
+            } => assert_eq!(name, "Public beta"),
+            _ => panic!("Expected Milestone add command"),
+        }
+        assert!(matches!(
+            Command::from_str("ms remove 2").unwrap(),
+            Command::Milestone {
+                action: MilestoneAction::Remove { id: 2 }
+            }
+        ));
+        assert!(Command::from_str("milestone add").is_err());
+    }
+
+    #[test]
+    fn test_command_update_due_natural_date() {
+        match Command::from_str("update 3 due next friday").unwrap() {
+            Command::Update { id, new_val, field } => {
+                assert_eq!(id, 3);
+                assert_eq!(new_val, "next friday");
+                assert!(matches!(field, TaskField::Due));
+            }
+            _ => panic!("Expected Update command"),
+        }
+    }
+
+    #[test]
+    fn test_command_stale() {
+        assert!(matches!(
+            Command::from_str("stale").unwrap(),
+            Command::Stale { days: None }
+        ));
+        assert!(matches!(
+            Command::from_str("stale 30").unwrap(),
+            Command::Stale { days: Some(30) }
+        ));
+        assert!(Command::from_str("stale soon").is_err());
+    }
+
+    #[test]
+    fn test_command_skip_and_except() {
+        assert!(matches!(
+            Command::from_str("skip 4").unwrap(),
+            Command::Skip { id: 4 }
+        ));
+        match Command::from_str("except 4 dec 25").unwrap() {
+            Command::Except { id, date } => {
+                assert_eq!(id, 4);
+                assert_eq!(date, "dec 25");
+            }
+            _ => panic!("Expected Except command"),
+        }
+        assert!(Command::from_str("except 4").is_err());
+    }
+
+    #[test]
+    fn test_command_link() {
+        match Command::from_str("link 3 duplicates 7").unwrap() {
+            Command::Link { id, kind, other } => {
+                assert_eq!((id, other), (3, 7));
+                assert_eq!(kind, RelationKind::Duplicates);
+            }
+            _ => panic!("Expected Link command"),
+        }
+        assert!(Command::from_str("link 3 blocks 7").is_err());
+        assert!(matches!(
+            Command::from_str("unlink 3 7").unwrap(),
+            Command::Unlink { id: 3, other: 7 }
+        ));
+    }
+
+    #[test]
+    fn test_command_next() {
+        assert!(matches!(
+            Command::from_str("next").unwrap(),
+            Command::Next {
+                count: 1,
+                energy: None
+            }
+        ));
+        assert!(matches!(
+            Command::from_str("next --energy low").unwrap(),
+            Command::Next {
+                count: 1,
+                energy: Some(Energy::Low)
+            }
+        ));
+        assert!(matches!(
+            Command::from_str("n 5 --energy high").unwrap(),
+            Command::Next {
+                count: 5,
+                energy: Some(Energy::High)
+            }
+        ));
+        assert!(Command::from_str("next 0").is_err());
+        assert!(Command::from_str("next --energy sleepy").is_err());
+        assert!(Command::from_str("next --energy").is_err());
+    }
+
+    #[test]
+    fn test_command_open() {
+        assert!(matches!(
+            Command::from_str("open 3").unwrap(),
+            Command::Open { id: 3 }
+        ));
+        assert!(Command::from_str("o").is_err());
+    }
+
     #[test]
     fn test_command_quit() {
         let cmd = Command::from_str("quit").unwrap();
@@ -731,15 +9143,15 @@ mod tests {
     // Format and TaskField enum parsing tests
     #[test]
     fn test_format_fromstr_valid() {
-        assert!(matches!(Format::from_str("json"), Ok(Format::Json)));
-        assert!(matches!(Format::from_str("j"), Ok(Format::Json)));
-        assert!(matches!(Format::from_str("yaml"), Ok(Format::Yaml)));
-        assert!(matches!(Format::from_str("y"), Ok(Format::Yaml)));
+        assert!(matches!(Format::from_str("json"), Ok(Format::JSON)));
+        assert!(matches!(Format::from_str("j"), Ok(Format::JSON)));
+        assert!(matches!(Format::from_str("yaml"), Ok(Format::YAML)));
+        assert!(matches!(Format::from_str("y"), Ok(Format::YAML)));
         assert!(matches!(
             Format::from_str("plaintext"),
-            Ok(Format::Plaintext)
+            Ok(Format("plaintext"))
         ));
-        assert!(matches!(Format::from_str("p"), Ok(Format::Plaintext)));
+        assert!(matches!(Format::from_str("p"), Ok(Format("plaintext"))));
     }
 
     #[test]
@@ -764,6 +9176,28 @@ mod tests {
             Ok(TaskField::Status)
         ));
         assert!(matches!(TaskField::from_str("s"), Ok(TaskField::Status)));
+        assert!(matches!(TaskField::from_str("due"), Ok(TaskField::Due)));
+        assert!(matches!(TaskField::from_str("tags"), Ok(TaskField::Tags)));
+        assert!(matches!(
+            TaskField::from_str("project"),
+            Ok(TaskField::Project)
+        ));
+        assert!(matches!(
+            TaskField::from_str("progress"),
+            Ok(TaskField::Progress)
+        ));
+        assert!(matches!(
+            TaskField::from_str("parent"),
+            Ok(TaskField::Parent)
+        ));
+        assert!(matches!(
+            TaskField::from_str("waiting-on"),
+            Ok(TaskField::WaitingOn)
+        ));
+        assert!(matches!(
+            TaskField::from_str("sch"),
+            Ok(TaskField::Scheduled)
+        ));
     }
 
     #[test]
@@ -0,0 +1,91 @@
+// Each style is switched off with its own reset code so that rendered descriptions
+// can be nested inside an outer colour.
+const BOLD: (&str, &str) = ("\x1b[1m", "\x1b[22m");
+const ITALIC: (&str, &str) = ("\x1b[3m", "\x1b[23m");
+const UNDERLINE: (&str, &str) = ("\x1b[4m", "\x1b[24m");
+const CODE: (&str, &str) = ("\x1b[36m", "\x1b[39m");
+
+/// Renders inline Markdown (`**bold**`, `*italic*`, `` `code` `` and `[text](url)`)
+/// as ANSI escape sequences. Unterminated markup is left as written.
+pub fn render(text: &str) -> String {
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        let rendered = match c {
+            '`' => {
+                delimited(rest, "`").map(|(code, len)| (format!("{}{code}{}", CODE.0, CODE.1), len))
+            }
+            '*' if rest.starts_with("**") => delimited(rest, "**")
+                .map(|(inner, len)| (format!("{}{}{}", BOLD.0, render(inner), BOLD.1), len)),
+            '*' | '_' => delimited(rest, &rest[..1])
+                .map(|(inner, len)| (format!("{}{}{}", ITALIC.0, render(inner), ITALIC.1), len)),
+            '[' => link(rest).map(|(label, url, len)| {
+                (
+                    format!("{}{}{} ({})", UNDERLINE.0, render(label), UNDERLINE.1, url),
+                    len,
+                )
+            }),
+            _ => None,
+        };
+        match rendered {
+            Some((rendered, len)) => {
+                out.push_str(&rendered);
+                rest = &rest[len..];
+            }
+            None => {
+                out.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    out
+}
+
+/// Splits `marker inner marker` off the front of `s`, returning `inner` and the consumed length.
+fn delimited<'a>(s: &'a str, marker: &str) -> Option<(&'a str, usize)> {
+    let body = &s[marker.len()..];
+    let end = body.find(marker)?;
+    let inner = &body[..end];
+    if inner.is_empty() || inner.starts_with(' ') || inner.ends_with(' ') {
+        return None;
+    }
+    Some((inner, marker.len() * 2 + end))
+}
+
+fn link(s: &str) -> Option<(&str, &str, usize)> {
+    let label_end = s.find("](")?;
+    let url_len = s[label_end + 2..].find(')')?;
+    let url = &s[label_end + 2..label_end + 2 + url_len];
+    Some((&s[1..label_end], url, label_end + 3 + url_len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_plain_text_unchanged() {
+        assert_eq!(render("buy milk"), "buy milk");
+        assert_eq!(render("2 * 3 = 6"), "2 * 3 = 6");
+        assert_eq!(render("a * b * c"), "a * b * c");
+        assert_eq!(render("unterminated `code"), "unterminated `code");
+        assert_eq!(render("naïve café"), "naïve café");
+    }
+
+    #[test]
+    fn test_render_inline_markup() {
+        assert_eq!(render("**urgent** fix"), "\x1b[1murgent\x1b[22m fix");
+        assert_eq!(render("*maybe*"), "\x1b[3mmaybe\x1b[23m");
+        assert_eq!(render("_maybe_"), "\x1b[3mmaybe\x1b[23m");
+        assert_eq!(render("run `cargo test`"), "run \x1b[36mcargo test\x1b[39m");
+    }
+
+    #[test]
+    fn test_render_link() {
+        assert_eq!(
+            render("see [docs](https://example.com) now"),
+            "see \x1b[4mdocs\x1b[24m (https://example.com) now"
+        );
+        assert_eq!(render("[not a link]"), "[not a link]");
+    }
+}
@@ -0,0 +1,234 @@
+//! Moving tasks from Microsoft To Do and Outlook: the task JSON of the Microsoft Graph
+//! API, as To Do export tools save it, and Outlook's task CSV export. Exports are written
+//! as Outlook CSV, which Outlook imports back.
+
+use crate::Formatter;
+use crate::Priority;
+use crate::Task;
+use crate::TaskList;
+use crate::TaskStatus;
+use crate::csv::Table;
+use crate::csv_field;
+use chrono::NaiveDate;
+use serde_json::Value;
+
+/// Reads To Do JSON, when `text` is JSON, or else Outlook CSV.
+pub fn parse(text: &str) -> Result<TaskList, String> {
+    if text.trim_start().starts_with(['{', '[']) {
+        let value: Value = serde_json::from_str(text).map_err(|err| err.to_string())?;
+        let mut tasks = TaskList::new();
+        add_json(&mut tasks, &value, None);
+        Ok(tasks)
+    } else {
+        parse_csv(text)
+    }
+}
+
+/// Adds every To Do task in `value`: a task, an array of them, a `{"value": [...]}` page
+/// from the API or a list with its `tasks`, whose name becomes their project.
+fn add_json(tasks: &mut TaskList, value: &Value, project: Option<&str>) {
+    match value {
+        Value::Array(items) => {
+            for item in items {
+                add_json(tasks, item, project);
+            }
+        }
+        Value::Object(object) if object.contains_key("title") => {
+            let text = |key: &str| value[key].as_str().filter(|s| !s.is_empty());
+            let mut task = Task::new(
+                tasks.tasks.len() as u32,
+                text("title").unwrap_or_default().into(),
+            );
+            task.status = match text("status") {
+                Some("inProgress") => TaskStatus::InProgress,
+                Some("waitingOnOthers") => TaskStatus::Waiting,
+                Some("completed") => TaskStatus::Completed,
+                _ => TaskStatus::NotStarted,
+            };
+            task.priority = priority(text("importance").unwrap_or_default());
+            task.due = value["dueDateTime"]["dateTime"].as_str().and_then(date);
+            task.notes = value["body"]["content"]
+                .as_str()
+                .map(|notes| notes.trim().to_string())
+                .filter(|notes| !notes.is_empty());
+            task.tags = value["categories"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|category| category.as_str())
+                .map(tag)
+                .collect();
+            task.project = project.map(String::from);
+            tasks.tasks.push(task);
+        }
+        Value::Object(object) => {
+            let name = value["displayName"].as_str().or(project);
+            for key in ["value", "tasks", "lists"] {
+                if let Some(items) = object.get(key) {
+                    add_json(tasks, items, name);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn parse_csv(text: &str) -> Result<TaskList, String> {
+    let table = Table::parse(text)?;
+    if !table.has("subject") {
+        return Err("Not an Outlook task export: it has no Subject column".into());
+    }
+    let mut tasks = TaskList::new();
+    for row in &table.rows {
+        let Some(subject) = table.get(row, "subject") else {
+            continue;
+        };
+        let mut task = Task::new(tasks.tasks.len() as u32, subject.to_string());
+        task.status = match table.get(row, "status").unwrap_or_default() {
+            "In Progress" => TaskStatus::InProgress,
+            "Waiting on someone else" => TaskStatus::Waiting,
+            "Completed" => TaskStatus::Completed,
+            _ if table.get(row, "date completed").is_some() => TaskStatus::Completed,
+            _ => TaskStatus::NotStarted,
+        };
+        task.priority = table.get(row, "priority").and_then(priority);
+        task.scheduled = table.get(row, "start date").and_then(date);
+        task.due = table.get(row, "due date").and_then(date);
+        task.notes = table.get(row, "notes").map(String::from);
+        task.tags = table
+            .get(row, "categories")
+            .into_iter()
+            .flat_map(|categories| categories.split([';', ',']))
+            .map(str::trim)
+            .filter(|category| !category.is_empty())
+            .map(tag)
+            .collect();
+        tasks.tasks.push(task);
+    }
+    Ok(tasks)
+}
+
+/// Outlook's and To Do's importance; normal is todore's default of no priority.
+fn priority(importance: &str) -> Option<Priority> {
+    match importance.to_lowercase().as_str() {
+        "high" => Some(Priority::High),
+        "low" => Some(Priority::Low),
+        _ => None,
+    }
+}
+
+/// A date as To Do's ISO date-times or Outlook's US style `3/5/2025` dates give it.
+fn date(text: &str) -> Option<NaiveDate> {
+    let text = text.trim();
+    text.get(..10)
+        .and_then(|day| NaiveDate::parse_from_str(day, "%Y-%m-%d").ok())
+        .or_else(|| NaiveDate::parse_from_str(text, "%m/%d/%Y").ok())
+}
+
+/// A category as a tag, which can't contain spaces.
+fn tag(category: &str) -> String {
+    category.trim().replace(char::is_whitespace, "-")
+}
+
+/// The tasks as an Outlook task CSV, with the columns Outlook maps on import.
+pub struct OutlookFormatter;
+
+impl Formatter for OutlookFormatter {
+    fn format(&self, tasks: &TaskList) -> Result<String, Box<dyn std::error::Error>> {
+        let mut out =
+            String::from("Subject,Start Date,Due Date,Categories,Notes,Priority,Status\n");
+        let date = |date: Option<NaiveDate>| {
+            date.map(|date| date.format("%-m/%-d/%Y").to_string())
+                .unwrap_or_default()
+        };
+        for task in tasks.ordered() {
+            let fields = [
+                task.description.clone(),
+                date(task.scheduled),
+                date(task.due),
+                task.tags.join(";"),
+                task.notes.clone().unwrap_or_default(),
+                match task.priority {
+                    Some(Priority::High) => "High",
+                    Some(Priority::Low) => "Low",
+                    _ => "Normal",
+                }
+                .to_string(),
+                match task.status {
+                    TaskStatus::NotStarted => "Not Started",
+                    TaskStatus::InProgress => "In Progress",
+                    TaskStatus::Waiting => "Waiting on someone else",
+                    TaskStatus::Completed => "Completed",
+                }
+                .to_string(),
+            ];
+            let row: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+            out.push_str(&row.join(","));
+            out.push('\n');
+        }
+        Ok(out)
+    }
+
+    fn parse(&self, text: &str) -> Result<TaskList, Box<dyn std::error::Error>> {
+        Ok(parse(text)?)
+    }
+
+    fn imports(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ms_todo_json() {
+        let json = r#"[{"displayName": "Groceries", "tasks": [
+            {"title": "Buy milk", "status": "completed", "importance": "high",
+             "body": {"content": "2 litres", "contentType": "text"},
+             "dueDateTime": {"dateTime": "2025-03-05T00:00:00.0000000", "timeZone": "UTC"},
+             "categories": ["Red category"]},
+            {"title": "Eggs", "status": "notStarted", "importance": "normal",
+             "body": {"content": "", "contentType": "text"}}
+        ]}]"#;
+        let tasks = parse(json).unwrap();
+        assert_eq!(tasks.tasks.len(), 2);
+        let milk = &tasks.tasks[0];
+        assert_eq!(milk.status, TaskStatus::Completed);
+        assert_eq!(milk.priority, Some(Priority::High));
+        assert_eq!(milk.due, NaiveDate::from_ymd_opt(2025, 3, 5));
+        assert_eq!(milk.notes.as_deref(), Some("2 litres"));
+        assert_eq!(milk.tags, vec!["Red-category"]);
+        assert_eq!(milk.project.as_deref(), Some("Groceries"));
+        assert_eq!(tasks.tasks[1].priority, None);
+        assert_eq!(tasks.tasks[1].notes, None);
+    }
+
+    #[test]
+    fn test_outlook_csv_round_trip() {
+        let csv = "Subject,Start Date,Due Date,Reminder On/Off,Date Completed,Categories,Notes,\
+                   Priority,Status\r\n\
+                   Send invoice,,3/5/2025,False,,Work; Billing,\"Net 30, by email\",High,\
+                   Waiting on someone else\r\n\
+                   File taxes,1/2/2025,,False,2/1/2025,,,Normal,Deferred\r\n";
+        let tasks = parse(csv).unwrap();
+        let invoice = &tasks.tasks[0];
+        assert_eq!(invoice.due, NaiveDate::from_ymd_opt(2025, 3, 5));
+        assert_eq!(invoice.status, TaskStatus::Waiting);
+        assert_eq!(invoice.tags, vec!["Work", "Billing"]);
+        assert_eq!(invoice.notes.as_deref(), Some("Net 30, by email"));
+        assert_eq!(tasks.tasks[1].status, TaskStatus::Completed);
+        assert_eq!(
+            tasks.tasks[1].scheduled,
+            NaiveDate::from_ymd_opt(2025, 1, 2)
+        );
+
+        let exported = OutlookFormatter.format(&tasks).unwrap();
+        assert!(exported.contains("Send invoice,,3/5/2025,Work;Billing,\"Net 30, by email\",High"));
+        let reparsed = parse(&exported).unwrap();
+        assert_eq!(reparsed.tasks[0].tags, invoice.tags);
+        assert_eq!(reparsed.tasks[1].status, TaskStatus::Completed);
+        assert!(parse("Title\nx\n").is_err());
+    }
+}
@@ -0,0 +1,118 @@
+use crate::fuzzy;
+use crossterm::cursor;
+use crossterm::event;
+use crossterm::event::Event;
+use crossterm::event::KeyCode;
+use crossterm::event::KeyEvent;
+use crossterm::event::KeyEventKind;
+use crossterm::event::KeyModifiers;
+use crossterm::execute;
+use crossterm::terminal;
+use std::io;
+use std::io::Write;
+
+/// How many matches are shown below the query line.
+const VISIBLE: usize = 10;
+
+/// The items whose label fuzzy-matches `query`, best match first. Ties keep their
+/// original order, so an empty query lists everything as given.
+pub fn matches<'a>(items: &'a [(u32, String)], query: &str) -> Vec<&'a (u32, String)> {
+    let mut scored: Vec<(i64, &(u32, String))> = items
+        .iter()
+        .filter_map(|item| fuzzy::score(query, &item.1).map(|score| (score, item)))
+        .collect();
+    scored.sort_by_key(|(score, _)| -score);
+    scored.into_iter().map(|(_, item)| item).collect()
+}
+
+/// Lets the user choose an item by typing to filter and moving with the arrow keys.
+/// Enter picks the highlighted item; Esc or Ctrl-C picks nothing.
+pub fn pick(prompt: &str, items: &[(u32, String)]) -> io::Result<Option<u32>> {
+    terminal::enable_raw_mode()?;
+    let picked = run(prompt, items);
+    let mut out = io::stdout();
+    execute!(
+        out,
+        cursor::MoveToColumn(0),
+        terminal::Clear(terminal::ClearType::FromCursorDown)
+    )?;
+    terminal::disable_raw_mode()?;
+    picked
+}
+
+fn run(prompt: &str, items: &[(u32, String)]) -> io::Result<Option<u32>> {
+    let mut out = io::stdout();
+    let mut query = String::new();
+    let mut selected = 0;
+    loop {
+        let shown = matches(items, &query);
+        selected = selected.min(shown.len().saturating_sub(1));
+        execute!(
+            out,
+            cursor::MoveToColumn(0),
+            terminal::Clear(terminal::ClearType::FromCursorDown)
+        )?;
+        write!(out, "{}{}", prompt, query)?;
+        for (i, (id, label)) in shown.iter().take(VISIBLE).enumerate() {
+            let marker = if i == selected { '>' } else { ' ' };
+            write!(out, "\r\n{} {} {}", marker, id, label)?;
+        }
+        let lines = shown.len().min(VISIBLE) as u16;
+        if lines > 0 {
+            execute!(out, cursor::MoveUp(lines))?;
+        }
+        let column = (prompt.chars().count() + query.chars().count()) as u16;
+        execute!(out, cursor::MoveToColumn(column))?;
+        out.flush()?;
+
+        let Event::Key(KeyEvent {
+            code,
+            modifiers,
+            kind: KeyEventKind::Press,
+            ..
+        }) = event::read()?
+        else {
+            continue;
+        };
+        match code {
+            KeyCode::Enter => return Ok(shown.get(selected).map(|(id, _)| *id)),
+            KeyCode::Esc => return Ok(None),
+            KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => return Ok(None),
+            KeyCode::Up => selected = selected.saturating_sub(1),
+            KeyCode::Down => selected = (selected + 1).min(VISIBLE - 1),
+            KeyCode::Backspace => {
+                query.pop();
+                selected = 0;
+            }
+            KeyCode::Char(c) => {
+                query.push(c);
+                selected = 0;
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_filters_and_ranks() {
+        let items = vec![
+            (1, "Make invoices like last week".to_string()),
+            (2, "Buy milk".to_string()),
+            (3, "Call mom".to_string()),
+        ];
+        let ids = |query| {
+            matches(&items, query)
+                .iter()
+                .map(|(id, _)| *id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids(""), vec![1, 2, 3]);
+        assert_eq!(ids("milk"), vec![2, 1]);
+        assert_eq!(ids("mom"), vec![3]);
+        assert!(ids("xyz").is_empty());
+    }
+}
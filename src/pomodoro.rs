@@ -0,0 +1,75 @@
+use crossterm::event;
+use crossterm::event::Event;
+use crossterm::event::KeyCode;
+use crossterm::event::KeyEvent;
+use crossterm::event::KeyEventKind;
+use crossterm::event::KeyModifiers;
+use crossterm::terminal;
+use std::io;
+use std::io::IsTerminal;
+use std::io::Write;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+/// Counts down `length`, showing the time left on one line. On a terminal q, Esc or
+/// Ctrl-C stops early. Returns how long the countdown actually ran.
+pub fn countdown(length: Duration) -> io::Result<Duration> {
+    if !io::stdin().is_terminal() {
+        thread::sleep(length);
+        return Ok(length);
+    }
+    terminal::enable_raw_mode()?;
+    let ran = tick(Instant::now(), length);
+    terminal::disable_raw_mode()?;
+    println!();
+    ran
+}
+
+fn tick(start: Instant, length: Duration) -> io::Result<Duration> {
+    let mut out = io::stdout();
+    loop {
+        let elapsed = start.elapsed();
+        if elapsed >= length {
+            return Ok(length);
+        }
+        write!(out, "\r{} left (q to stop) ", remaining(length - elapsed))?;
+        out.flush()?;
+        if !event::poll(Duration::from_millis(250))? {
+            continue;
+        }
+        if let Event::Key(KeyEvent {
+            code,
+            modifiers,
+            kind: KeyEventKind::Press,
+            ..
+        }) = event::read()?
+        {
+            match code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(start.elapsed()),
+                KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => {
+                    return Ok(start.elapsed());
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Time left as `mm:ss`, rounded up so the display never shows 00:00 early.
+fn remaining(left: Duration) -> String {
+    let seconds = left.as_millis().div_ceil(1000);
+    format!("{:02}:{:02}", seconds / 60, seconds % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remaining() {
+        assert_eq!(remaining(Duration::from_secs(25 * 60)), "25:00");
+        assert_eq!(remaining(Duration::from_millis(59_001)), "01:00");
+        assert_eq!(remaining(Duration::from_millis(300)), "00:01");
+    }
+}
@@ -0,0 +1,233 @@
+use std::collections::BTreeMap;
+
+/// Splits a command line into words the way a POSIX shell would: whitespace separates
+/// words, `'single'` quotes keep everything literally, `"double"` quotes allow `\"` and
+/// `\\` escapes, and a backslash outside quotes escapes the next character.
+pub fn split(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err("Unterminated ' quote".into()),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\')) => word.push(c),
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err("Unterminated \" quote".into()),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err("Unterminated \" quote".into()),
+                    }
+                }
+            }
+            '\\' => match chars.next() {
+                Some(c) => word.get_or_insert_with(String::new).push(c),
+                None => return Err("Nothing to escape after \\".into()),
+            },
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+/// The byte offsets of the characters in `line` that are outside quotes and not escaped
+/// by a backslash, i.e. those that may act as `;` or `!!`.
+fn unquoted_positions(line: &str) -> Vec<usize> {
+    let mut positions = vec![];
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            _ if escaped => escaped = false,
+            (Some('"'), '\\') | (None, '\\') => escaped = true,
+            (Some(open), c) if c == open => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, _) => positions.push(i),
+        }
+    }
+    positions
+}
+
+/// Splits a line into the commands separated by unquoted `;`, dropping empty ones.
+pub fn split_commands(line: &str) -> Vec<String> {
+    let mut commands = vec![];
+    let mut start = 0;
+    for i in unquoted_positions(line) {
+        if line[i..].starts_with(';') {
+            commands.push(line[start..i].trim().to_string());
+            start = i + 1;
+        }
+    }
+    commands.push(line[start..].trim().to_string());
+    commands.retain(|command| !command.is_empty());
+    commands
+}
+
+/// Replaces every unquoted `!!` with the previous input line.
+pub fn expand_history(line: &str, previous: Option<&str>) -> Result<String, String> {
+    let positions = unquoted_positions(line);
+    let mut expanded = String::new();
+    let mut rest = 0;
+    for &i in &positions {
+        if i >= rest && line[i..].starts_with("!!") && positions.contains(&(i + 1)) {
+            let previous = previous.ok_or("No previous command for !!")?;
+            expanded.push_str(&line[rest..i]);
+            expanded.push_str(previous);
+            rest = i + 2;
+        }
+    }
+    expanded.push_str(&line[rest..]);
+    Ok(expanded)
+}
+
+/// Quotes a word so that [`split`] reads it back unchanged.
+pub fn quote(word: &str) -> String {
+    let plain = |c: char| c.is_alphanumeric() || "-_./:@+,%=".contains(c);
+    if !word.is_empty() && word.chars().all(plain) {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', r"'\''"))
+    }
+}
+
+/// Expands a user-defined alias at the start of `line`. `$1`..`$9` in the alias are
+/// replaced by the matching argument and `$@` by all of them; when the alias uses no
+/// placeholders the arguments are appended. Lines not starting with an alias are
+/// returned unchanged.
+pub fn expand_alias(line: &str, aliases: &BTreeMap<String, String>) -> Result<String, String> {
+    let words = split(line)?;
+    let Some(expansion) = words
+        .first()
+        .and_then(|name| aliases.get(&name.to_lowercase()))
+    else {
+        return Ok(line.to_string());
+    };
+    let args: Vec<String> = words[1..].iter().map(|arg| quote(arg)).collect();
+    if !expansion.contains('$') {
+        return Ok([expansion.clone()]
+            .into_iter()
+            .chain(args)
+            .collect::<Vec<_>>()
+            .join(" "));
+    }
+    let mut expanded = String::new();
+    let mut chars = expansion.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('$', Some('@')) => {
+                chars.next();
+                expanded.push_str(&args.join(" "));
+            }
+            ('$', Some(digit @ '1'..='9')) => {
+                let index = digit.to_digit(10).unwrap() as usize;
+                chars.next();
+                let arg = args.get(index - 1).ok_or(format!(
+                    "Alias {} needs at least {} argument(s)",
+                    words[0], index
+                ))?;
+                expanded.push_str(arg);
+            }
+            (c, _) => expanded.push(c),
+        }
+    }
+    Ok(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_words() {
+        assert_eq!(split("add Buy  milk").unwrap(), vec!["add", "Buy", "milk"]);
+        assert_eq!(split("  ").unwrap(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_split_quotes_and_escapes() {
+        assert_eq!(
+            split(r#"add "Buy milk" '@not context' it\'s"#).unwrap(),
+            vec!["add", "Buy milk", "@not context", "it's"]
+        );
+        assert_eq!(
+            split(r#"u 1 d "say \"hi\" \n""#).unwrap(),
+            vec!["u", "1", "d", r#"say "hi" \n"#]
+        );
+        assert_eq!(split(r#"a "" b"#).unwrap(), vec!["a", "", "b"]);
+        assert_eq!(split(r#"pre"fix"ed"#).unwrap(), vec!["prefixed"]);
+    }
+
+    #[test]
+    fn test_quote_round_trips() {
+        for word in ["plain", "two words", "it's", "", r#"say "hi""#] {
+            assert_eq!(split(&quote(word)).unwrap(), vec![word]);
+        }
+        assert_eq!(quote("in-progress"), "in-progress");
+    }
+
+    #[test]
+    fn test_expand_alias() {
+        let aliases = BTreeMap::from([
+            ("t".to_string(), "update $1 status in-progress".to_string()),
+            ("w".to_string(), "add $@ @work".to_string()),
+            ("ls".to_string(), "list --all".to_string()),
+        ]);
+        let expand = |line| expand_alias(line, &aliases);
+        assert_eq!(expand("t 3").unwrap(), "update 3 status in-progress");
+        assert_eq!(
+            expand("w 'Fix bug' now").unwrap(),
+            "add 'Fix bug' now @work"
+        );
+        assert_eq!(expand("ls @home").unwrap(), "list --all @home");
+        assert_eq!(expand("list").unwrap(), "list");
+        assert!(expand("t").is_err());
+    }
+
+    #[test]
+    fn test_split_commands() {
+        assert_eq!(
+            split_commands(r#"add "x; y"; add 'z;' ;; list"#),
+            vec![r#"add "x; y""#, "add 'z;'", "list"]
+        );
+        assert_eq!(split_commands(r"add a\;b"), vec![r"add a\;b"]);
+        assert!(split_commands(" ; ").is_empty());
+    }
+
+    #[test]
+    fn test_expand_history() {
+        assert_eq!(expand_history("!!", Some("list")).unwrap(), "list");
+        assert_eq!(
+            expand_history("!! --all; !!", Some("list")).unwrap(),
+            "list --all; list"
+        );
+        assert_eq!(expand_history("add 'wow!!'", None).unwrap(), "add 'wow!!'");
+        assert!(expand_history("!!", None).is_err());
+    }
+
+    #[test]
+    fn test_split_unterminated() {
+        assert!(split("add \"Buy milk").is_err());
+        assert!(split("add 'Buy milk").is_err());
+        assert!(split("add milk\\").is_err());
+    }
+}
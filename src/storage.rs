@@ -0,0 +1,756 @@
+//! Where the task list is kept between runs. The session only talks to a [`Storage`],
+//! so another backend can be dropped in without touching `TaskList` or the commands.
+
+use crate::Format;
+use crate::Task;
+use crate::TaskList;
+use crate::TemplateStore;
+use crate::crypto;
+use crate::crypto::Cipher;
+use chrono::DateTime;
+use chrono::Local;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::Deserialize;
+use serde::Serialize;
+use sha2::Digest;
+use sha2::Sha256;
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::io::Read;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process;
+use std::sync::mpsc;
+use std::sync::mpsc::RecvTimeoutError;
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// Backends are `Send` so a [`BackgroundSave`] can take one to another thread.
+pub trait Storage: Send {
+    /// Reads every task. A store that doesn't exist yet holds none.
+    fn load(&self) -> Result<TaskList, Box<dyn std::error::Error>>;
+
+    /// Replaces everything stored with `tasks`.
+    fn save(&self, tasks: &TaskList) -> Result<(), Box<dyn std::error::Error>>;
+
+    /// Records a change to the tasks, for backends that keep a journal as well as the
+    /// tasks themselves. Does nothing by default.
+    fn append_event(&self, _event: &Event) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+
+    /// Every event appended so far, oldest first. Backends without a journal have none.
+    fn events(&self) -> Result<Vec<Event>, Box<dyn std::error::Error>> {
+        Ok(vec![])
+    }
+
+    /// Keeps `snapshot` under `name`, replacing any snapshot already called that.
+    fn save_snapshot(
+        &self,
+        _name: &str,
+        _snapshot: &Snapshot,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        Err("This storage can't keep snapshots".into())
+    }
+
+    fn load_snapshot(&self, name: &str) -> Result<Snapshot, Box<dyn std::error::Error>> {
+        Err(format!("No snapshot called {}", name).into())
+    }
+
+    /// The names of every snapshot, sorted.
+    fn snapshots(&self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        Ok(vec![])
+    }
+
+    /// Whether what's stored is exactly what the last save wrote, for backends that can
+    /// tell. Anything else was changed behind todore's back, by hand or by damage.
+    fn check(&self) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// The tasks as they were before the last save, and when they were saved, for
+    /// recovering from a damaged store. Backends that keep no such copy have none.
+    fn last_good(&self) -> Result<Option<Saved>, Box<dyn std::error::Error>> {
+        Ok(None)
+    }
+
+    /// Whether anything has been stored yet.
+    fn exists(&self) -> bool;
+}
+
+/// A task list and when it was saved.
+pub type Saved = (TaskList, DateTime<Local>);
+
+/// Saves a task list on another thread once `interval` has passed, trying again every
+/// interval while that fails, until [`BackgroundSave::finish`] hands the storage back.
+pub struct BackgroundSave {
+    finished: mpsc::Sender<()>,
+    saver: JoinHandle<Finished>,
+}
+
+/// The storage handed back by [`BackgroundSave::finish`], and the outcome of the last
+/// save if one was tried.
+pub type Finished = (Box<dyn Storage>, Option<Result<(), String>>);
+
+impl BackgroundSave {
+    pub fn start(storage: Box<dyn Storage>, tasks: TaskList, interval: Duration) -> Self {
+        let (finished, wait) = mpsc::channel();
+        let saver = thread::spawn(move || {
+            let mut saved = None;
+            while wait.recv_timeout(interval) == Err(RecvTimeoutError::Timeout) {
+                let result = storage.save(&tasks).map_err(|err| err.to_string());
+                let done = result.is_ok();
+                saved = Some(result);
+                if done {
+                    break;
+                }
+            }
+            (storage, saved)
+        });
+        BackgroundSave { finished, saver }
+    }
+
+    /// Stops waiting and returns the storage.
+    pub fn finish(self) -> Finished {
+        let _ = self.finished.send(());
+        self.saver.join().expect("the background save panicked")
+    }
+}
+
+/// Everything `snapshot restore` brings back: the tasks, with the archive, milestones and
+/// sort order, and the templates.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub created: DateTime<Local>,
+    pub tasks: TaskList,
+    #[serde(default)]
+    pub templates: TemplateStore,
+}
+
+/// One journal entry: a command and the tasks it changed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Event {
+    pub at: DateTime<Local>,
+    pub command: String,
+    /// Each task the command added or changed as it was afterwards, or `None` if the
+    /// command removed it.
+    pub changes: BTreeMap<u32, Option<Task>>,
+}
+
+impl Event {
+    /// Redoes the event's changes on `tasks`.
+    pub fn apply(&self, tasks: &mut TaskList) {
+        for (id, change) in &self.changes {
+            let index = tasks.tasks.iter().position(|task| task.id == *id);
+            match (index, change) {
+                (Some(index), Some(task)) => tasks.tasks[index] = task.clone(),
+                (None, Some(task)) => tasks.tasks.push(task.clone()),
+                (Some(index), None) => {
+                    tasks.tasks.remove(index);
+                }
+                (None, None) => {}
+            }
+        }
+    }
+}
+
+/// Replaces `path` with `contents` without ever leaving it half written: the contents go
+/// to a temporary file in the same directory, are flushed to disk and then renamed over
+/// `path`. Missing directories are created.
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    write_atomic_with(path, |file| file.write_all(contents.as_ref()))
+}
+
+/// Like [`write_atomic`], with the contents written to the temporary file by `write`.
+pub fn write_atomic_with(
+    path: &Path,
+    write: impl FnOnce(&mut fs::File) -> io::Result<()>,
+) -> io::Result<()> {
+    let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty());
+    if let Some(dir) = dir {
+        fs::create_dir_all(dir)?;
+    }
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::other(format!("{} is not a file", path.display())))?;
+    let temp = path.with_file_name(format!(".{}.{}.tmp", name.to_string_lossy(), process::id()));
+    let written = fs::File::create(&temp).and_then(|mut file| {
+        write(&mut file)?;
+        file.sync_all()
+    });
+    if let Err(err) = written.and_then(|_| fs::rename(&temp, path)) {
+        let _ = fs::remove_file(&temp);
+        return Err(err);
+    }
+    // Make the rename itself durable; directories can't be opened for this on Windows.
+    if cfg!(unix) {
+        fs::File::open(dir.unwrap_or(Path::new(".")))?.sync_all()?;
+    }
+    Ok(())
+}
+
+/// An exclusive advisory lock on a task file, held until dropped. It is taken on a
+/// `<file>.lock` next to the file so the atomic renames in [`write_atomic`] don't lose it.
+pub struct FileLock {
+    _file: fs::File,
+}
+
+impl FileLock {
+    /// Locks `path`, waiting for another todore to finish when `wait` is set and
+    /// failing straight away otherwise.
+    pub fn acquire(path: &Path, wait: bool) -> io::Result<FileLock> {
+        let mut name = path.as_os_str().to_owned();
+        name.push(".lock");
+        let lock_path = PathBuf::from(name);
+        if let Some(dir) = lock_path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&lock_path)?;
+        if wait {
+            file.lock()?;
+        } else if let Err(err) = file.try_lock() {
+            let holder = fs::read_to_string(&lock_path).unwrap_or_default();
+            return Err(match err {
+                fs::TryLockError::WouldBlock => io::Error::other(format!(
+                    "{} is in use by another todore (process {}); use --wait to wait for it \
+                     or --no-lock to go ahead anyway",
+                    path.display(),
+                    holder.trim()
+                )),
+                fs::TryLockError::Error(err) => err,
+            });
+        }
+        file.set_len(0)?;
+        write!(file, "{}", process::id())?;
+        Ok(FileLock { _file: file })
+    }
+}
+
+/// How a task file is compressed: chosen by a `.gz` or `.zst` extension when saving and
+/// recognised by magic bytes when loading.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    fn of_path(path: &Path) -> Compression {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("gz") => Compression::Gzip,
+            Some("zst") => Compression::Zstd,
+            _ => Compression::None,
+        }
+    }
+
+    fn detect(data: &[u8]) -> Compression {
+        if data.starts_with(&[0x1f, 0x8b]) {
+            Compression::Gzip
+        } else if data.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Compression::Zstd
+        } else {
+            Compression::None
+        }
+    }
+
+    fn compress(self, data: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Compression::None => Ok(data.to_vec()),
+            Compression::Gzip => {
+                let mut encoder = GzEncoder::new(vec![], flate2::Compression::default());
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+            Compression::Zstd => zstd::encode_all(data, 0),
+        }
+    }
+
+    fn decompress(self, data: Vec<u8>) -> io::Result<Vec<u8>> {
+        match self {
+            Compression::None => Ok(data),
+            Compression::Gzip => {
+                let mut out = vec![];
+                GzDecoder::new(&data[..]).read_to_end(&mut out)?;
+                Ok(out)
+            }
+            Compression::Zstd => zstd::decode_all(&data[..]),
+        }
+    }
+}
+
+/// A single file holding every task, as YAML or TOML when its extension says so and as
+/// JSON otherwise, e.g. `tasks.yaml.gz`. With a [`Cipher`] the file, journal and
+/// snapshots are encrypted.
+pub struct FileStorage {
+    path: PathBuf,
+    format: Format,
+    /// Set from the extension and updated when a compressed file is read, so saves keep
+    /// a file compressed whatever it is called.
+    compression: Cell<Compression>,
+    cipher: Option<Cipher>,
+}
+
+impl FileStorage {
+    /// `suffix` appended to the task file's name, for the files kept alongside it.
+    fn sibling(&self, suffix: &str) -> PathBuf {
+        let mut name = self.path.as_os_str().to_owned();
+        name.push(suffix);
+        PathBuf::from(name)
+    }
+
+    /// The journal kept next to the task file, one JSON [`Event`] per line.
+    fn journal(&self) -> PathBuf {
+        self.sibling(".journal")
+    }
+
+    /// Where snapshots are kept: a directory next to the task file, one JSON file each.
+    fn snapshot_dir(&self) -> PathBuf {
+        self.sibling(".snapshots")
+    }
+
+    /// The checksum of what was last saved to `file` and when, as `<sha256> <time>`.
+    fn checksum_file(file: &Path) -> PathBuf {
+        let mut name = file.as_os_str().to_owned();
+        name.push(".sha256");
+        PathBuf::from(name)
+    }
+
+    /// When `file` holds exactly what was saved to it, the time it was saved.
+    fn verified(file: &Path) -> Option<DateTime<Local>> {
+        let data = fs::read(file).ok()?;
+        let checksum = fs::read_to_string(Self::checksum_file(file)).ok()?;
+        let (sum, at) = checksum.trim().split_once(' ')?;
+        (sum == crypto::to_hex(&Sha256::digest(&data)))
+            .then(|| DateTime::parse_from_rfc3339(at).ok())
+            .flatten()
+            .map(|at| at.with_timezone(&Local))
+    }
+
+    fn snapshot_file(&self, name: &str) -> Result<PathBuf, String> {
+        let valid = |c: char| c.is_alphanumeric() || c == '-' || c == '_';
+        if name.is_empty() || !name.chars().all(valid) {
+            return Err(format!(
+                "Invalid snapshot name {}, use letters, digits, - and _",
+                name
+            ));
+        }
+        Ok(self.snapshot_dir().join(format!("{}.json", name)))
+    }
+
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let compression = Compression::of_path(&path);
+        let unpacked = match compression {
+            Compression::None => path.clone(),
+            _ => path.with_extension(""),
+        };
+        let format = Format::of_store(&unpacked);
+        FileStorage {
+            path,
+            format,
+            compression: Cell::new(compression),
+            cipher: None,
+        }
+    }
+
+    /// Encrypts everything written from now on. Files written before are still read.
+    pub fn with_cipher(mut self, cipher: Cipher) -> Self {
+        self.cipher = Some(cipher);
+        self
+    }
+
+    fn read(&self, path: &Path) -> Result<String, Box<dyn std::error::Error>> {
+        self.decode(path, fs::read(path)?)
+    }
+
+    fn decode(&self, path: &Path, mut data: Vec<u8>) -> Result<String, Box<dyn std::error::Error>> {
+        if crypto::is_sealed(&data) {
+            let cipher = self
+                .cipher
+                .as_ref()
+                .ok_or_else(|| format!("{} is encrypted", path.display()))?;
+            data = cipher.open(&data)?;
+        }
+        let compression = Compression::detect(&data);
+        if compression != Compression::None {
+            self.compression.set(compression);
+        }
+        Ok(String::from_utf8(compression.decompress(data)?)?)
+    }
+
+    /// Writes `contents` compressed, then encrypted, as the task file is. Returns the
+    /// bytes written.
+    fn write(&self, path: &Path, contents: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let mut data = self.compression.get().compress(contents.as_bytes())?;
+        if let Some(cipher) = &self.cipher {
+            data = cipher.seal(&data)?;
+        }
+        write_atomic(path, &data)?;
+        Ok(data)
+    }
+}
+
+impl Storage for FileStorage {
+    fn load(&self) -> Result<TaskList, Box<dyn std::error::Error>> {
+        if !self.path.exists() {
+            return Ok(TaskList::new());
+        }
+        self.format.parse(&self.read(&self.path)?)
+    }
+
+    /// Saves `tasks`, first keeping the file being replaced as `<file>.bak` if it is
+    /// intact, and records the checksum of what was written.
+    fn save(&self, tasks: &TaskList) -> Result<(), Box<dyn std::error::Error>> {
+        let content = tasks.export_to_string(self.format.formatter().as_ref())?;
+        let checksum = Self::checksum_file(&self.path);
+        if Self::verified(&self.path).is_some() {
+            let backup = self.sibling(".bak");
+            write_atomic(&backup, fs::read(&self.path)?)?;
+            write_atomic(&Self::checksum_file(&backup), fs::read(&checksum)?)?;
+        }
+        let data = self.write(&self.path, &content)?;
+        let sum = crypto::to_hex(&Sha256::digest(&data));
+        write_atomic(
+            &checksum,
+            format!("{} {}\n", sum, Local::now().to_rfc3339()),
+        )?;
+        Ok(())
+    }
+
+    fn check(&self) -> Result<(), String> {
+        let unverified = Self::checksum_file(&self.path).exists()
+            && self.path.exists()
+            && Self::verified(&self.path).is_none();
+        if unverified {
+            return Err(format!(
+                "{} doesn't match the checksum from its last save",
+                self.path.display()
+            ));
+        }
+        Ok(())
+    }
+
+    fn last_good(&self) -> Result<Option<Saved>, Box<dyn std::error::Error>> {
+        let backup = self.sibling(".bak");
+        let Some(at) = Self::verified(&backup) else {
+            return Ok(None);
+        };
+        let tasks = self
+            .format
+            .parse(&self.decode(&backup, fs::read(&backup)?)?)?;
+        Ok(Some((tasks, at)))
+    }
+
+    fn append_event(&self, event: &Event) -> Result<(), Box<dyn std::error::Error>> {
+        let mut journal = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.journal())?;
+        let line = serde_json::to_string(event)?;
+        match &self.cipher {
+            Some(cipher) => writeln!(
+                journal,
+                "{}",
+                crypto::to_hex(&cipher.seal(line.as_bytes())?)
+            )?,
+            None => writeln!(journal, "{}", line)?,
+        }
+        journal.sync_data()?;
+        Ok(())
+    }
+
+    fn events(&self) -> Result<Vec<Event>, Box<dyn std::error::Error>> {
+        let journal = self.journal();
+        if !journal.exists() {
+            return Ok(vec![]);
+        }
+        let text = fs::read_to_string(&journal)?;
+        let lines: Vec<&str> = text
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .collect();
+        let mut events = vec![];
+        for (number, line) in lines.iter().enumerate() {
+            // Sealed entries are written as hex, plain ones as JSON objects.
+            let parsed = match (crypto::from_hex(line), &self.cipher) {
+                (Some(data), Some(cipher)) if crypto::is_sealed(&data) => cipher
+                    .open(&data)
+                    .and_then(|json| serde_json::from_slice(&json).map_err(|err| err.to_string())),
+                (Some(_), None) => return Err(format!("{} is encrypted", journal.display()).into()),
+                _ => serde_json::from_str(line).map_err(|err| err.to_string()),
+            };
+            match parsed {
+                Ok(event) => events.push(event),
+                // A crash while appending can only cut off the last entry.
+                Err(_) if number + 1 == lines.len() && !text.ends_with('\n') => {}
+                Err(err) => {
+                    return Err(format!("{}:{}: {}", journal.display(), number + 1, err).into());
+                }
+            }
+        }
+        Ok(events)
+    }
+
+    fn save_snapshot(
+        &self,
+        name: &str,
+        snapshot: &Snapshot,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let file = self.snapshot_file(name)?;
+        self.write(&file, &serde_json::to_string_pretty(snapshot)?)?;
+        Ok(())
+    }
+
+    fn load_snapshot(&self, name: &str) -> Result<Snapshot, Box<dyn std::error::Error>> {
+        let file = self.snapshot_file(name)?;
+        if !file.exists() {
+            return Err(format!("No snapshot called {}", name).into());
+        }
+        Ok(serde_json::from_str(&self.read(&file)?)?)
+    }
+
+    fn snapshots(&self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let dir = self.snapshot_dir();
+        if !dir.exists() {
+            return Ok(vec![]);
+        }
+        let mut names = vec![];
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "json")
+                && let Some(name) = path.file_stem()
+            {
+                names.push(name.to_string_lossy().into_owned());
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    fn exists(&self) -> bool {
+        self.path.exists()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Task;
+    use std::env;
+    use std::process;
+
+    #[test]
+    fn test_write_atomic_replaces_file() {
+        let dir = env::temp_dir().join(format!("todore-atomic-{}", process::id()));
+        let path = dir.join("tasks.json");
+        write_atomic(&path, "first").unwrap();
+        write_atomic(&path, "second").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        assert!(write_atomic(&dir.join(".."), "x").is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_file_lock_is_exclusive() {
+        let dir = env::temp_dir().join(format!("todore-lock-{}", process::id()));
+        let path = dir.join("tasks.json");
+        let lock = FileLock::acquire(&path, false).unwrap();
+        let err = FileLock::acquire(&path, false).err().unwrap();
+        assert!(err.to_string().contains("in use by another todore"));
+        drop(lock);
+        assert!(FileLock::acquire(&path, false).is_ok());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_journal_replays_changes() {
+        let dir = env::temp_dir().join(format!("todore-journal-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let storage = FileStorage::new(dir.join("tasks.json"));
+        let event = |command: &str, changes: Vec<(u32, Option<Task>)>| Event {
+            at: Local::now(),
+            command: command.to_string(),
+            changes: changes.into_iter().collect(),
+        };
+        let mut milk = Task::new(0, "Buy milk".to_string());
+        storage
+            .append_event(&event("add Buy milk", vec![(0, Some(milk.clone()))]))
+            .unwrap();
+        let eggs = Task::new(1, "Eggs".to_string());
+        storage
+            .append_event(&event("add Eggs", vec![(1, Some(eggs))]))
+            .unwrap();
+        milk.description = "Buy oat milk".to_string();
+        storage
+            .append_event(&event("edit", vec![(0, Some(milk)), (1, None)]))
+            .unwrap();
+
+        let events = storage.events().unwrap();
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[1].command, "add Eggs");
+        let mut tasks = TaskList::new();
+        for event in &events {
+            event.apply(&mut tasks);
+        }
+        assert_eq!(tasks.tasks.len(), 1);
+        assert_eq!(tasks.tasks[0].description, "Buy oat milk");
+
+        // A torn final line is ignored, anything else is an error.
+        let mut journal = fs::OpenOptions::new()
+            .append(true)
+            .open(storage.journal())
+            .unwrap();
+        write!(journal, "{{\"at\":").unwrap();
+        assert_eq!(storage.events().unwrap().len(), 3);
+        writeln!(journal).unwrap();
+        assert!(storage.events().is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_snapshots() {
+        let dir = env::temp_dir().join(format!("todore-snapshots-{}", process::id()));
+        let storage = FileStorage::new(dir.join("tasks.json"));
+        assert!(storage.snapshots().unwrap().is_empty());
+        let mut tasks = TaskList::new();
+        tasks.add(Task::new(0, "Buy milk".to_string()));
+        let snapshot = Snapshot {
+            created: Local::now(),
+            tasks,
+            templates: TemplateStore::default(),
+        };
+        storage.save_snapshot("before-import", &snapshot).unwrap();
+        storage.save_snapshot("a", &snapshot).unwrap();
+        assert_eq!(storage.snapshots().unwrap(), vec!["a", "before-import"]);
+        let loaded = storage.load_snapshot("before-import").unwrap();
+        assert_eq!(loaded.tasks.tasks[0].description, "Buy milk");
+        assert!(storage.load_snapshot("missing").is_err());
+        assert!(storage.save_snapshot("../escape", &snapshot).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_background_save() {
+        let dir = env::temp_dir().join(format!("todore-background-{}", process::id()));
+        let path = dir.join("tasks.json");
+        let mut tasks = TaskList::new();
+        tasks.add(Task::new(0, "Buy milk".to_string()));
+
+        let background = BackgroundSave::start(
+            Box::new(FileStorage::new(&path)),
+            tasks.clone(),
+            Duration::from_secs(60),
+        );
+        let (storage, saved) = background.finish();
+        assert!(saved.is_none());
+        assert!(!storage.exists());
+
+        let background = BackgroundSave::start(storage, tasks, Duration::from_millis(10));
+        thread::sleep(Duration::from_millis(200));
+        let (storage, saved) = background.finish();
+        assert_eq!(saved, Some(Ok(())));
+        assert_eq!(storage.load().unwrap().tasks[0].description, "Buy milk");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_file_storage_round_trip() {
+        let dir = env::temp_dir().join(format!("todore-storage-{}", process::id()));
+        let mut tasks = TaskList::new();
+        tasks.add(Task::new(3, "Buy milk".to_string()));
+        for name in ["tasks.json", "tasks.yaml"] {
+            let storage = FileStorage::new(dir.join("nested").join(name));
+            assert!(!storage.exists());
+            assert!(storage.load().unwrap().tasks.is_empty());
+            storage.save(&tasks).unwrap();
+            assert_eq!(storage.load().unwrap().tasks[0].description, "Buy milk");
+        }
+        let yaml = fs::read_to_string(dir.join("nested").join("tasks.yaml")).unwrap();
+        assert!(yaml.starts_with("tasks:"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_compressed_file_storage() {
+        let dir = env::temp_dir().join(format!("todore-compressed-{}", process::id()));
+        let mut tasks = TaskList::new();
+        for id in 0..50 {
+            tasks.add(Task::new(id, "Buy milk".to_string()));
+        }
+        FileStorage::new(dir.join("plain.json"))
+            .save(&tasks)
+            .unwrap();
+        let plain = fs::metadata(dir.join("plain.json")).unwrap().len();
+        for (name, magic) in [
+            ("tasks.json.gz", &[0x1f, 0x8b][..]),
+            ("tasks.yaml.zst", &[0x28, 0xb5, 0x2f, 0xfd][..]),
+        ] {
+            let storage = FileStorage::new(dir.join(name));
+            storage.save(&tasks).unwrap();
+            let data = fs::read(dir.join(name)).unwrap();
+            assert!(data.starts_with(magic));
+            assert!((data.len() as u64) < plain / 4);
+            assert_eq!(storage.load().unwrap().tasks.len(), 50);
+        }
+        // A compressed file is recognised, and kept compressed, whatever it is called.
+        fs::rename(dir.join("tasks.json.gz"), dir.join("renamed.json")).unwrap();
+        let storage = FileStorage::new(dir.join("renamed.json"));
+        assert_eq!(storage.load().unwrap().tasks.len(), 50);
+        storage.save(&tasks).unwrap();
+        assert!(
+            fs::read(dir.join("renamed.json"))
+                .unwrap()
+                .starts_with(&[0x1f, 0x8b])
+        );
+        let yaml = FileStorage::new(dir.join("tasks.yaml.zst"));
+        assert_eq!(yaml.format, Format::YAML);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_encrypted_file_storage() {
+        let dir = env::temp_dir().join(format!("todore-encrypted-{}", process::id()));
+        let path = dir.join("tasks.json");
+        let mut tasks = TaskList::new();
+        tasks.add(Task::new(3, "Buy milk".to_string()));
+        // A plain file is read, then sealed by the next save.
+        FileStorage::new(&path).save(&tasks).unwrap();
+        let storage = FileStorage::new(&path).with_cipher(Cipher::new("secret"));
+        assert_eq!(storage.load().unwrap().tasks.len(), 1);
+        storage.save(&tasks).unwrap();
+        let event = Event {
+            at: Local::now(),
+            command: "add Buy milk".to_string(),
+            changes: BTreeMap::from([(3, Some(tasks.tasks[0].clone()))]),
+        };
+        storage.append_event(&event).unwrap();
+
+        let data = fs::read(&path).unwrap();
+        assert!(crypto::is_sealed(&data));
+        assert!(!String::from_utf8_lossy(&data).contains("milk"));
+        assert!(
+            !fs::read_to_string(storage.journal())
+                .unwrap()
+                .contains("milk")
+        );
+        let reopened = FileStorage::new(&path).with_cipher(Cipher::new("secret"));
+        assert_eq!(reopened.load().unwrap().tasks[0].description, "Buy milk");
+        assert_eq!(reopened.events().unwrap()[0].command, "add Buy milk");
+        assert!(FileStorage::new(&path).load().is_err());
+        let wrong = FileStorage::new(&path).with_cipher(Cipher::new("guess"));
+        assert!(wrong.load().is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,257 @@
+use crate::Task;
+use crate::TaskStatus;
+use chrono::Datelike;
+use chrono::Days;
+use chrono::Months;
+use chrono::NaiveDate;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// When a task is due relative to today, as shown by `agenda`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Bucket {
+    Overdue,
+    Today,
+    Tomorrow,
+    /// A day later this week, i.e. two to six days from now.
+    Day(NaiveDate),
+    Later,
+    NoDueDate,
+}
+
+impl Bucket {
+    pub fn of(due: Option<NaiveDate>, today: NaiveDate) -> Bucket {
+        let Some(due) = due else {
+            return Bucket::NoDueDate;
+        };
+        match (due - today).num_days() {
+            ..0 => Bucket::Overdue,
+            0 => Bucket::Today,
+            1 => Bucket::Tomorrow,
+            2..=6 => Bucket::Day(due),
+            _ => Bucket::Later,
+        }
+    }
+}
+
+impl fmt::Display for Bucket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Bucket::Overdue => write!(f, "Overdue"),
+            Bucket::Today => write!(f, "Today"),
+            Bucket::Tomorrow => write!(f, "Tomorrow"),
+            Bucket::Day(date) => write!(f, "{}", date.format("%A %b %-d")),
+            Bucket::Later => write!(f, "Later"),
+            Bucket::NoDueDate => write!(f, "No due date"),
+        }
+    }
+}
+
+/// How far ahead an agenda looks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Span {
+    /// Overdue tasks and those due today.
+    Today,
+    /// Everything due within the next seven days, including overdue tasks.
+    Week,
+    /// Every open task, with or without a due date.
+    All,
+}
+
+impl Span {
+    fn includes(&self, due: Option<NaiveDate>, today: NaiveDate) -> bool {
+        match self {
+            Span::Today => due.is_some_and(|due| due <= today),
+            Span::Week => due.is_some_and(|due| due < today + Days::new(7)),
+            Span::All => true,
+        }
+    }
+}
+
+/// Open tasks within `span` grouped into due date buckets, soonest first. Tasks keep
+/// the order they are given in within a bucket, except that earlier due dates come first.
+pub fn agenda<'a>(
+    tasks: impl IntoIterator<Item = &'a Task>,
+    span: Span,
+    today: NaiveDate,
+) -> Vec<(Bucket, Vec<&'a Task>)> {
+    let mut open: Vec<&Task> = tasks
+        .into_iter()
+        .filter(|task| task.status != TaskStatus::Completed)
+        .filter(|task| span.includes(task.due, today))
+        .collect();
+    open.sort_by_key(|task| (Bucket::of(task.due, today), task.due));
+    let mut buckets: Vec<(Bucket, Vec<&Task>)> = vec![];
+    for task in open {
+        let bucket = Bucket::of(task.due, today);
+        match buckets.last_mut() {
+            Some((last, tasks)) if *last == bucket => tasks.push(task),
+            _ => buckets.push((bucket, vec![task])),
+        }
+    }
+    buckets
+}
+
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+
+/// The first day of the month named by `s`: `2025-03`, a month name in the current
+/// year such as `mar` or `march`, or `next` / `prev` relative to `today`.
+pub fn parse_month(s: &str, today: NaiveDate) -> Result<NaiveDate, String> {
+    let this_month = today.with_day(1).unwrap();
+    let err = || {
+        format!(
+            "Invalid month {}, expected e.g. 2025-03, march, next or prev",
+            s
+        )
+    };
+    match s {
+        "next" => return Ok(this_month + Months::new(1)),
+        "prev" | "last" => return Ok(this_month - Months::new(1)),
+        _ => {}
+    }
+    if let Some((year, month)) = s.split_once('-') {
+        let year = year.parse().map_err(|_| err())?;
+        let month = month.parse().map_err(|_| err())?;
+        return NaiveDate::from_ymd_opt(year, month, 1).ok_or_else(err);
+    }
+    let month = MONTHS
+        .iter()
+        .position(|name| s.get(..3) == Some(*name))
+        .ok_or_else(err)?;
+    Ok(this_month.with_month(month as u32 + 1).unwrap())
+}
+
+/// A month grid starting on Monday. Each day shows how many open tasks are due on it,
+/// e.g. `12(3)`, and today is marked with `>`.
+pub fn calendar<'a>(
+    tasks: impl IntoIterator<Item = &'a Task>,
+    month: NaiveDate,
+    today: NaiveDate,
+) -> String {
+    let first = month.with_day(1).unwrap();
+    let next = first + Months::new(1);
+    let mut due: BTreeMap<NaiveDate, usize> = BTreeMap::new();
+    for task in tasks {
+        if let Some(date) = task.due.filter(|date| (first..next).contains(date))
+            && task.status != TaskStatus::Completed
+        {
+            *due.entry(date).or_default() += 1;
+        }
+    }
+    let mut out = format!("{}\n", first.format("%B %Y"));
+    out.push_str(" Mo     Tu     We     Th     Fr     Sa     Su\n");
+    let mut cells = vec!["      ".to_string(); first.weekday().num_days_from_monday() as usize];
+    for date in first.iter_days().take_while(|date| *date < next) {
+        let marker = if date == today { '>' } else { ' ' };
+        let count = match due.get(&date) {
+            Some(&n) if n > 9 => "(+)".to_string(),
+            Some(n) => format!("({})", n),
+            None => "   ".to_string(),
+        };
+        cells.push(format!("{}{:>2}{}", marker, date.day(), count));
+    }
+    for week in cells.chunks(7) {
+        out.push_str(week.join(" ").trim_end());
+        out.push('\n');
+    }
+    let total: usize = due.values().sum();
+    out.push_str(&format!("{} open task(s) due this month", total));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 3, day).unwrap()
+    }
+
+    #[test]
+    fn test_bucket_of() {
+        let today = date(10);
+        assert_eq!(Bucket::of(Some(date(1)), today), Bucket::Overdue);
+        assert_eq!(Bucket::of(Some(date(10)), today), Bucket::Today);
+        assert_eq!(Bucket::of(Some(date(11)), today), Bucket::Tomorrow);
+        assert_eq!(Bucket::of(Some(date(16)), today), Bucket::Day(date(16)));
+        assert_eq!(Bucket::of(Some(date(17)), today), Bucket::Later);
+        assert_eq!(Bucket::of(None, today), Bucket::NoDueDate);
+        assert_eq!(Bucket::Day(date(12)).to_string(), "Wednesday Mar 12");
+    }
+
+    #[test]
+    fn test_parse_month() {
+        let today = date(10);
+        assert_eq!(
+            parse_month("2024-12", today),
+            Ok(NaiveDate::from_ymd_opt(2024, 12, 1).unwrap())
+        );
+        assert_eq!(parse_month("march", today), Ok(date(1)));
+        assert_eq!(parse_month("sept", today).map(|d| d.month()), Ok(9));
+        assert_eq!(parse_month("next", today).map(|d| d.month()), Ok(4));
+        assert!(parse_month("2024-13", today).is_err());
+        assert!(parse_month("ma", today).is_err());
+    }
+
+    #[test]
+    fn test_calendar_grid() {
+        let mut tasks = vec![];
+        for (id, day) in [(1, 3), (2, 3), (3, 31)] {
+            let mut task = Task::new(id, "Due".to_string());
+            task.due = Some(date(day));
+            tasks.push(task);
+        }
+        let calendar = calendar(&tasks, date(15), date(10));
+        let lines: Vec<&str> = calendar.lines().collect();
+        assert_eq!(lines[0], "March 2025");
+        assert_eq!(lines[2], "                                     1      2");
+        assert_eq!(lines[3], "  3(2)   4      5      6      7      8      9");
+        assert_eq!(lines[4], ">10     11     12     13     14     15     16");
+        assert_eq!(lines[7], " 31(1)");
+        assert_eq!(lines[8], "3 open task(s) due this month");
+    }
+
+    #[test]
+    fn test_agenda_groups_open_tasks() {
+        let today = date(10);
+        let due = |id, day: Option<u32>| {
+            let mut task = Task::new(id, format!("Task {}", id));
+            task.due = day.map(date);
+            task
+        };
+        let mut done = due(6, Some(10));
+        done.status = TaskStatus::Completed;
+        let tasks = vec![
+            due(1, Some(20)),
+            due(2, None),
+            due(3, Some(12)),
+            due(4, Some(5)),
+            due(5, Some(10)),
+            done,
+            due(7, Some(3)),
+        ];
+        let ids = |span| {
+            agenda(&tasks, span, today)
+                .into_iter()
+                .map(|(bucket, tasks)| (bucket, tasks.iter().map(|task| task.id).collect()))
+                .collect::<Vec<(Bucket, Vec<u32>)>>()
+        };
+        assert_eq!(
+            ids(Span::All),
+            vec![
+                (Bucket::Overdue, vec![7, 4]),
+                (Bucket::Today, vec![5]),
+                (Bucket::Day(date(12)), vec![3]),
+                (Bucket::Later, vec![1]),
+                (Bucket::NoDueDate, vec![2]),
+            ]
+        );
+        assert_eq!(ids(Span::Week).len(), 3);
+        assert_eq!(
+            ids(Span::Today),
+            vec![(Bucket::Overdue, vec![7, 4]), (Bucket::Today, vec![5])]
+        );
+    }
+}
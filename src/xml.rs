@@ -0,0 +1,148 @@
+//! XML export, for tools that take XML. The document has this structure:
+//!
+//! ```xml
+//! <xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+//!   <xs:element name="tasks">
+//!     <xs:complexType>
+//!       <xs:sequence>
+//!         <xs:element name="task" minOccurs="0" maxOccurs="unbounded">
+//!           <xs:complexType>
+//!             <xs:sequence>
+//!               <xs:element name="description" type="xs:string"/>
+//!               <xs:element name="due" type="xs:date" minOccurs="0"/>
+//!               <xs:element name="scheduled" type="xs:date" minOccurs="0"/>
+//!               <xs:element name="priority" minOccurs="0">
+//!                 <!-- low, medium or high -->
+//!               </xs:element>
+//!               <xs:element name="project" type="xs:string" minOccurs="0"/>
+//!               <xs:element name="context" type="xs:string" minOccurs="0"/>
+//!               <xs:element name="sprint" type="xs:string" minOccurs="0"/>
+//!               <xs:element name="points" type="xs:nonNegativeInteger" minOccurs="0"/>
+//!               <xs:element name="url" type="xs:anyURI" minOccurs="0"/>
+//!               <xs:element name="notes" type="xs:string" minOccurs="0"/>
+//!               <xs:element name="tags" minOccurs="0">
+//!                 <xs:complexType>
+//!                   <xs:sequence>
+//!                     <xs:element name="tag" type="xs:string" maxOccurs="unbounded"/>
+//!                   </xs:sequence>
+//!                 </xs:complexType>
+//!               </xs:element>
+//!             </xs:sequence>
+//!             <xs:attribute name="id" type="xs:nonNegativeInteger" use="required"/>
+//!             <!-- not-started, in-progress, waiting or completed -->
+//!             <xs:attribute name="status" type="xs:string" use="required"/>
+//!             <xs:attribute name="parent" type="xs:nonNegativeInteger"/>
+//!             <xs:attribute name="created" type="xs:dateTime"/>
+//!             <xs:attribute name="completed" type="xs:dateTime"/>
+//!           </xs:complexType>
+//!         </xs:element>
+//!       </xs:sequence>
+//!     </xs:complexType>
+//!   </xs:element>
+//! </xs:schema>
+//! ```
+
+use crate::Formatter;
+use crate::TaskList;
+use crate::TaskStatus;
+
+pub struct XmlFormatter;
+
+impl Formatter for XmlFormatter {
+    fn format(&self, tasks: &TaskList) -> Result<String, Box<dyn std::error::Error>> {
+        let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<tasks>\n");
+        for task in tasks.ordered() {
+            let status = match task.status {
+                TaskStatus::NotStarted => "not-started",
+                TaskStatus::InProgress => "in-progress",
+                TaskStatus::Waiting => "waiting",
+                TaskStatus::Completed => "completed",
+            };
+            out.push_str(&format!("  <task id=\"{}\" status=\"{}\"", task.id, status));
+            if let Some(parent) = task.parent {
+                out.push_str(&format!(" parent=\"{}\"", parent));
+            }
+            for (name, at) in [
+                ("created", task.created_at),
+                ("completed", task.completed_at),
+            ] {
+                if let Some(at) = at {
+                    out.push_str(&format!(" {}=\"{}\"", name, at.to_rfc3339()));
+                }
+            }
+            out.push_str(">\n");
+            let elements = [
+                ("description", Some(task.description.clone())),
+                ("due", task.due.map(|due| due.to_string())),
+                ("scheduled", task.scheduled.map(|date| date.to_string())),
+                ("priority", task.priority.map(|p| p.to_string())),
+                ("project", task.project.clone()),
+                ("context", task.context.clone()),
+                ("sprint", task.sprint.clone()),
+                ("points", task.points.map(|points| points.to_string())),
+                ("url", task.url.clone()),
+                ("notes", task.notes.clone()),
+            ];
+            for (name, value) in elements {
+                if let Some(value) = value {
+                    out.push_str(&format!("    <{0}>{1}</{0}>\n", name, escape(&value)));
+                }
+            }
+            if !task.tags.is_empty() {
+                out.push_str("    <tags>\n");
+                for tag in &task.tags {
+                    out.push_str(&format!("      <tag>{}</tag>\n", escape(tag)));
+                }
+                out.push_str("    </tags>\n");
+            }
+            out.push_str("  </task>\n");
+        }
+        out.push_str("</tasks>\n");
+        Ok(out)
+    }
+}
+
+/// `text` with the characters XML gives meaning to replaced by entities.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Priority;
+    use crate::Task;
+
+    #[test]
+    fn test_xml_formatter() {
+        let mut list = TaskList::new();
+        list.add(Task::new(0, "Plan <launch> & party".to_string()));
+        list.add(Task::new(1, "Book venue".to_string()));
+        list.update_parent(1, Some(0)).unwrap();
+        list.update_priority(1, Some(Priority::High)).unwrap();
+        list.update_tags(1, vec!["o'clock".to_string(), "events".to_string()])
+            .unwrap();
+        list.tasks[0].created_at = None;
+        list.tasks[1].created_at = None;
+        assert_eq!(
+            XmlFormatter.format(&list).unwrap(),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<tasks>\n\
+             \x20 <task id=\"0\" status=\"not-started\">\n\
+             \x20   <description>Plan &lt;launch&gt; &amp; party</description>\n\
+             \x20 </task>\n\
+             \x20 <task id=\"1\" status=\"not-started\" parent=\"0\">\n\
+             \x20   <description>Book venue</description>\n\
+             \x20   <priority>high</priority>\n\
+             \x20   <tags>\n\
+             \x20     <tag>o&apos;clock</tag>\n\
+             \x20     <tag>events</tag>\n\
+             \x20   </tags>\n\
+             \x20 </task>\n\
+             </tasks>\n"
+        );
+    }
+}